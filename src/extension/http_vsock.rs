@@ -1,4 +1,9 @@
-use std::{future::Future, path::PathBuf, sync::Arc};
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+};
 
 use bytes::Bytes;
 use futures_util::lock::Mutex;
@@ -11,7 +16,10 @@ use crate::{
     process_spawner::ProcessSpawner,
     runtime::{Runtime, util::RuntimeHyperExecutor},
     vm::Vm,
-    vmm::executor::VmmExecutor,
+    vmm::{
+        executor::VmmExecutor,
+        process::{HyperResponseExt, LimitedResponseBodyError},
+    },
 };
 
 /// An error that can be emitted by the HTTP-over-vsock extension.
@@ -52,6 +60,8 @@ pub enum VmVsockHttpClientError {
     /// pool. This is internally either a [hyper::Error] or an [hyper_util::client::legacy::Error],
     /// but more variants may be added as the internal implementation changes, thus the boxed opaque type.
     RequestError(Box<dyn std::error::Error + Send + Sync>),
+    /// Reading the body of a response with a limited size failed according to a [LimitedResponseBodyError].
+    ResponseBodyError(LimitedResponseBodyError),
 }
 
 impl std::error::Error for VmVsockHttpClientError {}
@@ -66,12 +76,16 @@ impl std::fmt::Display for VmVsockHttpClientError {
                 f,
                 "Sending a request to the vsock device or establishing a connection to it failed: {err}"
             ),
+            VmVsockHttpClientError::ResponseBodyError(err) => {
+                write!(f, "Reading the body of a response from the vsock device failed: {err}")
+            }
         }
     }
 }
 
 /// A managed HTTP client to a vsock application inside a VM, backed by either a [hyper_util]
-/// HTTP connection pool or a singular [hyper] HTTP connection. This client is cloneable cheaply
+/// HTTP connection pool, a singular [hyper] HTTP connection or a singular lazily established and
+/// reestablished [hyper] HTTP connection. This client is cloneable cheaply
 /// when using a connection pool, but, when using a single connection, cloning will introduce
 /// locking contention, as only one clone will be able to make a request at time, while others
 /// wait for the internal [Mutex] holding the connection to unlock. To avoid this issue, using
@@ -88,6 +102,25 @@ enum VmVsockHttpClientInner<B: hyper_client_sockets::Backend + Send + Sync + 'st
         socket_path: PathBuf,
         guest_port: u32,
     },
+    LazyConnection {
+        send_request: Arc<Mutex<Option<SendRequest<Full<Bytes>>>>>,
+        socket_path: PathBuf,
+        guest_port: u32,
+        connection_spawner: ConnectionSpawner,
+    },
+}
+
+type BoxedConnection = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// The spawning of connection tasks onto the runtime is type-erased, since the client is only generic over the
+// socket backend and not over the runtime
+#[derive(Clone)]
+struct ConnectionSpawner(Arc<dyn Fn(BoxedConnection) + Send + Sync>);
+
+impl std::fmt::Debug for ConnectionSpawner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConnectionSpawner").finish_non_exhaustive()
+    }
 }
 
 impl<B: hyper_client_sockets::Backend + Send + Sync + 'static> VmVsockHttpClient<B> {
@@ -126,8 +159,73 @@ impl<B: hyper_client_sockets::Backend + Send + Sync + 'static> VmVsockHttpClient
                     .await
                     .map_err(|err| VmVsockHttpClientError::RequestError(Box::new(err)))
             }
+            VmVsockHttpClientInner::LazyConnection {
+                ref send_request,
+                ref socket_path,
+                guest_port,
+                ref connection_spawner,
+            } => {
+                let mut send_request = send_request.lock().await;
+
+                // the connection is (re-)established when none has been made yet or the previous one was lost
+                if send_request
+                    .as_ref()
+                    .is_none_or(|send_request| send_request.is_closed())
+                {
+                    let (new_send_request, connection) = establish_connection::<B>(socket_path, guest_port)
+                        .await
+                        .map_err(|err| VmVsockHttpClientError::RequestError(Box::new(err)))?;
+                    (connection_spawner.0)(connection);
+                    *send_request = Some(new_send_request);
+                }
+
+                send_request
+                    .as_mut()
+                    .expect("send_request was None after establishing a connection")
+                    .send_request(request)
+                    .await
+                    .map_err(|err| VmVsockHttpClientError::RequestError(Box::new(err)))
+            }
         }
     }
+
+    /// Send a HTTP request via this client like [VmVsockHttpClient::send_request] and read the entire body of the
+    /// response, which is rejected once it exceeds the given maximum amount of bytes. Since the application inside
+    /// the VM is untrusted, this should be preferred over reading the body of a [Response] of [Incoming] unboundedly.
+    pub async fn send_request_with_limited_response(
+        &self,
+        request: Request<Full<Bytes>>,
+        max_response_body_bytes: usize,
+    ) -> Result<Response<Bytes>, VmVsockHttpClientError> {
+        let mut response = self.send_request(request).await?;
+        let body = response
+            .read_body_to_buffer_limited(max_response_body_bytes)
+            .await
+            .map_err(VmVsockHttpClientError::ResponseBodyError)?
+            .freeze();
+        let (parts, _) = response.into_parts();
+        Ok(Response::from_parts(parts, body))
+    }
+}
+
+async fn establish_connection<B: hyper_client_sockets::Backend + Send + Sync + 'static>(
+    socket_path: &Path,
+    guest_port: u32,
+) -> Result<(SendRequest<Full<Bytes>>, BoxedConnection), VmVsockHttpError> {
+    let stream = B::connect_to_firecracker_socket(socket_path, guest_port)
+        .await
+        .map_err(VmVsockHttpError::ConnectionError)?;
+
+    let (send_request, connection) = hyper::client::conn::http1::handshake::<_, Full<Bytes>>(stream)
+        .await
+        .map_err(VmVsockHttpError::HandshakeError)?;
+
+    Ok((
+        send_request,
+        Box::pin(async move {
+            let _ = connection.await;
+        }),
+    ))
 }
 
 /// An extension that allows connecting to guest applications that expose a plain-HTTP (REST or any other) server
@@ -151,6 +249,15 @@ pub trait VmVsockHttp {
         &self,
         guest_port: u32,
     ) -> Result<VmVsockHttpClient<Self::SocketBackend>, VmVsockHttpError>;
+
+    /// Create a [VmVsockHttpClient] backed by a single HTTP-over-vsock connection to the given guest port lazily,
+    /// i.e. not actually establishing the connection until the first request is sent. If the connection is lost,
+    /// it is reestablished when the next request is sent, which makes this client tolerant of the guest application
+    /// not having bound to its vsock port yet at the time of the client's creation.
+    fn connect_lazily_to_http_over_vsock(
+        &self,
+        guest_port: u32,
+    ) -> Result<VmVsockHttpClient<Self::SocketBackend>, VmVsockHttpError>;
 }
 
 impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmVsockHttp for Vm<E, S, R> {
@@ -169,16 +276,7 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmVsockHttp for Vm<E, S, R>
             .uds
             .get_effective_path()
             .ok_or(VmVsockHttpError::VsockResourceUninitialized)?;
-        let stream = <R::SocketBackend as hyper_client_sockets::Backend>::connect_to_firecracker_socket(
-            &socket_path,
-            guest_port,
-        )
-        .await
-        .map_err(VmVsockHttpError::ConnectionError)?;
-
-        let (send_request, connection) = hyper::client::conn::http1::handshake::<_, Full<Bytes>>(stream)
-            .await
-            .map_err(VmVsockHttpError::HandshakeError)?;
+        let (send_request, connection) = establish_connection::<R::SocketBackend>(socket_path, guest_port).await?;
         self.vmm_process.resource_system.runtime.spawn_task(connection);
 
         Ok(VmVsockHttpClient(VmVsockHttpClientInner::Connection(Arc::new(
@@ -211,4 +309,30 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmVsockHttp for Vm<E, S, R>
             guest_port,
         }))
     }
+
+    fn connect_lazily_to_http_over_vsock(
+        &self,
+        guest_port: u32,
+    ) -> Result<VmVsockHttpClient<R::SocketBackend>, VmVsockHttpError> {
+        let socket_path = self
+            .get_configuration()
+            .get_data()
+            .vsock_device
+            .as_ref()
+            .ok_or(VmVsockHttpError::VsockNotConfigured)?
+            .uds
+            .get_effective_path()
+            .ok_or(VmVsockHttpError::VsockResourceUninitialized)?
+            .to_owned();
+        let runtime = self.vmm_process.resource_system.runtime.clone();
+
+        Ok(VmVsockHttpClient(VmVsockHttpClientInner::LazyConnection {
+            send_request: Arc::new(Mutex::new(None)),
+            socket_path,
+            guest_port,
+            connection_spawner: ConnectionSpawner(Arc::new(move |connection| {
+                runtime.spawn_task(connection);
+            })),
+        }))
+    }
 }
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use futures_channel::mpsc;
 use futures_util::{AsyncBufReadExt, SinkExt, StreamExt, io::BufReader};
@@ -32,6 +32,65 @@ pub struct Metrics {
     pub rtc: Option<RtcMetrics>,
 }
 
+// Metric groups that hold point-in-time values rather than values accumulated since the previous flush, alongside
+// the fields of a MetricsAggregate
+const POINT_IN_TIME_METRIC_GROUPS: [&str; 2] = ["api_server", "latencies_us"];
+const POINT_IN_TIME_METRIC_FIELDS: [&str; 3] = ["min_us", "max_us", "sum_us"];
+
+impl Metrics {
+    /// Render these [Metrics] in the Prometheus text exposition format, with every metric being named after its
+    /// path in the [Metrics] joined by underscores and preceded by the given prefix (if it isn't empty), such as
+    /// "firecracker_block_read_bytes" for the prefix "firecracker".
+    ///
+    /// All metrics are exposed as gauges, since Firecracker resets its counters on every flush, meaning that every
+    /// metric other than latencies, process startup times and aggregate values only counts the events that occurred
+    /// since the previous flush and thus doesn't increase monotonically like a Prometheus counter. The UTC timestamp
+    /// of the [Metrics] isn't rendered, since Prometheus assigns its own timestamps on scraping.
+    pub fn to_prometheus(&self, prefix: &str) -> String {
+        let mut output = String::new();
+
+        if let Ok(serde_json::Value::Object(groups)) = serde_json::to_value(self) {
+            for (group_name, value) in groups {
+                if group_name != "utc_timestamp_ms" {
+                    write_prometheus_metrics(&mut output, prefix, &group_name, &value);
+                }
+            }
+        }
+
+        output
+    }
+}
+
+fn write_prometheus_metrics(output: &mut String, prefix: &str, path: &str, value: &serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (field_name, value) in fields {
+                write_prometheus_metrics(output, prefix, &format!("{path}.{field_name}"), value);
+            }
+        }
+        serde_json::Value::Number(number) => {
+            let group_name = path.split('.').next().unwrap_or_default();
+            let field_name = path.rsplit('.').next().unwrap_or_default();
+            let is_point_in_time =
+                POINT_IN_TIME_METRIC_GROUPS.contains(&group_name) || POINT_IN_TIME_METRIC_FIELDS.contains(&field_name);
+            let help_suffix = match is_point_in_time {
+                true => "",
+                false => " since the previous flush",
+            };
+            let name = match prefix.is_empty() {
+                true => path.replace('.', "_"),
+                false => format!("{prefix}_{}", path.replace('.', "_")),
+            };
+
+            output.push_str(&format!("# HELP {name} The Firecracker metric {path}{help_suffix}.\n"));
+            output.push_str(&format!("# TYPE {name} gauge\n"));
+            output.push_str(&format!("{name} {number}\n"));
+        }
+        // absent optional metric groups are serialized as null and skipped
+        _ => {}
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct ApiServerMetrics {
     pub process_startup_time_us: u64,
@@ -327,13 +386,62 @@ pub struct MetricsTask<R: Runtime> {
     pub receiver: mpsc::Receiver<Metrics>,
 }
 
+/// The mode in which a metrics task reads the metrics file/pipe, which should match how the metrics are written to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MetricsReadMode {
+    /// Every flush appends a new line to the metrics file/pipe, so lines are streamed until the end of the file is
+    /// reached. This is the case for FIFO pipes.
+    Append,
+    /// Every flush truncates and rewrites the metrics file, so the file is re-read at the given poll interval and its
+    /// last line is only sent once it has changed and is fully written. This is the case for plain-text files.
+    Overwrite {
+        /// The interval at which the metrics file is re-read.
+        poll_interval: Duration,
+    },
+}
+
 /// Spawn a dedicated async task that gathers Firecracker's metrics from the given metrics path with an
 /// asynchronous [mpsc] channel limited by the provided upper bound (buffer), using the provided [Runtime].
+/// The metrics are read in [MetricsReadMode::Append].
 pub fn spawn_metrics_task<R: Runtime, P: Into<PathBuf>>(metrics_path: P, buffer: usize, runtime: R) -> MetricsTask<R> {
+    spawn_metrics_task_with_read_mode(metrics_path, buffer, MetricsReadMode::Append, runtime)
+}
+
+/// Spawn a dedicated async task that gathers Firecracker's metrics from the given metrics path in the given
+/// [MetricsReadMode], otherwise being identical to [spawn_metrics_task].
+pub fn spawn_metrics_task_with_read_mode<R: Runtime, P: Into<PathBuf>>(
+    metrics_path: P,
+    buffer: usize,
+    read_mode: MetricsReadMode,
+    runtime: R,
+) -> MetricsTask<R> {
     let (mut sender, receiver) = mpsc::channel(buffer);
     let metrics_path = metrics_path.into();
 
     let task = runtime.clone().spawn_task(async move {
+        if let MetricsReadMode::Overwrite { poll_interval } = read_mode {
+            let mut last_line = String::new();
+
+            while !sender.is_closed() {
+                let content = runtime
+                    .fs_read_to_string(&metrics_path)
+                    .await
+                    .map_err(MetricsTaskError::FilesystemError)?;
+
+                if let Some(line) = content.lines().last().filter(|line| *line != last_line) {
+                    // a failure to deserialize means that the file is still being rewritten, so it's retried later
+                    if let Ok(metrics_entry) = serde_json::from_str::<Metrics>(line) {
+                        last_line = line.to_owned();
+                        sender.send(metrics_entry).await.map_err(MetricsTaskError::SendError)?;
+                    }
+                }
+
+                runtime.sleep(poll_interval).await;
+            }
+
+            return Ok(());
+        }
+
         let mut buf_reader = BufReader::new(
             runtime
                 .fs_open_file_for_read(&metrics_path)
@@ -4,13 +4,29 @@ use std::{
     process::{ExitStatus, Output},
 };
 
-use crate::{runtime::Runtime, vmm::installation::VmmInstallation};
+use futures_util::AsyncReadExt;
+
+use crate::{
+    process_spawner::ProcessSpawner,
+    runtime::{Runtime, RuntimeChild},
+    vmm::installation::VmmInstallation,
+};
 
 /// An extension that provides bindings to functionality exposed by Firecracker's "snapshot-editor" binary.
 /// Internally this performs sanity checks and then spawns and awaits a "snapshot-editor" process.
 pub trait SnapshotEditorExt {
-    /// Get a [SnapshotEditor] binding that is bound to this [VmmInstallation]'s lifetime.
+    /// Get a [SnapshotEditor] binding that is bound to this [VmmInstallation]'s lifetime and runs the
+    /// "snapshot-editor" process directly via the given [Runtime].
     fn snapshot_editor<R: Runtime>(&self, runtime: R) -> SnapshotEditor<'_, R>;
+
+    /// Get a [SnapshotEditor] binding that is bound to this [VmmInstallation]'s lifetime and spawns the
+    /// "snapshot-editor" process via the given [ProcessSpawner]. This is needed for editing the snapshot files of
+    /// jailed VMs, which are owned by the jail's UID and GID and thus may be inaccessible to the control process.
+    fn snapshot_editor_with_spawner<S: ProcessSpawner, R: Runtime>(
+        &self,
+        process_spawner: S,
+        runtime: R,
+    ) -> SnapshotEditor<'_, R, S>;
 }
 
 impl SnapshotEditorExt for VmmInstallation {
@@ -18,15 +34,47 @@ impl SnapshotEditorExt for VmmInstallation {
         SnapshotEditor {
             path: self.get_snapshot_editor_path(),
             runtime,
+            process_spawner: None,
+        }
+    }
+
+    fn snapshot_editor_with_spawner<S: ProcessSpawner, R: Runtime>(
+        &self,
+        process_spawner: S,
+        runtime: R,
+    ) -> SnapshotEditor<'_, R, S> {
+        SnapshotEditor {
+            path: self.get_snapshot_editor_path(),
+            runtime,
+            process_spawner: Some(process_spawner),
         }
     }
 }
 
-/// A struct exposing bindings to a "snapshot-editor" binary of this [VmmInstallation].
+/// A struct exposing bindings to a "snapshot-editor" binary of this [VmmInstallation]. The "snapshot-editor"
+/// process is spawned via the [ProcessSpawner] S if one was provided, or directly via the [Runtime] otherwise.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct SnapshotEditor<'p, R: Runtime> {
+pub struct SnapshotEditor<'p, R: Runtime, S: ProcessSpawner = NoProcessSpawner> {
     path: &'p Path,
     runtime: R,
+    process_spawner: Option<S>,
+}
+
+/// The uninhabited [ProcessSpawner] of a [SnapshotEditor] that was created without one, meaning that it runs the
+/// "snapshot-editor" process directly via its [Runtime]. Since it can't be constructed, it never spawns anything.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NoProcessSpawner {}
+
+impl ProcessSpawner for NoProcessSpawner {
+    async fn spawn<R: Runtime>(
+        &self,
+        _binary_path: &Path,
+        _arguments: &[OsString],
+        _disable_pipes: bool,
+        _runtime: &R,
+    ) -> Result<R::Child, std::io::Error> {
+        match *self {}
+    }
 }
 
 /// An error that can be emitted by a "snapshot-editor" invocation.
@@ -58,7 +106,7 @@ impl std::fmt::Display for SnapshotEditorError {
     }
 }
 
-impl<'p, R: Runtime> SnapshotEditor<'p, R> {
+impl<'p, R: Runtime, S: ProcessSpawner> SnapshotEditor<'p, R, S> {
     /// Rebase base_memory_path onto diff_memory_path.
     pub async fn rebase_memory<P: AsRef<Path> + Send, Q: AsRef<Path> + Send>(
         &self,
@@ -143,16 +191,48 @@ impl<'p, R: Runtime> SnapshotEditor<'p, R> {
     }
 
     async fn run(&self, args: &[&str]) -> Result<Output, SnapshotEditorError> {
-        let output = self
-            .runtime
-            .run_process(
-                self.path.as_os_str(),
-                args.iter().map(OsString::from).collect::<Vec<_>>().as_slice(),
-                true,
-                false,
-            )
-            .await
-            .map_err(SnapshotEditorError::ProcessRunError)?;
+        let args = args.iter().map(OsString::from).collect::<Vec<_>>();
+        let output = match self.process_spawner {
+            Some(ref process_spawner) => {
+                let mut child = process_spawner
+                    .spawn(self.path, &args, false, &self.runtime)
+                    .await
+                    .map_err(SnapshotEditorError::ProcessRunError)?;
+                let mut stdout_pipe = child.take_stdout();
+                let mut stderr_pipe = child.take_stderr();
+                let mut stdout = Vec::new();
+                let mut stderr = Vec::new();
+
+                let (stdout_result, stderr_result) = futures_util::future::join(
+                    async {
+                        match stdout_pipe {
+                            Some(ref mut pipe) => pipe.read_to_end(&mut stdout).await.map(|_| ()),
+                            None => Ok(()),
+                        }
+                    },
+                    async {
+                        match stderr_pipe {
+                            Some(ref mut pipe) => pipe.read_to_end(&mut stderr).await.map(|_| ()),
+                            None => Ok(()),
+                        }
+                    },
+                )
+                .await;
+                stdout_result.map_err(SnapshotEditorError::ProcessRunError)?;
+                stderr_result.map_err(SnapshotEditorError::ProcessRunError)?;
+
+                Output {
+                    status: child.wait().await.map_err(SnapshotEditorError::ProcessRunError)?,
+                    stdout,
+                    stderr,
+                }
+            }
+            None => self
+                .runtime
+                .run_process(self.path.as_os_str(), &args, true, false)
+                .await
+                .map_err(SnapshotEditorError::ProcessRunError)?,
+        };
 
         if !output.status.success() {
             return Err(SnapshotEditorError::ExitedWithNonZeroStatus(output.status));
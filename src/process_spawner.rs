@@ -1,17 +1,24 @@
-#[cfg(any(feature = "direct-process-spawner", feature = "elevation-process-spawners"))]
+#[cfg(any(
+    feature = "direct-process-spawner",
+    feature = "elevation-process-spawners",
+    feature = "throttling-process-spawner"
+))]
 use std::ffi::OsString;
+#[cfg(any(feature = "elevation-process-spawners", feature = "throttling-process-spawner"))]
+use std::sync::Arc;
+#[cfg(feature = "throttling-process-spawner")]
+use std::{collections::VecDeque, sync::Mutex};
 use std::{ffi::OsStr, future::Future, path::Path};
 #[cfg(feature = "elevation-process-spawners")]
-use std::{
-    path::PathBuf,
-    sync::{Arc, LazyLock},
-};
+use std::{path::PathBuf, sync::LazyLock};
 
 #[cfg(feature = "elevation-process-spawners")]
 use futures_util::AsyncWriteExt;
 
 use crate::runtime::Runtime;
-#[cfg(feature = "elevation-process-spawners")]
+#[cfg(feature = "throttling-process-spawner")]
+use crate::runtime::RuntimeAsyncFd;
+#[cfg(any(feature = "elevation-process-spawners", feature = "throttling-process-spawner"))]
 use crate::runtime::RuntimeChild;
 
 /// A [ProcessSpawner] concerns itself with spawning a rootful or rootless process from the given binary path and arguments.
@@ -33,7 +40,7 @@ pub trait ProcessSpawner: Clone + Send + Sync + 'static {
 }
 
 /// A [ProcessSpawner] that directly invokes the underlying process.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg(feature = "direct-process-spawner")]
 #[cfg_attr(docsrs, doc(cfg(feature = "direct-process-spawner")))]
 pub struct DirectProcessSpawner;
@@ -179,3 +186,167 @@ impl ProcessSpawner for SudoProcessSpawner {
         Ok(child)
     }
 }
+
+/// A [ProcessSpawner] that wraps an inner [ProcessSpawner] and limits the amount of processes spawned through it that
+/// are alive simultaneously, which prevents storms of forks when many VMs are started at once, for example during a
+/// cold start of a fleet. Spawning a process waits until a permit is available, and the permit is only released once
+/// the process has exited, which is detected via a pidfd polled in a background task spawned onto the [Runtime]. If
+/// no pidfd can be allocated for the process, its permit is released right after it has been spawned.
+///
+/// All clones of a [ThrottlingProcessSpawner] share the same permits.
+#[cfg(feature = "throttling-process-spawner")]
+#[cfg_attr(docsrs, doc(cfg(feature = "throttling-process-spawner")))]
+#[derive(Debug, Clone)]
+pub struct ThrottlingProcessSpawner<S: ProcessSpawner>(Arc<ThrottlingProcessSpawnerInner<S>>);
+
+#[cfg(feature = "throttling-process-spawner")]
+#[derive(Debug)]
+struct ThrottlingProcessSpawnerInner<S> {
+    process_spawner: S,
+    permits: Mutex<ThrottlingPermits>,
+}
+
+#[cfg(feature = "throttling-process-spawner")]
+#[derive(Debug)]
+struct ThrottlingPermits {
+    available: usize,
+    waiters: VecDeque<futures_channel::oneshot::Sender<()>>,
+}
+
+#[cfg(feature = "throttling-process-spawner")]
+#[cfg_attr(docsrs, doc(cfg(feature = "throttling-process-spawner")))]
+impl<S: ProcessSpawner> ThrottlingProcessSpawner<S> {
+    /// Create a new [ThrottlingProcessSpawner] that wraps the given [ProcessSpawner] and allows at most the given
+    /// amount of processes spawned through it to be alive simultaneously.
+    pub fn new(process_spawner: S, max_processes: usize) -> Self {
+        Self(Arc::new(ThrottlingProcessSpawnerInner {
+            process_spawner,
+            permits: Mutex::new(ThrottlingPermits {
+                available: max_processes,
+                waiters: VecDeque::new(),
+            }),
+        }))
+    }
+
+    /// Get a shared reference to the inner [ProcessSpawner] of this [ThrottlingProcessSpawner].
+    pub fn get_inner(&self) -> &S {
+        &self.0.process_spawner
+    }
+
+    /// Get the amount of permits that are currently available, i.e. the amount of processes that can be spawned
+    /// without waiting.
+    pub fn get_available_permits(&self) -> usize {
+        self.0.lock_permits().available
+    }
+
+    async fn acquire_permit(&self) -> ThrottlingPermit<S> {
+        let receiver = {
+            let mut permits = self.0.lock_permits();
+
+            if permits.available > 0 {
+                permits.available -= 1;
+                return ThrottlingPermit(self.0.clone());
+            }
+
+            let (sender, receiver) = futures_channel::oneshot::channel();
+            permits.waiters.push_back(sender);
+            receiver
+        };
+
+        let mut pending_permit = PendingThrottlingPermit {
+            inner: self.0.clone(),
+            receiver: Some(receiver),
+        };
+
+        // waiters are only ever removed from the queue by sending a permit to them, so the sender can't be dropped
+        let _ = pending_permit
+            .receiver
+            .as_mut()
+            .expect("Receiver of pending permit was taken")
+            .await;
+        pending_permit.receiver = None;
+        ThrottlingPermit(self.0.clone())
+    }
+}
+
+#[cfg(feature = "throttling-process-spawner")]
+impl<S> ThrottlingProcessSpawnerInner<S> {
+    fn lock_permits(&self) -> std::sync::MutexGuard<'_, ThrottlingPermits> {
+        self.permits.lock().expect("Throttling permits mutex was poisoned")
+    }
+
+    fn release_permit(&self) {
+        let mut permits = self.lock_permits();
+
+        // a waiter whose acquisition was cancelled has dropped its receiver, so the permit is handed to the next one
+        while let Some(sender) = permits.waiters.pop_front() {
+            if sender.send(()).is_ok() {
+                return;
+            }
+        }
+
+        permits.available += 1;
+    }
+}
+
+#[cfg(feature = "throttling-process-spawner")]
+struct ThrottlingPermit<S>(Arc<ThrottlingProcessSpawnerInner<S>>);
+
+#[cfg(feature = "throttling-process-spawner")]
+impl<S> Drop for ThrottlingPermit<S> {
+    fn drop(&mut self) {
+        self.0.release_permit();
+    }
+}
+
+#[cfg(feature = "throttling-process-spawner")]
+struct PendingThrottlingPermit<S> {
+    inner: Arc<ThrottlingProcessSpawnerInner<S>>,
+    receiver: Option<futures_channel::oneshot::Receiver<()>>,
+}
+
+#[cfg(feature = "throttling-process-spawner")]
+impl<S> Drop for PendingThrottlingPermit<S> {
+    fn drop(&mut self) {
+        // if the acquisition was cancelled after a permit had already been sent, that permit must not be lost
+        if let Some(mut receiver) = self.receiver.take() {
+            receiver.close();
+
+            if let Ok(Some(())) = receiver.try_recv() {
+                self.inner.release_permit();
+            }
+        }
+    }
+}
+
+#[cfg(feature = "throttling-process-spawner")]
+#[cfg_attr(docsrs, doc(cfg(feature = "throttling-process-spawner")))]
+impl<S: ProcessSpawner> ProcessSpawner for ThrottlingProcessSpawner<S> {
+    async fn spawn<R: Runtime>(
+        &self,
+        binary_path: &Path,
+        arguments: &[OsString],
+        disable_pipes: bool,
+        runtime: &R,
+    ) -> Result<R::Child, std::io::Error> {
+        let permit = self.acquire_permit().await;
+        let child = self
+            .0
+            .process_spawner
+            .spawn(binary_path, arguments, disable_pipes, runtime)
+            .await?;
+
+        if let Some(pid) = child.id() {
+            if let Ok(async_pidfd) =
+                crate::syscall::pidfd_open(pid as i32).and_then(|pidfd| runtime.create_async_fd(pidfd))
+            {
+                runtime.spawn_task(async move {
+                    let _ = async_pidfd.readable().await;
+                    drop(permit);
+                });
+            }
+        }
+
+        Ok(child)
+    }
+}
@@ -0,0 +1,86 @@
+//! Provides a lightweight [CancellationToken] that is independent of any async runtime, which long-running operations
+//! across fctools, such as starting a VM or synchronizing a resource system, can be aborted early with.
+
+use std::{
+    future::Future,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::{Context, Poll, Waker},
+};
+
+/// A token that can be cancelled once in order to signal that all operations it was passed to should be aborted as
+/// soon as possible. The token can be cloned cheaply, with all clones sharing the same cancellation state, so that a
+/// supervisor can keep one clone and cancel it while another clone is used by the operation.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<CancellationTokenInner>);
+
+#[derive(Debug, Default)]
+struct CancellationTokenInner {
+    is_cancelled: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl CancellationToken {
+    /// Create a new [CancellationToken] that hasn't been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancel this [CancellationToken] and all of its clones, waking up all futures waiting for the cancellation.
+    /// Cancelling an already cancelled [CancellationToken] has no effect.
+    pub fn cancel(&self) {
+        self.0.is_cancelled.store(true, Ordering::Release);
+        let wakers = std::mem::take(&mut *self.0.wakers.lock().expect("Cancellation token mutex was poisoned"));
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Whether this [CancellationToken] or any of its clones has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_cancelled.load(Ordering::Acquire)
+    }
+
+    /// Asynchronously wait until this [CancellationToken] is cancelled.
+    pub async fn cancelled(&self) {
+        std::future::poll_fn(|context| self.poll_cancelled(context)).await
+    }
+
+    /// Run the given future until it completes, returning its output, or until this [CancellationToken] is cancelled,
+    /// returning [None] and dropping the future. The future is never polled if this [CancellationToken] has already
+    /// been cancelled.
+    pub async fn run_until_cancelled<F: Future>(&self, future: F) -> Option<F::Output> {
+        let mut future = std::pin::pin!(future);
+
+        std::future::poll_fn(|context| {
+            if self.poll_cancelled(context).is_ready() {
+                return Poll::Ready(None);
+            }
+
+            future.as_mut().poll(context).map(Some)
+        })
+        .await
+    }
+
+    fn poll_cancelled(&self, context: &mut Context) -> Poll<()> {
+        if self.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        let mut wakers = self.0.wakers.lock().expect("Cancellation token mutex was poisoned");
+
+        // A cancellation between the first check and acquiring the lock would otherwise never wake this waker up
+        if self.is_cancelled() {
+            return Poll::Ready(());
+        }
+
+        if !wakers.iter().any(|waker| waker.will_wake(context.waker())) {
+            wakers.push(context.waker().clone());
+        }
+
+        Poll::Pending
+    }
+}
@@ -5,6 +5,9 @@
 //!
 //! Extra utilities that are used internally by certain layers of fctools and which are helpful for third-party runtime
 //! implementors are available via the optional `runtime-util` feature.
+//!
+//! A runtime-agnostic cancellation token for aborting long-running operations is always available in the
+//! [cancellation] module.
 
 use std::{
     ffi::{OsStr, OsString},
@@ -30,6 +33,8 @@ pub mod smol;
 #[cfg_attr(docsrs, doc(cfg(feature = "runtime-util")))]
 pub mod util;
 
+pub mod cancellation;
+
 /// An async runtime platform used by fctools. Instances of a [Runtime] are highly frequently cloned by fctools,
 /// so the [Clone] implementation is expected to be cheap and fast, meaning that the underlying structure of a [Runtime]
 /// implementation should either be a ZST or an [Arc](std::sync::Arc) of an inner shared type.
@@ -72,6 +77,9 @@ pub trait Runtime: Clone + Send + Sync + 'static {
         F: Future + Send,
         F::Output: Send;
 
+    /// Asynchronously sleep for the given [Duration], which is measured via a monotonic clock.
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send;
+
     /// Check if the given [Path] exists on the filesystem.
     fn fs_exists(&self, path: &Path) -> impl Future<Output = Result<bool, std::io::Error>> + Send;
 
@@ -84,6 +92,14 @@ pub trait Runtime: Clone + Send + Sync + 'static {
     /// Create a file at the given [Path] on the filesystem.
     fn fs_create_file(&self, path: &Path) -> impl Future<Output = Result<(), std::io::Error>> + Send;
 
+    /// Create a file at the given [Path] on the filesystem with the given permission mode (subject to the umask) in a
+    /// single operation, so that no window exists during which the file has different permissions.
+    fn fs_create_file_with_mode(
+        &self,
+        path: &Path,
+        mode: u32,
+    ) -> impl Future<Output = Result<(), std::io::Error>> + Send;
+
     /// Write the provided [String] blob to the given [Path] on the filesystem.
     fn fs_write(&self, path: &Path, content: String) -> impl Future<Output = Result<(), std::io::Error>> + Send;
 
@@ -117,6 +133,33 @@ pub trait Runtime: Clone + Send + Sync + 'static {
         destination_path: &Path,
     ) -> impl Future<Output = Result<(), std::io::Error>> + Send;
 
+    /// Copy the file at the source [Path] on the filesystem to the destination [Path] with O_DIRECT, bypassing the
+    /// page cache, or via a regular copy if the filesystem doesn't support O_DIRECT.
+    fn fs_copy_direct(
+        &self,
+        source_path: &Path,
+        destination_path: &Path,
+    ) -> impl Future<Output = Result<(), std::io::Error>> + Send;
+
+    /// Get the size in bytes of the file at the given [Path] on the filesystem.
+    fn fs_file_size(&self, path: &Path) -> impl Future<Output = Result<u64, std::io::Error>> + Send;
+
+    /// Recursively get the disk space in bytes allocated for the given [Path] and all of its contents on the
+    /// filesystem, without following symlinks and counting hard-linked files only once.
+    fn fs_disk_usage(&self, path: &Path) -> impl Future<Output = Result<u64, std::io::Error>> + Send;
+
+    /// Preallocate disk space for the entire current length of the file at the given [Path] on the filesystem.
+    fn fs_preallocate(&self, path: &Path) -> impl Future<Output = Result<(), std::io::Error>> + Send;
+
+    /// Deduplicate the file at the given [Path] on the filesystem against the reference file at the other given [Path]
+    /// by punching holes for zeroed blocks and sharing blocks identical to the reference file's blocks at the same
+    /// offsets where the filesystem supports it, returning the amount of deduplicated bytes.
+    fn fs_deduplicate(
+        &self,
+        path: &Path,
+        reference_path: &Path,
+    ) -> impl Future<Output = Result<u64, std::io::Error>> + Send;
+
     /// Open the file at the given [Path] on the filesystem in read-only mode, returning an I/O object used for
     /// asynchronously reading its contents.
     fn fs_open_file_for_read(&self, path: &Path) -> impl Future<Output = Result<Self::File, std::io::Error>> + Send;
@@ -190,6 +233,9 @@ pub trait RuntimeChild: Sized + Send + Sync + std::fmt::Debug {
     /// Immediately terminate the execution of this child process.
     fn kill(&mut self) -> Result<(), std::io::Error>;
 
+    /// Get the PID of this child process, or [None] if it is no longer available due to the child having been reaped.
+    fn id(&self) -> Option<u32>;
+
     /// Get the stdout pipe of this child process.
     fn get_stdout(&mut self) -> &mut Option<Self::Stdout>;
 
@@ -13,11 +13,15 @@ use std::{
     time::{Duration, Instant},
 };
 
+use async_fs::unix::OpenOptionsExt;
 use async_io::Timer;
 use async_process::{Child, ChildStderr, ChildStdin, ChildStdout};
 use pin_project_lite::pin_project;
 
-use super::{Runtime, RuntimeAsyncFd, RuntimeChild, RuntimeTask, util::chown_all_blocking};
+use super::{
+    Runtime, RuntimeAsyncFd, RuntimeChild, RuntimeTask,
+    util::{chown_all_blocking, copy_direct_blocking, deduplicate_blocking, disk_usage_blocking, preallocate_blocking},
+};
 use crate::runtime::util::get_stdio_from_piped;
 
 #[derive(Clone)]
@@ -82,6 +86,10 @@ impl Runtime for SmolRuntime {
         }
     }
 
+    async fn sleep(&self, duration: Duration) {
+        Timer::after(duration).await;
+    }
+
     fn fs_exists(&self, path: &Path) -> impl Future<Output = Result<bool, std::io::Error>> + Send {
         let path = path.to_owned();
         blocking::unblock(move || std::fs::exists(&path))
@@ -99,6 +107,17 @@ impl Runtime for SmolRuntime {
         async_fs::File::create(path).await.map(|_| ())
     }
 
+    async fn fs_create_file_with_mode(&self, path: &Path, mode: u32) -> Result<(), std::io::Error> {
+        async_fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(mode)
+            .open(path)
+            .await
+            .map(|_| ())
+    }
+
     fn fs_write(&self, path: &Path, content: String) -> impl Future<Output = Result<(), std::io::Error>> + Send {
         async_fs::write(path, content)
     }
@@ -123,6 +142,40 @@ impl Runtime for SmolRuntime {
         async_fs::copy(source_path, destination_path).await.map(|_| ())
     }
 
+    fn fs_copy_direct(
+        &self,
+        source_path: &Path,
+        destination_path: &Path,
+    ) -> impl Future<Output = Result<(), std::io::Error>> + Send {
+        let source_path = source_path.to_owned();
+        let destination_path = destination_path.to_owned();
+        blocking::unblock(move || copy_direct_blocking(&source_path, &destination_path))
+    }
+
+    async fn fs_file_size(&self, path: &Path) -> Result<u64, std::io::Error> {
+        async_fs::metadata(path).await.map(|metadata| metadata.len())
+    }
+
+    fn fs_disk_usage(&self, path: &Path) -> impl Future<Output = Result<u64, std::io::Error>> + Send {
+        let path = path.to_owned();
+        blocking::unblock(move || disk_usage_blocking(&path))
+    }
+
+    fn fs_preallocate(&self, path: &Path) -> impl Future<Output = Result<(), std::io::Error>> + Send {
+        let path = path.to_owned();
+        blocking::unblock(move || preallocate_blocking(&path))
+    }
+
+    fn fs_deduplicate(
+        &self,
+        path: &Path,
+        reference_path: &Path,
+    ) -> impl Future<Output = Result<u64, std::io::Error>> + Send {
+        let path = path.to_owned();
+        let reference_path = reference_path.to_owned();
+        blocking::unblock(move || deduplicate_blocking(&path, &reference_path))
+    }
+
     fn fs_chown_all(&self, path: &Path, uid: u32, gid: u32) -> impl Future<Output = Result<(), std::io::Error>> + Send {
         let path = path.to_owned();
         blocking::unblock(move || chown_all_blocking(&path, uid, gid))
@@ -278,6 +331,10 @@ impl RuntimeChild for SmolRuntimeChild {
         self.0.kill()
     }
 
+    fn id(&self) -> Option<u32> {
+        Some(self.0.id())
+    }
+
     fn get_stdout(&mut self) -> &mut Option<Self::Stdout> {
         &mut self.0.stdout
     }
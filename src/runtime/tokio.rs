@@ -20,7 +20,10 @@ use tokio_util::compat::{Compat, TokioAsyncReadCompatExt, TokioAsyncWriteCompatE
 
 use super::{
     Runtime, RuntimeAsyncFd, RuntimeChild, RuntimeTask,
-    util::{chown_all_blocking, get_stdio_from_piped},
+    util::{
+        chown_all_blocking, copy_direct_blocking, deduplicate_blocking, disk_usage_blocking, get_stdio_from_piped,
+        preallocate_blocking,
+    },
 };
 
 /// The [Runtime] implementation backed by the [tokio] crate. Since [tokio] heavily utilizes thread-local
@@ -59,6 +62,10 @@ impl Runtime for TokioRuntime {
         tokio::time::timeout(duration, future)
     }
 
+    fn sleep(&self, duration: Duration) -> impl Future<Output = ()> + Send {
+        tokio::time::sleep(duration)
+    }
+
     fn fs_exists(&self, path: &Path) -> impl Future<Output = Result<bool, std::io::Error>> + Send {
         tokio::fs::try_exists(path)
     }
@@ -75,6 +82,17 @@ impl Runtime for TokioRuntime {
         tokio::fs::File::create(path).await.map(|_| ())
     }
 
+    async fn fs_create_file_with_mode(&self, path: &Path, mode: u32) -> Result<(), std::io::Error> {
+        tokio::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(mode)
+            .open(path)
+            .await
+            .map(|_| ())
+    }
+
     fn fs_write(&self, path: &Path, content: String) -> impl Future<Output = Result<(), std::io::Error>> + Send {
         tokio::fs::write(path, content)
     }
@@ -99,6 +117,44 @@ impl Runtime for TokioRuntime {
         tokio::fs::copy(source_path, destination_path).await.map(|_| ())
     }
 
+    async fn fs_copy_direct(&self, source_path: &Path, destination_path: &Path) -> Result<(), std::io::Error> {
+        let source_path = source_path.to_owned();
+        let destination_path = destination_path.to_owned();
+        match tokio::task::spawn_blocking(move || copy_direct_blocking(&source_path, &destination_path)).await {
+            Ok(result) => result,
+            Err(_) => Err(std::io::Error::other("copy_direct_blocking blocking task panicked")),
+        }
+    }
+
+    async fn fs_file_size(&self, path: &Path) -> Result<u64, std::io::Error> {
+        tokio::fs::metadata(path).await.map(|metadata| metadata.len())
+    }
+
+    async fn fs_disk_usage(&self, path: &Path) -> Result<u64, std::io::Error> {
+        let path = path.to_owned();
+        match tokio::task::spawn_blocking(move || disk_usage_blocking(&path)).await {
+            Ok(result) => result,
+            Err(_) => Err(std::io::Error::other("disk_usage_blocking blocking task panicked")),
+        }
+    }
+
+    async fn fs_preallocate(&self, path: &Path) -> Result<(), std::io::Error> {
+        let path = path.to_owned();
+        match tokio::task::spawn_blocking(move || preallocate_blocking(&path)).await {
+            Ok(result) => result,
+            Err(_) => Err(std::io::Error::other("preallocate_blocking blocking task panicked")),
+        }
+    }
+
+    async fn fs_deduplicate(&self, path: &Path, reference_path: &Path) -> Result<u64, std::io::Error> {
+        let path = path.to_owned();
+        let reference_path = reference_path.to_owned();
+        match tokio::task::spawn_blocking(move || deduplicate_blocking(&path, &reference_path)).await {
+            Ok(result) => result,
+            Err(_) => Err(std::io::Error::other("deduplicate_blocking blocking task panicked")),
+        }
+    }
+
     async fn fs_chown_all(&self, path: &Path, uid: u32, gid: u32) -> Result<(), std::io::Error> {
         let path = path.to_owned();
         match tokio::task::spawn_blocking(move || chown_all_blocking(&path, uid, gid)).await {
@@ -229,6 +285,10 @@ impl RuntimeChild for TokioRuntimeChild {
         self.child.start_kill()
     }
 
+    fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
     fn get_stdout(&mut self) -> &mut Option<Self::Stdout> {
         &mut self.stdout
     }
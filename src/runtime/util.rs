@@ -1,6 +1,16 @@
 //! Extra utilities for runtime implementors.
 
-use std::{future::Future, path::Path, process::Stdio};
+use std::{
+    collections::HashSet,
+    future::Future,
+    io::{Read, Write},
+    os::{
+        fd::{AsRawFd, RawFd},
+        unix::fs::MetadataExt,
+    },
+    path::Path,
+    process::Stdio,
+};
 
 use super::Runtime;
 
@@ -22,6 +32,245 @@ pub fn chown_all_blocking(path: &Path, uid: u32, gid: u32) -> Result<(), std::io
     crate::syscall::chown(path, uid, gid)
 }
 
+/// A simple utility that recursively sums up the disk space in bytes allocated for the given [Path] and, if it is a
+/// directory, all of its contents, without following symlinks and counting hard-linked files only once. Like
+/// [chown_all_blocking], this is implemented via blocking [std::fs] operations.
+///
+/// This is used with blocking threads by the Tokio and Smol runtime implementations to implement
+/// [Runtime::fs_disk_usage], and is public for usage by third-party runtimes too.
+pub fn disk_usage_blocking(path: &Path) -> Result<u64, std::io::Error> {
+    fn disk_usage_inner(path: &Path, seen_inodes: &mut HashSet<(u64, u64)>) -> Result<u64, std::io::Error> {
+        let metadata = std::fs::symlink_metadata(path)?;
+        // a hard-linked file occupies its blocks only once, no matter how many paths point to it
+        let mut disk_usage = match seen_inodes.insert((metadata.dev(), metadata.ino())) {
+            true => metadata.blocks() * 512,
+            false => 0,
+        };
+
+        if metadata.is_dir() {
+            for entry in std::fs::read_dir(path)? {
+                disk_usage += disk_usage_inner(entry?.path().as_path(), seen_inodes)?;
+            }
+        }
+
+        Ok(disk_usage)
+    }
+
+    disk_usage_inner(path, &mut HashSet::new())
+}
+
+/// A simple utility that preallocates disk space for the entire current length of the file at the given [Path] via
+/// the fallocate syscall, so that subsequent writes into its holes don't need to allocate blocks. Since allocating a
+/// large file can take a significant amount of time, this should only be called from a blocking thread.
+///
+/// This is used with blocking threads by the Tokio and Smol runtime implementations to implement
+/// [Runtime::fs_preallocate], and is public for usage by third-party runtimes too.
+pub fn preallocate_blocking(path: &Path) -> Result<(), std::io::Error> {
+    let file = std::fs::OpenOptions::new().write(true).open(path)?;
+    let length = file.metadata()?.len();
+    crate::syscall::fallocate(file.as_raw_fd(), length)
+}
+
+// O_DIRECT requires the buffer address, the file offset and the transfer length to be aligned to the logical block
+// size of the underlying device, which is at most the page size in practice
+const DIRECT_IO_ALIGNMENT: usize = 4096;
+const DIRECT_IO_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// A simple utility that copies the file at the source [Path] to the destination [Path] with O_DIRECT, bypassing the
+/// page cache so that copying a large file doesn't evict cached data of other processes. The alignment requirements
+/// of O_DIRECT are handled internally, and a regular copy is performed instead if either file resides on a filesystem
+/// that doesn't support O_DIRECT, such as tmpfs. Like [chown_all_blocking], this is implemented via blocking I/O.
+///
+/// This is used with blocking threads by the Tokio and Smol runtime implementations to implement
+/// [Runtime::fs_copy_direct], and is public for usage by third-party runtimes too.
+pub fn copy_direct_blocking(source_path: &Path, destination_path: &Path) -> Result<(), std::io::Error> {
+    match copy_direct_unchecked(source_path, destination_path) {
+        // EINVAL is returned by open when the filesystem doesn't support O_DIRECT, and by reads or writes when the
+        // filesystem's alignment requirements for O_DIRECT are stricter than expected. The regular copy truncates
+        // anything that has already been written to the destination
+        Err(err) if err.kind() == std::io::ErrorKind::InvalidInput => {
+            std::fs::copy(source_path, destination_path).map(|_| ())
+        }
+        result => result,
+    }
+}
+
+fn copy_direct_unchecked(source_path: &Path, destination_path: &Path) -> Result<(), std::io::Error> {
+    let mut source = crate::syscall::open_direct(source_path, false)?;
+    let mut destination = crate::syscall::open_direct(destination_path, true)?;
+
+    let mut buffer = vec![0; DIRECT_IO_CHUNK_SIZE + DIRECT_IO_ALIGNMENT];
+    let offset = buffer.as_ptr().align_offset(DIRECT_IO_ALIGNMENT);
+    let buffer = &mut buffer[offset..offset + DIRECT_IO_CHUNK_SIZE];
+    let mut length = 0;
+
+    loop {
+        let mut chunk_length = 0;
+
+        // a read that isn't a multiple of the alignment can only end at the end of the file, and reading any further
+        // would pass an unaligned buffer to O_DIRECT, so it's treated as the end of the file
+        while chunk_length < DIRECT_IO_CHUNK_SIZE && chunk_length % DIRECT_IO_ALIGNMENT == 0 {
+            match source.read(&mut buffer[chunk_length..])? {
+                0 => break,
+                read_length => chunk_length += read_length,
+            }
+        }
+
+        if chunk_length == 0 {
+            break;
+        }
+
+        // the last chunk is padded to the alignment, and the padding is truncated away once the copy is done
+        let padded_chunk_length = chunk_length.next_multiple_of(DIRECT_IO_ALIGNMENT);
+        buffer[chunk_length..padded_chunk_length].fill(0);
+        destination.write_all(&buffer[..padded_chunk_length])?;
+        length += chunk_length as u64;
+
+        if chunk_length < DIRECT_IO_CHUNK_SIZE {
+            break;
+        }
+    }
+
+    destination.set_len(length)?;
+    destination.set_permissions(source.metadata()?.permissions())
+}
+
+// Deduplication works at the granularity of guest pages, while reading the files in larger chunks
+const DEDUPLICATION_BLOCK_SIZE: usize = 4096;
+const DEDUPLICATION_CHUNK_SIZE: usize = 1024 * 1024;
+// The kernel caps the length of a single FIDEDUPERANGE call at 16 MiB
+const DEDUPLICATION_MAX_RANGE_LENGTH: u64 = 16 * 1024 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DeduplicationBlock {
+    Zeroed,
+    SameAsReference,
+    Different,
+}
+
+/// A simple utility that deduplicates the file at the given [Path] against the reference file at the other given
+/// [Path] at the granularity of 4 KiB blocks, returning the amount of deduplicated bytes. Blocks filled with zeroes
+/// are deallocated by punching holes into the file, while blocks identical to the block at the same offset in the
+/// reference file are shared with the reference file via the FIDEDUPERANGE ioctl, which the kernel only performs
+/// after verifying that the blocks are identical. If the filesystem doesn't support sharing blocks between files,
+/// only holes are punched. Like [chown_all_blocking], this is implemented via blocking I/O.
+///
+/// This is used with blocking threads by the Tokio and Smol runtime implementations to implement
+/// [Runtime::fs_deduplicate], and is public for usage by third-party runtimes too.
+pub fn deduplicate_blocking(path: &Path, reference_path: &Path) -> Result<u64, std::io::Error> {
+    fn read_chunk(file: &mut std::fs::File, buffer: &mut [u8]) -> Result<usize, std::io::Error> {
+        let mut length = 0;
+
+        while length < buffer.len() {
+            match file.read(&mut buffer[length..])? {
+                0 => break,
+                read_length => length += read_length,
+            }
+        }
+
+        Ok(length)
+    }
+
+    // returns the amount of deduplicated bytes, and stops sharing blocks once the filesystem is found not to support it
+    fn deduplicate_run(
+        fd: RawFd,
+        reference_fd: RawFd,
+        (kind, run_offset, run_length): (DeduplicationBlock, u64, u64),
+        can_share_blocks: &mut bool,
+    ) -> Result<u64, std::io::Error> {
+        match kind {
+            DeduplicationBlock::Zeroed => {
+                crate::syscall::punch_hole(fd, run_offset, run_length)?;
+                Ok(run_length)
+            }
+            DeduplicationBlock::SameAsReference if *can_share_blocks => {
+                match crate::syscall::dedupe_range(reference_fd, run_offset, run_length, fd, run_offset) {
+                    Err(err)
+                        if matches!(
+                            err.kind(),
+                            std::io::ErrorKind::Unsupported
+                                | std::io::ErrorKind::InvalidInput
+                                | std::io::ErrorKind::CrossesDevices
+                        ) =>
+                    {
+                        *can_share_blocks = false;
+                        Ok(0)
+                    }
+                    result => result,
+                }
+            }
+            _ => Ok(0),
+        }
+    }
+
+    let mut file = std::fs::OpenOptions::new().read(true).write(true).open(path)?;
+    let mut reference_file = std::fs::File::open(reference_path)?;
+    let mut buffer = vec![0; DEDUPLICATION_CHUNK_SIZE];
+    let mut reference_buffer = vec![0; DEDUPLICATION_CHUNK_SIZE];
+    let mut can_share_blocks = true;
+    let mut deduplicated_length = 0;
+    let mut offset = 0;
+    // a run of adjacent blocks of the same kind, which is deduplicated at once in order to minimize syscalls
+    let mut run: Option<(DeduplicationBlock, u64, u64)> = None;
+
+    loop {
+        let length = read_chunk(&mut file, &mut buffer)?;
+
+        if length == 0 {
+            break;
+        }
+
+        let reference_length = read_chunk(&mut reference_file, &mut reference_buffer[..length])?;
+
+        for block_start in (0..length).step_by(DEDUPLICATION_BLOCK_SIZE) {
+            let block_end = (block_start + DEDUPLICATION_BLOCK_SIZE).min(length);
+            let block = &buffer[block_start..block_end];
+
+            let kind = if block.iter().all(|byte| *byte == 0) {
+                DeduplicationBlock::Zeroed
+            } else if block_end <= reference_length && block == &reference_buffer[block_start..block_end] {
+                DeduplicationBlock::SameAsReference
+            } else {
+                DeduplicationBlock::Different
+            };
+            let block_length = (block_end - block_start) as u64;
+
+            run = match run {
+                Some((run_kind, run_offset, run_length))
+                    if run_kind == kind && run_length + block_length <= DEDUPLICATION_MAX_RANGE_LENGTH =>
+                {
+                    Some((run_kind, run_offset, run_length + block_length))
+                }
+                previous_run => {
+                    if let Some(previous_run) = previous_run {
+                        deduplicated_length += deduplicate_run(
+                            file.as_raw_fd(),
+                            reference_file.as_raw_fd(),
+                            previous_run,
+                            &mut can_share_blocks,
+                        )?;
+                    }
+
+                    Some((kind, offset + block_start as u64, block_length))
+                }
+            };
+        }
+
+        offset += length as u64;
+
+        if length < DEDUPLICATION_CHUNK_SIZE {
+            break;
+        }
+    }
+
+    if let Some(run) = run {
+        deduplicated_length +=
+            deduplicate_run(file.as_raw_fd(), reference_file.as_raw_fd(), run, &mut can_share_blocks)?;
+    }
+
+    Ok(deduplicated_length)
+}
+
 /// A [hyper::rt::Executor] implementation that is agnostic over any [Runtime] by simply using [Runtime::spawn_task]
 /// internally. Any static [Send] future that returns a static [Send] type upon completion is supported, mirroring
 /// the definition of [Runtime::spawn_task] itself.
@@ -36,6 +36,161 @@ mod imp_nix {
             .map_err(|_| std::io::Error::last_os_error())
     }
 
+    #[inline]
+    pub fn is_symlink(path: &Path) -> Result<bool, std::io::Error> {
+        let stat = nix::sys::stat::lstat(path).map_err(|_| std::io::Error::last_os_error())?;
+        Ok((stat.st_mode & nix::libc::S_IFMT) == nix::libc::S_IFLNK)
+    }
+
+    #[inline]
+    pub fn fallocate(fd: RawFd, length: u64) -> Result<(), std::io::Error> {
+        let ret = unsafe { nix::libc::fallocate(fd, 0, 0, length as nix::libc::off_t) };
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn punch_hole(fd: RawFd, offset: u64, length: u64) -> Result<(), std::io::Error> {
+        let ret = unsafe {
+            nix::libc::fallocate(
+                fd,
+                nix::libc::FALLOC_FL_PUNCH_HOLE | nix::libc::FALLOC_FL_KEEP_SIZE,
+                offset as nix::libc::off_t,
+                length as nix::libc::off_t,
+            )
+        };
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn dedupe_range(
+        source_fd: RawFd,
+        source_offset: u64,
+        length: u64,
+        destination_fd: RawFd,
+        destination_offset: u64,
+    ) -> Result<u64, std::io::Error> {
+        let mut range = super::FileDedupeRange::new(source_offset, length, destination_fd, destination_offset);
+        // FIDEDUPERANGE isn't exported by libc
+        let ret = unsafe {
+            nix::libc::ioctl(
+                source_fd,
+                super::FIDEDUPERANGE as _,
+                &mut range as *mut super::FileDedupeRange,
+            )
+        };
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        range.into_result()
+    }
+
+    #[inline]
+    pub fn mknod_block_device(path: &Path, device: u64) -> Result<(), std::io::Error> {
+        nix::sys::stat::mknod(
+            path,
+            nix::sys::stat::SFlag::S_IFBLK,
+            Mode::S_IRUSR | Mode::S_IWUSR,
+            device as nix::libc::dev_t,
+        )
+        .map_err(|_| std::io::Error::last_os_error())
+    }
+
+    #[inline]
+    pub fn loop_get_free(control_fd: RawFd) -> Result<u32, std::io::Error> {
+        // The loop ioctls aren't exported by libc
+        let ret = unsafe { nix::libc::ioctl(control_fd, super::LOOP_CTL_GET_FREE as _) };
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(ret as u32)
+    }
+
+    #[inline]
+    pub fn loop_set_fd(loop_fd: RawFd, backing_fd: RawFd) -> Result<(), std::io::Error> {
+        let ret = unsafe { nix::libc::ioctl(loop_fd, super::LOOP_SET_FD as _, backing_fd) };
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn loop_clear_fd(loop_fd: RawFd) -> Result<(), std::io::Error> {
+        let ret = unsafe { nix::libc::ioctl(loop_fd, super::LOOP_CLR_FD as _, 0) };
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn device_mapper_ioctl(
+        control_fd: RawFd,
+        opcode: u32,
+        ioctl: &mut super::DeviceMapperIoctl,
+    ) -> Result<(), std::io::Error> {
+        // The device-mapper ioctls aren't exported by libc
+        let ret = unsafe { nix::libc::ioctl(control_fd, opcode as _, ioctl as *mut super::DeviceMapperIoctl) };
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn available_space(path: &Path) -> Result<u64, std::io::Error> {
+        let statvfs = nix::sys::statvfs::statvfs(path).map_err(|_| std::io::Error::last_os_error())?;
+        Ok(statvfs.blocks_available() as u64 * statvfs.fragment_size() as u64)
+    }
+
+    #[inline]
+    pub fn open_direct(path: &Path, write: bool) -> Result<std::fs::File, std::io::Error> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        std::fs::OpenOptions::new()
+            .read(!write)
+            .write(write)
+            .create(write)
+            .truncate(write)
+            .custom_flags(nix::libc::O_DIRECT)
+            .open(path)
+    }
+
+    #[inline]
+    pub fn open_fifo_writer_nonblocking(path: &Path) -> Result<Option<std::fs::File>, std::io::Error> {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .custom_flags(nix::libc::O_NONBLOCK)
+            .open(path)
+        {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.raw_os_error() == Some(nix::libc::ENXIO) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
     #[inline]
     pub fn pidfd_open(pid: i32) -> Result<OwnedFd, std::io::Error> {
         // pidfd_open isn't wrapped in nix or libc, so a libc-wrapped syscall is needed
@@ -48,6 +203,19 @@ mod imp_nix {
         Ok(unsafe { OwnedFd::from_raw_fd(fd as i32) })
     }
 
+    #[inline]
+    pub fn getpgid(pid: i32) -> Result<i32, std::io::Error> {
+        nix::unistd::getpgid(Some(nix::unistd::Pid::from_raw(pid)))
+            .map(|pgid| pgid.as_raw())
+            .map_err(|_| std::io::Error::last_os_error())
+    }
+
+    #[inline]
+    pub fn killpg_sigkill(pgid: i32) -> Result<(), std::io::Error> {
+        nix::sys::signal::killpg(nix::unistd::Pid::from_raw(pgid), nix::sys::signal::Signal::SIGKILL)
+            .map_err(|_| std::io::Error::last_os_error())
+    }
+
     #[inline]
     pub fn pidfd_send_sigkill(fd: RawFd) -> Result<(), std::io::Error> {
         // pidfd_send_signal isn't wrapped in nix or libc, so a libc-wrapped syscall is needed
@@ -59,6 +227,18 @@ mod imp_nix {
 
         Ok(())
     }
+
+    #[inline]
+    pub fn kvm_get_api_version(fd: RawFd) -> Result<i32, std::io::Error> {
+        // KVM_GET_API_VERSION is _IO(KVMIO, 0x00), with KVMIO being 0xAE, and isn't exported by libc
+        let ret = unsafe { nix::libc::ioctl(fd, 0xAE00 as _, 0) };
+
+        if ret < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(ret)
+    }
 }
 
 #[cfg(feature = "rustix-syscall-backend")]
@@ -104,6 +284,206 @@ mod imp_rustix {
         .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
     }
 
+    #[inline]
+    pub fn is_symlink(path: &Path) -> Result<bool, std::io::Error> {
+        let stat = rustix::fs::lstat(path).map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))?;
+        Ok(rustix::fs::FileType::from_raw_mode(stat.st_mode) == rustix::fs::FileType::Symlink)
+    }
+
+    #[inline]
+    pub fn fallocate(fd: RawFd, length: u64) -> Result<(), std::io::Error> {
+        rustix::fs::fallocate(
+            unsafe { BorrowedFd::borrow_raw(fd) },
+            rustix::fs::FallocateFlags::empty(),
+            0,
+            length,
+        )
+        .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    #[inline]
+    pub fn punch_hole(fd: RawFd, offset: u64, length: u64) -> Result<(), std::io::Error> {
+        rustix::fs::fallocate(
+            unsafe { BorrowedFd::borrow_raw(fd) },
+            rustix::fs::FallocateFlags::PUNCH_HOLE | rustix::fs::FallocateFlags::KEEP_SIZE,
+            offset,
+            length,
+        )
+        .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    struct DedupeRange(super::FileDedupeRange);
+
+    unsafe impl rustix::ioctl::Ioctl for DedupeRange {
+        type Output = Result<u64, std::io::Error>;
+
+        const IS_MUTATING: bool = true;
+
+        fn opcode(&self) -> rustix::ioctl::Opcode {
+            super::FIDEDUPERANGE as rustix::ioctl::Opcode
+        }
+
+        fn as_ptr(&mut self) -> *mut std::ffi::c_void {
+            &mut self.0 as *mut super::FileDedupeRange as *mut std::ffi::c_void
+        }
+
+        unsafe fn output_from_ptr(
+            _: rustix::ioctl::IoctlOutput,
+            ptr: *mut std::ffi::c_void,
+        ) -> rustix::io::Result<Self::Output> {
+            Ok(unsafe { std::ptr::read(ptr as *const super::FileDedupeRange) }.into_result())
+        }
+    }
+
+    #[inline]
+    pub fn dedupe_range(
+        source_fd: RawFd,
+        source_offset: u64,
+        length: u64,
+        destination_fd: RawFd,
+        destination_offset: u64,
+    ) -> Result<u64, std::io::Error> {
+        let range = super::FileDedupeRange::new(source_offset, length, destination_fd, destination_offset);
+        unsafe { rustix::ioctl::ioctl(BorrowedFd::borrow_raw(source_fd), DedupeRange(range)) }
+            .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))?
+    }
+
+    #[inline]
+    pub fn mknod_block_device(path: &Path, device: u64) -> Result<(), std::io::Error> {
+        rustix::fs::mknodat(
+            rustix::fs::CWD,
+            path,
+            rustix::fs::FileType::BlockDevice,
+            Mode::RUSR | Mode::WUSR,
+            device,
+        )
+        .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    // rustix's NoArg discards the ioctl's return value, which is the number of the free loop device in this case
+    struct LoopGetFree;
+
+    unsafe impl rustix::ioctl::Ioctl for LoopGetFree {
+        type Output = u32;
+
+        const IS_MUTATING: bool = false;
+
+        fn opcode(&self) -> rustix::ioctl::Opcode {
+            super::LOOP_CTL_GET_FREE as rustix::ioctl::Opcode
+        }
+
+        fn as_ptr(&mut self) -> *mut std::ffi::c_void {
+            std::ptr::null_mut()
+        }
+
+        unsafe fn output_from_ptr(
+            output: rustix::ioctl::IoctlOutput,
+            _: *mut std::ffi::c_void,
+        ) -> rustix::io::Result<Self::Output> {
+            Ok(output as u32)
+        }
+    }
+
+    #[inline]
+    pub fn loop_get_free(control_fd: RawFd) -> Result<u32, std::io::Error> {
+        unsafe { rustix::ioctl::ioctl(BorrowedFd::borrow_raw(control_fd), LoopGetFree) }
+            .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    #[inline]
+    pub fn loop_set_fd(loop_fd: RawFd, backing_fd: RawFd) -> Result<(), std::io::Error> {
+        unsafe {
+            rustix::ioctl::ioctl(
+                BorrowedFd::borrow_raw(loop_fd),
+                rustix::ioctl::IntegerSetter::<{ super::LOOP_SET_FD as rustix::ioctl::Opcode }>::new_usize(
+                    backing_fd as usize,
+                ),
+            )
+        }
+        .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    #[inline]
+    pub fn loop_clear_fd(loop_fd: RawFd) -> Result<(), std::io::Error> {
+        unsafe {
+            rustix::ioctl::ioctl(
+                BorrowedFd::borrow_raw(loop_fd),
+                rustix::ioctl::NoArg::<{ super::LOOP_CLR_FD as rustix::ioctl::Opcode }>::new(),
+            )
+        }
+        .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    // The opcode of a device-mapper ioctl is only known at runtime, so rustix's Updater can't be used
+    struct DeviceMapperUpdater<'a>(u32, &'a mut super::DeviceMapperIoctl);
+
+    unsafe impl rustix::ioctl::Ioctl for DeviceMapperUpdater<'_> {
+        type Output = ();
+
+        const IS_MUTATING: bool = true;
+
+        fn opcode(&self) -> rustix::ioctl::Opcode {
+            self.0 as rustix::ioctl::Opcode
+        }
+
+        fn as_ptr(&mut self) -> *mut std::ffi::c_void {
+            self.1 as *mut super::DeviceMapperIoctl as *mut std::ffi::c_void
+        }
+
+        unsafe fn output_from_ptr(
+            _: rustix::ioctl::IoctlOutput,
+            _: *mut std::ffi::c_void,
+        ) -> rustix::io::Result<Self::Output> {
+            Ok(())
+        }
+    }
+
+    #[inline]
+    pub fn device_mapper_ioctl(
+        control_fd: RawFd,
+        opcode: u32,
+        ioctl: &mut super::DeviceMapperIoctl,
+    ) -> Result<(), std::io::Error> {
+        unsafe { rustix::ioctl::ioctl(BorrowedFd::borrow_raw(control_fd), DeviceMapperUpdater(opcode, ioctl)) }
+            .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    #[inline]
+    pub fn available_space(path: &Path) -> Result<u64, std::io::Error> {
+        let statvfs =
+            rustix::fs::statvfs(path).map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))?;
+        Ok(statvfs.f_bavail * statvfs.f_frsize)
+    }
+
+    #[inline]
+    pub fn open_direct(path: &Path, write: bool) -> Result<std::fs::File, std::io::Error> {
+        let flags = match write {
+            true => rustix::fs::OFlags::WRONLY | rustix::fs::OFlags::CREATE | rustix::fs::OFlags::TRUNC,
+            false => rustix::fs::OFlags::RDONLY,
+        };
+
+        rustix::fs::open(
+            path,
+            flags | rustix::fs::OFlags::DIRECT | rustix::fs::OFlags::CLOEXEC,
+            Mode::from_raw_mode(0o644),
+        )
+        .map(std::fs::File::from)
+        .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    #[inline]
+    pub fn open_fifo_writer_nonblocking(path: &Path) -> Result<Option<std::fs::File>, std::io::Error> {
+        match rustix::fs::open(
+            path,
+            rustix::fs::OFlags::WRONLY | rustix::fs::OFlags::NONBLOCK | rustix::fs::OFlags::CLOEXEC,
+            Mode::empty(),
+        ) {
+            Ok(fd) => Ok(Some(std::fs::File::from(fd))),
+            Err(rustix::io::Errno::NXIO) => Ok(None),
+            Err(errno) => Err(std::io::Error::from_raw_os_error(errno.raw_os_error())),
+        }
+    }
+
     #[inline]
     pub fn pidfd_open(pid: i32) -> Result<OwnedFd, std::io::Error> {
         rustix::process::pidfd_open(
@@ -118,11 +498,61 @@ mod imp_rustix {
         .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
     }
 
+    #[inline]
+    pub fn getpgid(pid: i32) -> Result<i32, std::io::Error> {
+        rustix::process::getpgid(rustix::process::Pid::from_raw(pid))
+            .map(|pgid| pgid.as_raw_nonzero().get())
+            .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
+    #[inline]
+    pub fn killpg_sigkill(pgid: i32) -> Result<(), std::io::Error> {
+        let pgid = rustix::process::Pid::from_raw(pgid).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "The provided PGID for killpg was not positive",
+            )
+        })?;
+
+        rustix::process::kill_process_group(pgid, rustix::process::Signal::KILL)
+            .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
+
     #[inline]
     pub fn pidfd_send_sigkill(fd: RawFd) -> Result<(), std::io::Error> {
         rustix::process::pidfd_send_signal(unsafe { BorrowedFd::borrow_raw(fd) }, rustix::process::Signal::KILL)
             .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
     }
+
+    // rustix's NoArg discards the ioctl's return value, which is the KVM API version in this case
+    struct KvmGetApiVersion;
+
+    unsafe impl rustix::ioctl::Ioctl for KvmGetApiVersion {
+        type Output = i32;
+
+        const IS_MUTATING: bool = false;
+
+        fn opcode(&self) -> rustix::ioctl::Opcode {
+            rustix::ioctl::opcode::none(0xAE, 0x00)
+        }
+
+        fn as_ptr(&mut self) -> *mut std::ffi::c_void {
+            std::ptr::null_mut()
+        }
+
+        unsafe fn output_from_ptr(
+            output: rustix::ioctl::IoctlOutput,
+            _: *mut std::ffi::c_void,
+        ) -> rustix::io::Result<Self::Output> {
+            Ok(output)
+        }
+    }
+
+    #[inline]
+    pub fn kvm_get_api_version(fd: RawFd) -> Result<i32, std::io::Error> {
+        unsafe { rustix::ioctl::ioctl(BorrowedFd::borrow_raw(fd), KvmGetApiVersion) }
+            .map_err(|errno| std::io::Error::from_raw_os_error(errno.raw_os_error()))
+    }
 }
 
 #[cfg(not(any(feature = "nix-syscall-backend", feature = "rustix-syscall-backend")))]
@@ -152,15 +582,241 @@ mod imp_dummy {
         panic!("No syscall backend was enabled for fctools");
     }
 
+    #[inline]
+    pub fn is_symlink(path: &Path) -> Result<bool, std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn fallocate(fd: RawFd, length: u64) -> Result<(), std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn punch_hole(fd: RawFd, offset: u64, length: u64) -> Result<(), std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn dedupe_range(
+        source_fd: RawFd,
+        source_offset: u64,
+        length: u64,
+        destination_fd: RawFd,
+        destination_offset: u64,
+    ) -> Result<u64, std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn mknod_block_device(path: &Path, device: u64) -> Result<(), std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn loop_get_free(control_fd: RawFd) -> Result<u32, std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn loop_set_fd(loop_fd: RawFd, backing_fd: RawFd) -> Result<(), std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn loop_clear_fd(loop_fd: RawFd) -> Result<(), std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn device_mapper_ioctl(
+        control_fd: RawFd,
+        opcode: u32,
+        ioctl: &mut super::DeviceMapperIoctl,
+    ) -> Result<(), std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn available_space(path: &Path) -> Result<u64, std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn open_direct(path: &Path, write: bool) -> Result<std::fs::File, std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn open_fifo_writer_nonblocking(path: &Path) -> Result<Option<std::fs::File>, std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
     #[inline]
     pub fn pidfd_open(pid: i32) -> Result<OwnedFd, std::io::Error> {
         panic!("No syscall backend was enabled for fctools");
     }
 
+    #[inline]
+    pub fn getpgid(pid: i32) -> Result<i32, std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
+    #[inline]
+    pub fn killpg_sigkill(pgid: i32) -> Result<(), std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+
     #[inline]
     pub fn pidfd_send_sigkill(fd: RawFd) -> Result<(), std::io::Error> {
         panic!("No syscall backend was enabled for fctools");
     }
+
+    #[inline]
+    pub fn kvm_get_api_version(fd: RawFd) -> Result<i32, std::io::Error> {
+        panic!("No syscall backend was enabled for fctools");
+    }
+}
+
+// The loop ioctls are _IO(0x4C, nr)
+const LOOP_SET_FD: u32 = 0x4C00;
+const LOOP_CLR_FD: u32 = 0x4C01;
+const LOOP_CTL_GET_FREE: u32 = 0x4C82;
+
+// The device-mapper ioctls are _IOWR(0xFD, nr, struct dm_ioctl), with the struct being 312 bytes
+pub const DM_DEV_CREATE: u32 = 0xC138FD03;
+pub const DM_DEV_REMOVE: u32 = 0xC138FD04;
+pub const DM_DEV_SUSPEND: u32 = 0xC138FD06;
+pub const DM_TABLE_LOAD: u32 = 0xC138FD09;
+
+// DM_DEFERRED_REMOVE postpones the removal of a device that is still open until it's closed
+const DM_DEFERRED_REMOVE_FLAG: u32 = 1 << 17;
+
+// struct dm_ioctl followed by a single struct dm_target_spec and its parameters, which is all that's needed. The
+// kernel only reads the target when the target count is non-zero.
+#[repr(C)]
+pub struct DeviceMapperIoctl {
+    version: [u32; 3],
+    data_size: u32,
+    data_start: u32,
+    target_count: u32,
+    open_count: i32,
+    flags: u32,
+    event_nr: u32,
+    padding: u32,
+    pub dev: u64,
+    name: [u8; 128],
+    uuid: [u8; 129],
+    data: [u8; 7],
+    sector_start: u64,
+    length: u64,
+    status: i32,
+    next: u32,
+    target_type: [u8; 16],
+    target_parameters: [u8; 256],
+}
+
+impl DeviceMapperIoctl {
+    pub fn new(name: &str) -> Result<Self, std::io::Error> {
+        let mut ioctl = Self {
+            // 4.0.0 is the oldest version of the interface that is still accepted
+            version: [4, 0, 0],
+            data_size: std::mem::size_of::<Self>() as u32,
+            data_start: std::mem::offset_of!(Self, sector_start) as u32,
+            target_count: 0,
+            open_count: 0,
+            flags: 0,
+            event_nr: 0,
+            padding: 0,
+            dev: 0,
+            name: [0; 128],
+            uuid: [0; 129],
+            data: [0; 7],
+            sector_start: 0,
+            length: 0,
+            status: 0,
+            next: 0,
+            target_type: [0; 16],
+            target_parameters: [0; 256],
+        };
+        copy_nul_terminated(name, &mut ioctl.name)?;
+        Ok(ioctl)
+    }
+
+    pub fn with_target(
+        mut self,
+        sectors: u64,
+        target_type: &str,
+        target_parameters: &str,
+    ) -> Result<Self, std::io::Error> {
+        self.target_count = 1;
+        self.length = sectors;
+        copy_nul_terminated(target_type, &mut self.target_type)?;
+        copy_nul_terminated(target_parameters, &mut self.target_parameters)?;
+        Ok(self)
+    }
+
+    pub fn with_deferred_remove(mut self) -> Self {
+        self.flags |= DM_DEFERRED_REMOVE_FLAG;
+        self
+    }
+}
+
+fn copy_nul_terminated(value: &str, buffer: &mut [u8]) -> Result<(), std::io::Error> {
+    if value.len() >= buffer.len() || value.contains('\0') {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "A device-mapper ioctl string was too long or contained a NUL byte",
+        ));
+    }
+
+    buffer[..value.len()].copy_from_slice(value.as_bytes());
+    Ok(())
+}
+
+// FIDEDUPERANGE is _IOWR(0x94, 54, struct file_dedupe_range), with the size of the struct excluding its trailing
+// array of destinations being 24 bytes
+const FIDEDUPERANGE: u32 = 0xC0189436;
+
+// struct file_dedupe_range with a single struct file_dedupe_range_info destination, which is all that's needed
+#[repr(C)]
+struct FileDedupeRange {
+    src_offset: u64,
+    src_length: u64,
+    dest_count: u16,
+    reserved1: u16,
+    reserved2: u32,
+    dest_fd: i64,
+    dest_offset: u64,
+    bytes_deduped: u64,
+    status: i32,
+    reserved: u32,
+}
+
+impl FileDedupeRange {
+    fn new(source_offset: u64, length: u64, destination_fd: i32, destination_offset: u64) -> Self {
+        Self {
+            src_offset: source_offset,
+            src_length: length,
+            dest_count: 1,
+            reserved1: 0,
+            reserved2: 0,
+            dest_fd: destination_fd as i64,
+            dest_offset: destination_offset,
+            bytes_deduped: 0,
+            status: 0,
+            reserved: 0,
+        }
+    }
+
+    fn into_result(self) -> Result<u64, std::io::Error> {
+        // the status is FILE_DEDUPE_RANGE_SAME (0), FILE_DEDUPE_RANGE_DIFFERS (1) or a negated errno
+        match self.status {
+            0 => Ok(self.bytes_deduped),
+            1 => Ok(0),
+            status => Err(std::io::Error::from_raw_os_error(-status)),
+        }
+    }
 }
 
 #[cfg(not(any(feature = "nix-syscall-backend", feature = "rustix-syscall-backend")))]
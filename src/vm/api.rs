@@ -1,12 +1,14 @@
-use std::future::Future;
+use std::{future::Future, num::NonZeroU16, path::PathBuf};
 
 use bytes::Bytes;
 use http::{
-    Request, Response, StatusCode,
+    Request, Response, StatusCode, Uri,
     header::{ACCEPT, CONTENT_TYPE},
 };
 use http_body_util::Full;
 use hyper::body::Incoming;
+use hyper_client_sockets::{connector::UnixConnector, uri::UnixUri};
+use hyper_util::client::legacy::Client;
 use serde::{Serialize, de::DeserializeOwned};
 
 use crate::{
@@ -14,12 +16,13 @@ use crate::{
     runtime::Runtime,
     vm::{
         Vm, VmState, VmStateCheckError,
-        configuration::VmConfigurationData,
+        configuration::{ConfigValidationError, VmConfigurationData},
         models::{
-            BalloonDevice, BalloonStatistics, CreateSnapshot, Info, LoadSnapshot, MachineConfiguration,
-            MemoryHotplugStatus, ReprAction, ReprActionType, ReprApiError, ReprFirecrackerVersion, ReprInfo,
-            ReprIsPaused, ReprUpdateState, ReprUpdatedState, UpdateBalloonDevice, UpdateBalloonStatistics, UpdateDrive,
-            UpdateMemoryHotplugConfiguration, UpdateNetworkInterface,
+            ApiErrorDetails, BalloonDevice, BalloonStatistics, CreateSnapshot, DriveInfo, Info, LoadSnapshot,
+            MachineConfiguration, MemoryBackendType, MemoryHotplugStatus, PatchMachineConfiguration, ReprAction,
+            ReprActionType, ReprFirecrackerVersion, ReprInfo, ReprIsPaused, ReprUpdateState, ReprUpdatedState,
+            ReprVmConfig, UpdateBalloonDevice, UpdateBalloonStatistics, UpdateDrive, UpdateMemoryHotplugConfiguration,
+            UpdateNetworkInterface, validate_rate_limiters,
         },
         snapshot::VmSnapshot,
         upgrade_owner,
@@ -32,6 +35,10 @@ use crate::{
     },
 };
 
+/// The content type of the bodies of all requests sent and responses received by the [VmApi] bindings, except for
+/// custom requests sent via [VmApi::send_custom_api_request].
+const JSON_CONTENT_TYPE: &str = "application/json";
+
 /// An error that can be emitted by the [VmApi] Firecracker Management API bindings.
 #[derive(Debug)]
 pub enum VmApiError {
@@ -58,10 +65,54 @@ pub enum VmApiError {
     SnapshotChangeOwnerError(ChangeOwnerError),
     /// A [ResourceSystemError] occurred when using the resource system of the VM.
     ResourceSystemError(ResourceSystemError),
+    /// The request was rejected before being sent, since it is invalid for the VM's configuration according to the
+    /// given [ConfigValidationError].
+    InvalidConfiguration(ConfigValidationError),
+    /// Balloon statistics were requested or updated while they were disabled at boot, either due to the VM not
+    /// having a balloon device or due to its statistics polling interval being unset or zero. Firecracker doesn't
+    /// allow enabling balloon statistics after boot.
+    BalloonStatisticsDisabled,
 }
 
 impl std::error::Error for VmApiError {}
 
+impl VmApiError {
+    /// Get the HTTP status code that most appropriately represents this [VmApiError] when it is exposed by an
+    /// HTTP API built on top of fctools.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            VmApiError::StateCheckError(_) | VmApiError::BalloonStatisticsDisabled => 409,
+            VmApiError::InvalidConfiguration(_) => 400,
+            VmApiError::ReceivedErrorResponse {
+                status_code,
+                fault_message: _,
+            } if status_code.is_client_error() => 400,
+            VmApiError::ReceivedErrorResponse {
+                status_code: _,
+                fault_message: _,
+            }
+            | VmApiError::ResponseBodyReceiveError(_)
+            | VmApiError::ResponseBodyContainsUnexpectedData(_) => 502,
+            VmApiError::ConnectionError(err) => err.http_status(),
+            _ => 500,
+        }
+    }
+
+    /// Get a client-safe message describing this [VmApiError], which, unlike its [Display](std::fmt::Display)
+    /// implementation, doesn't leak any details such as host paths or internal error messages.
+    pub fn public_message(&self) -> &'static str {
+        match self {
+            VmApiError::ConnectionError(err) => err.public_message(),
+            _ => match self.http_status() {
+                409 => "The VM is in a state that doesn't permit this operation",
+                400 => "The VM's management API rejected the request as invalid",
+                502 => "The VM's management API failed to process the request",
+                _ => "An internal error occurred while managing the VM",
+            },
+        }
+    }
+}
+
 impl std::fmt::Display for VmApiError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -95,6 +146,12 @@ impl std::fmt::Display for VmApiError {
             VmApiError::ResourceSystemError(err) => {
                 write!(f, "An error occurred within the resource system: {err}")
             }
+            VmApiError::InvalidConfiguration(err) => {
+                write!(f, "The request is invalid for the VM's configuration: {err}")
+            }
+            VmApiError::BalloonStatisticsDisabled => {
+                write!(f, "The statistics of the VM's balloon device were disabled at boot")
+            }
         }
     }
 }
@@ -107,7 +164,9 @@ pub trait VmApi {
     /// Send a custom [Request] with a [Bytes] payload to the given URI of the Management HTTP server. Should only be used
     /// for operations not supported by other [VmApi] functions. The "new_is_paused" parameter should optionally contain
     /// the new value for whether the VM is paused, if the called request modifies whether the VM is paused. Normally,
-    /// this type of tracking is done automatically, but arbitrary API calls bypass this safeguard.
+    /// this type of tracking is done automatically, but arbitrary API calls bypass this safeguard. The [Request] is
+    /// sent with exactly the headers it was built with, so no JSON headers are forced onto it and the caller is in
+    /// control of labeling its body with the appropriate content type.
     fn send_custom_api_request<U: AsRef<str> + Send>(
         &mut self,
         uri: U,
@@ -130,9 +189,19 @@ pub trait VmApi {
         update_balloon: UpdateBalloonDevice,
     ) -> impl Future<Output = Result<(), VmApiError>> + Send;
 
-    /// Get the balloon statistics of the VM from the API.
+    /// Get the balloon statistics of the VM from the API. Fails with [VmApiError::BalloonStatisticsDisabled] without
+    /// calling the API if the statistics were disabled at boot.
     fn get_balloon_statistics(&mut self) -> impl Future<Output = Result<BalloonStatistics, VmApiError>> + Send;
 
+    /// Enable the balloon statistics of the VM with the given polling interval in seconds. If the VM hasn't been
+    /// started yet, the interval is set in its configured balloon device so that the statistics are enabled at boot.
+    /// Otherwise, the interval is updated via the API, which Firecracker only allows if the statistics were already
+    /// enabled at boot, so [VmApiError::BalloonStatisticsDisabled] is returned if they weren't.
+    fn enable_balloon_statistics(
+        &mut self,
+        stats_polling_interval_s: NonZeroU16,
+    ) -> impl Future<Output = Result<(), VmApiError>> + Send;
+
     /// Update the balloon statistics of the VM via the API.
     fn update_balloon_statistics(
         &mut self,
@@ -162,6 +231,9 @@ pub trait VmApi {
     /// Update a drive of the VM via the API.
     fn update_drive(&mut self, update_drive: UpdateDrive) -> impl Future<Output = Result<(), VmApiError>> + Send;
 
+    /// Get the effective configuration of all drives of the VM via the API, which reflects prior drive updates.
+    fn get_drives(&mut self) -> impl Future<Output = Result<Vec<DriveInfo>, VmApiError>> + Send;
+
     /// Update a network interface of the VM via the API.
     fn update_network_interface(
         &mut self,
@@ -171,7 +243,21 @@ pub trait VmApi {
     /// Get the machine configuration of the VM via the API.
     fn get_machine_configuration(&mut self) -> impl Future<Output = Result<MachineConfiguration, VmApiError>> + Send;
 
-    /// Create a snapshot of the VM via the API.
+    /// Partially update the machine configuration of the VM with the fields set in the [PatchMachineConfiguration],
+    /// which is only possible before the VM has been started, in order to adjust a template configuration. Since
+    /// the API server only becomes available during startup, the patch is applied to the configured
+    /// [MachineConfiguration] that is then sent as part of the VM's initialization, equivalently to a
+    /// PATCH /machine-config request right before boot. The patched configuration is validated upfront, so an
+    /// invalid patch is rejected with [VmApiError::InvalidConfiguration] and leaves the configuration unchanged.
+    fn patch_machine_configuration(
+        &mut self,
+        patch_machine_configuration: PatchMachineConfiguration,
+    ) -> impl Future<Output = Result<(), VmApiError>> + Send;
+
+    /// Create a snapshot of the VM via the API. A diff snapshot is rejected with
+    /// [ConfigValidationError::DirtyPageTrackingDisabled] without calling the API if dirty page tracking isn't enabled
+    /// in the VM's configuration. Unless [CreateSnapshot::overwrite] is set, the snapshot is also rejected with
+    /// [ConfigValidationError::SnapshotFileAlreadyExists] if the snapshot or memory file already exists.
     fn create_snapshot(
         &mut self,
         create_snapshot: CreateSnapshot,
@@ -273,9 +359,38 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmApi for Vm<E, S, R> {
     async fn get_balloon_statistics(&mut self) -> Result<BalloonStatistics, VmApiError> {
         self.ensure_state(VmState::Running)
             .map_err(VmApiError::StateCheckError)?;
+
+        if !are_balloon_statistics_enabled(self.configuration.get_data()) {
+            return Err(VmApiError::BalloonStatisticsDisabled);
+        }
+
         send_api_request_with_response(self, "/balloon/statistics", "GET", None::<i32>).await
     }
 
+    async fn enable_balloon_statistics(&mut self, stats_polling_interval_s: NonZeroU16) -> Result<(), VmApiError> {
+        if self.get_state() == VmState::NotStarted {
+            let balloon_device = self
+                .configuration
+                .get_data_mut()
+                .balloon_device
+                .as_mut()
+                .ok_or(VmApiError::BalloonStatisticsDisabled)?;
+            balloon_device.stats_polling_interval_s = Some(stats_polling_interval_s.get().into());
+            return Ok(());
+        }
+
+        self.ensure_paused_or_running().map_err(VmApiError::StateCheckError)?;
+
+        if !are_balloon_statistics_enabled(self.configuration.get_data()) {
+            return Err(VmApiError::BalloonStatisticsDisabled);
+        }
+
+        self.update_balloon_statistics(UpdateBalloonStatistics {
+            stats_polling_interval_s: stats_polling_interval_s.get(),
+        })
+        .await
+    }
+
     async fn update_balloon_statistics(
         &mut self,
         update_balloon_statistics: UpdateBalloonStatistics,
@@ -315,6 +430,7 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmApi for Vm<E, S, R> {
 
     async fn update_drive(&mut self, update_drive: UpdateDrive) -> Result<(), VmApiError> {
         self.ensure_paused_or_running().map_err(VmApiError::StateCheckError)?;
+        validate_rate_limiters([&update_drive.rate_limiter]).map_err(VmApiError::InvalidConfiguration)?;
         send_api_request(
             self,
             format!("/drives/{}", update_drive.drive_id).as_str(),
@@ -324,11 +440,25 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmApi for Vm<E, S, R> {
         .await
     }
 
+    async fn get_drives(&mut self) -> Result<Vec<DriveInfo>, VmApiError> {
+        self.ensure_paused_or_running().map_err(VmApiError::StateCheckError)?;
+        Ok(
+            send_api_request_with_response::<ReprVmConfig, _, _, _>(self, "/vm/config", "GET", None::<i32>)
+                .await?
+                .drives,
+        )
+    }
+
     async fn update_network_interface(
         &mut self,
         update_network_interface: UpdateNetworkInterface,
     ) -> Result<(), VmApiError> {
         self.ensure_paused_or_running().map_err(VmApiError::StateCheckError)?;
+        validate_rate_limiters([
+            &update_network_interface.rx_rate_limiter,
+            &update_network_interface.tx_rate_limiter,
+        ])
+        .map_err(VmApiError::InvalidConfiguration)?;
         send_api_request(
             self,
             format!("/network-interfaces/{}", update_network_interface.iface_id).as_str(),
@@ -343,10 +473,34 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmApi for Vm<E, S, R> {
         send_api_request_with_response(self, "/machine-config", "GET", None::<i32>).await
     }
 
+    async fn patch_machine_configuration(
+        &mut self,
+        patch_machine_configuration: PatchMachineConfiguration,
+    ) -> Result<(), VmApiError> {
+        self.ensure_state(VmState::NotStarted)
+            .map_err(VmApiError::StateCheckError)?;
+
+        let mut data = self.configuration.get_data().clone();
+        data.machine_configuration.apply_patch(&patch_machine_configuration);
+        data.validate().map_err(VmApiError::InvalidConfiguration)?;
+        self.configuration.get_data_mut().machine_configuration = data.machine_configuration;
+        Ok(())
+    }
+
     async fn create_snapshot(&mut self, create_snapshot: CreateSnapshot) -> Result<VmSnapshot, VmApiError> {
         self.ensure_state(VmState::Paused)
             .map_err(VmApiError::StateCheckError)?;
-        send_api_request(self, "/snapshot/create", "PUT", Some(&create_snapshot)).await?;
+
+        // Firecracker would reject this as well, but only with an opaque error message
+        #[cfg(feature = "firecracker-diff-snapshots")]
+        if create_snapshot.snapshot_type == Some(crate::vm::models::SnapshotType::Diff)
+            && !self.configuration.is_dirty_page_tracking_enabled()
+        {
+            return Err(VmApiError::InvalidConfiguration(
+                ConfigValidationError::DirtyPageTrackingDisabled,
+            ));
+        }
+
         let snapshot_effective_path = self
             .vmm_process
             .resolve_effective_path(create_snapshot.snapshot.get_initial_path());
@@ -354,6 +508,25 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmApi for Vm<E, S, R> {
             .vmm_process
             .resolve_effective_path(create_snapshot.mem_file.get_initial_path());
 
+        if !create_snapshot.overwrite {
+            for effective_path in [&snapshot_effective_path, &mem_file_effective_path] {
+                if self
+                    .vmm_process
+                    .resource_system
+                    .runtime
+                    .fs_exists(effective_path)
+                    .await
+                    .map_err(|err| VmApiError::ResourceSystemError(ResourceSystemError::FilesystemError(err)))?
+                {
+                    return Err(VmApiError::InvalidConfiguration(
+                        ConfigValidationError::SnapshotFileAlreadyExists(effective_path.clone()),
+                    ));
+                }
+            }
+        }
+
+        send_api_request(self, "/snapshot/create", "PUT", Some(&create_snapshot)).await?;
+
         futures_util::try_join!(
             upgrade_owner(
                 &snapshot_effective_path,
@@ -472,7 +645,7 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmApi for Vm<E, S, R> {
 
     async fn get_mmds<T: DeserializeOwned>(&mut self) -> Result<T, VmApiError> {
         self.ensure_paused_or_running().map_err(VmApiError::StateCheckError)?;
-        send_api_request_with_response(self, "/mmds", "GET", None::<i32>).await
+        send_api_request_with_streamed_response(self, "/mmds", "GET", None::<i32>).await
     }
 
     async fn create_mmds_untyped(&mut self, value: &serde_json::Value) -> Result<(), VmApiError> {
@@ -487,83 +660,232 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmApi for Vm<E, S, R> {
 
     async fn get_mmds_untyped(&mut self) -> Result<serde_json::Value, VmApiError> {
         self.ensure_paused_or_running().map_err(VmApiError::StateCheckError)?;
-        send_api_request_with_response(self, "/mmds", "GET", None::<i32>).await
+        send_api_request_with_streamed_response(self, "/mmds", "GET", None::<i32>).await
+    }
+}
+
+/// A cheaply cloneable handle to the Management API server of a [Vm], obtained via [Vm::get_api_handle], that performs
+/// read-only API calls via a shared reference. Since the [VmApi] implementation of a [Vm] requires exclusive access
+/// in order to track the [VmState], this allows, for example, a monitoring task to poll balloon statistics
+/// concurrently to the API calls made by the task owning the [Vm], with the hyper client's pooling of connections.
+///
+/// A [VmApiHandle] doesn't track or check the [VmState], so its calls simply fail with a [VmApiError::ConnectionError]
+/// or [VmApiError::ReceivedErrorResponse] when the [Vm] is in a state that doesn't permit them.
+#[derive(Clone)]
+pub struct VmApiHandle<R: Runtime> {
+    client: Client<UnixConnector<R::SocketBackend>, Full<Bytes>>,
+    socket_path: PathBuf,
+}
+
+impl<R: Runtime> std::fmt::Debug for VmApiHandle<R> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VmApiHandle")
+            .field("socket_path", &self.socket_path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: Runtime> VmApiHandle<R> {
+    /// Get [Info] about the VM, equivalent to [VmApi::get_info].
+    pub async fn get_info(&self) -> Result<Info, VmApiError> {
+        let repr: ReprInfo = self.get("/").await?;
+        Ok(Info {
+            id: repr.id,
+            is_paused: repr.is_paused == ReprIsPaused::Paused,
+            vmm_version: repr.vmm_version,
+            app_name: repr.app_name,
+        })
+    }
+
+    /// Get the [BalloonDevice] of the VM, equivalent to [VmApi::get_balloon_device].
+    pub async fn get_balloon_device(&self) -> Result<BalloonDevice, VmApiError> {
+        self.get("/balloon").await
+    }
+
+    /// Get the [BalloonStatistics] of the VM, equivalent to [VmApi::get_balloon_statistics], except for not
+    /// checking whether balloon statistics were enabled in the VM's configuration beforehand.
+    pub async fn get_balloon_statistics(&self) -> Result<BalloonStatistics, VmApiError> {
+        self.get("/balloon/statistics").await
+    }
+
+    /// Get the [DriveInfo]s of all drives of the VM, equivalent to [VmApi::get_drives].
+    pub async fn get_drives(&self) -> Result<Vec<DriveInfo>, VmApiError> {
+        Ok(self.get::<ReprVmConfig>("/vm/config").await?.drives)
+    }
+
+    /// Get the [MachineConfiguration] of the VM, equivalent to [VmApi::get_machine_configuration].
+    pub async fn get_machine_configuration(&self) -> Result<MachineConfiguration, VmApiError> {
+        self.get("/machine-config").await
+    }
+
+    /// Get the version of Firecracker running the VM, equivalent to [VmApi::get_firecracker_version].
+    pub async fn get_firecracker_version(&self) -> Result<String, VmApiError> {
+        Ok(self
+            .get::<ReprFirecrackerVersion>("/version")
+            .await?
+            .firecracker_version)
+    }
+
+    /// Get the [MemoryHotplugStatus] of the VM, equivalent to [VmApi::get_memory_hotplug_status].
+    pub async fn get_memory_hotplug_status(&self) -> Result<MemoryHotplugStatus, VmApiError> {
+        self.get("/hotplug/memory").await
+    }
+
+    /// Get the contents of the VM's MMDS deserialized into the given type, equivalent to [VmApi::get_mmds].
+    pub async fn get_mmds<T: DeserializeOwned>(&self) -> Result<T, VmApiError> {
+        self.get("/mmds").await
+    }
+
+    /// Get the contents of the VM's MMDS as an untyped JSON value, equivalent to [VmApi::get_mmds_untyped].
+    pub async fn get_mmds_untyped(&self) -> Result<serde_json::Value, VmApiError> {
+        self.get("/mmds").await
+    }
+
+    async fn get<Resp: DeserializeOwned>(&self, route: &str) -> Result<Resp, VmApiError> {
+        let mut request = build_api_request("GET", None::<i32>)?;
+        *request.uri_mut() = Uri::unix(&self.socket_path, route).map_err(|error| {
+            VmApiError::ConnectionError(VmmProcessError::InvalidUri {
+                uri: route.to_owned(),
+                error,
+            })
+        })?;
+
+        let mut response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|err| VmApiError::ConnectionError(VmmProcessError::RequestError(Box::new(err))))?;
+
+        if !response.status().is_success() {
+            return Err(read_error_response(&mut response).await);
+        }
+
+        let response_chunks = response
+            .read_body_to_chunks()
+            .await
+            .map_err(VmApiError::ResponseBodyReceiveError)?;
+        serde_json::from_reader(response_chunks).map_err(VmApiError::SerdeError)
+    }
+}
+
+impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
+    /// Get a [VmApiHandle] for making read-only Management API calls to this [Vm] via a shared reference and
+    /// concurrently to other API calls. Allowed in [VmState::Paused] or [VmState::Running].
+    pub async fn get_api_handle(&mut self) -> Result<VmApiHandle<R>, VmApiError> {
+        self.ensure_paused_or_running().map_err(VmApiError::StateCheckError)?;
+        let socket_path = self
+            .vmm_process
+            .get_socket_path()
+            .ok_or(VmApiError::ConnectionError(VmmProcessError::ApiSocketDisabled))?;
+        let client = self
+            .vmm_process
+            .get_api_client()
+            .await
+            .map_err(VmApiError::ConnectionError)?
+            .clone();
+
+        Ok(VmApiHandle { client, socket_path })
     }
 }
 
+/// Parse the body of an unsuccessful response from the Firecracker Management API into [ApiErrorDetails]. This is
+/// done internally by all [VmApi] bindings, and is useful for uniformly handling the error responses to requests
+/// sent via [VmApi::send_custom_api_request].
+pub fn parse_api_error(body: &str) -> Result<ApiErrorDetails, serde_json::Error> {
+    serde_json::from_str(body)
+}
+
+fn are_balloon_statistics_enabled(data: &VmConfigurationData) -> bool {
+    data.balloon_device
+        .as_ref()
+        .and_then(|balloon_device| balloon_device.stats_polling_interval_s)
+        .is_some_and(|stats_polling_interval_s| stats_polling_interval_s > 0)
+}
+
 pub(super) async fn init_new<E: VmmExecutor, S: ProcessSpawner, R: Runtime>(
     vm: &mut Vm<E, S, R>,
     data: VmConfigurationData,
 ) -> Result<(), VmApiError> {
-    send_api_request(vm, "/boot-source", "PUT", Some(&data.boot_source)).await?;
+    // The requests are intentionally not overlapped: Firecracker's API server handles them one at a time on a single
+    // thread, so concurrency wouldn't reduce latency, while it could reorder device registration (for example, the
+    // order of drives determines their names inside the guest). hyper doesn't support HTTP/1.1 pipelining either, so
+    // the batch is serialized upfront in order to never leave the VMM partially configured due to a serialization
+    // error, and is then sent back-to-back over the same kept-alive connection.
+    for (route, request_body) in build_init_batch(&data)? {
+        send_api_request(vm, &route, "PUT", Some(request_body)).await?;
+    }
+
+    send_api_request(
+        vm,
+        "/actions",
+        "PUT",
+        Some(ReprAction {
+            action_type: ReprActionType::InstanceStart,
+        }),
+    )
+    .await
+}
+
+fn build_init_batch(data: &VmConfigurationData) -> Result<Vec<(String, serde_json::Value)>, VmApiError> {
+    fn entry<T: Serialize>(route: impl Into<String>, body: &T) -> Result<(String, serde_json::Value), VmApiError> {
+        Ok((
+            route.into(),
+            serde_json::to_value(body).map_err(VmApiError::SerdeError)?,
+        ))
+    }
+
+    let mut batch = vec![entry("/boot-source", &data.boot_source)?];
 
     for drive in data.drives.iter() {
-        send_api_request(vm, format!("/drives/{}", drive.drive_id).as_str(), "PUT", Some(drive)).await?;
+        batch.push(entry(format!("/drives/{}", drive.drive_id), drive)?);
     }
 
     for pmem_device in data.pmem_devices.iter() {
-        send_api_request(
-            vm,
-            format!("/pmem/{}", pmem_device.id).as_str(),
-            "PUT",
-            Some(pmem_device),
-        )
-        .await?;
+        batch.push(entry(format!("/pmem/{}", pmem_device.id), pmem_device)?);
     }
 
-    send_api_request(vm, "/machine-config", "PUT", Some(&data.machine_configuration)).await?;
+    batch.push(entry("/machine-config", &data.machine_configuration)?);
 
     if let Some(ref cpu_template) = data.cpu_template {
-        send_api_request(vm, "/cpu-config", "PUT", Some(cpu_template)).await?;
+        batch.push(entry("/cpu-config", cpu_template)?);
     }
 
     for network_interface in data.network_interfaces.iter() {
-        send_api_request(
-            vm,
-            format!("/network-interfaces/{}", network_interface.iface_id).as_str(),
-            "PUT",
-            Some(network_interface),
-        )
-        .await?;
+        batch.push(entry(
+            format!("/network-interfaces/{}", network_interface.iface_id),
+            network_interface,
+        )?);
     }
 
     if let Some(ref balloon_device) = data.balloon_device {
-        send_api_request(vm, "/balloon", "PUT", Some(balloon_device)).await?;
+        batch.push(entry("/balloon", balloon_device)?);
     }
 
     if let Some(ref vsock_device) = data.vsock_device {
-        send_api_request(vm, "/vsock", "PUT", Some(vsock_device)).await?;
+        batch.push(entry("/vsock", vsock_device)?);
     }
 
     if let Some(ref logger_system) = data.logger_system {
-        send_api_request(vm, "/logger", "PUT", Some(logger_system)).await?;
+        batch.push(entry("/logger", logger_system)?);
     }
 
     if let Some(ref metrics_system) = data.metrics_system {
-        send_api_request(vm, "/metrics", "PUT", Some(metrics_system)).await?;
+        batch.push(entry("/metrics", metrics_system)?);
     }
 
     if let Some(ref memory_hotplug_configuration) = data.memory_hotplug_configuration {
-        send_api_request(vm, "/hotplug/memory", "PUT", Some(memory_hotplug_configuration)).await?;
+        batch.push(entry("/hotplug/memory", memory_hotplug_configuration)?);
     }
 
     if let Some(ref mmds_configuration) = data.mmds_configuration {
-        send_api_request(vm, "/mmds/config", "PUT", Some(mmds_configuration)).await?;
+        batch.push(entry("/mmds/config", mmds_configuration)?);
     }
 
     if let Some(ref entropy_device) = data.entropy_device {
-        send_api_request(vm, "/entropy", "PUT", Some(entropy_device)).await?;
+        batch.push(entry("/entropy", entropy_device)?);
     }
 
-    send_api_request(
-        vm,
-        "/actions",
-        "PUT",
-        Some(ReprAction {
-            action_type: ReprActionType::InstanceStart,
-        }),
-    )
-    .await
+    Ok(batch)
 }
 
 pub(super) async fn init_restored_from_snapshot<E: VmmExecutor, S: ProcessSpawner, R: Runtime>(
@@ -571,6 +893,14 @@ pub(super) async fn init_restored_from_snapshot<E: VmmExecutor, S: ProcessSpawne
     data: VmConfigurationData,
     load_snapshot: LoadSnapshot,
 ) -> Result<(), VmApiError> {
+    // Firecracker allows swapping the memory backend of any snapshot from a file to UFFD, but not the other way
+    // around for snapshots of VMs with guest memory backed by huge pages
+    if data.requires_hugetlbfs() && load_snapshot.mem_backend.backend_type == MemoryBackendType::File {
+        return Err(VmApiError::InvalidConfiguration(
+            ConfigValidationError::HugePagesRequireUffdBackend,
+        ));
+    }
+
     if let Some(ref logger_system) = data.logger_system {
         send_api_request(vm, "/logger", "PUT", Some(logger_system)).await?;
     }
@@ -579,6 +909,8 @@ pub(super) async fn init_restored_from_snapshot<E: VmmExecutor, S: ProcessSpawne
         send_api_request(vm, "/metrics", "PUT", Some(metrics_system)).await?;
     }
 
+    // The guest clock is frozen at snapshot time and can only be resynchronized once the guest runs and is reachable,
+    // so this is left to Vm::on_restore_sync_clock after startup instead of being done here
     send_api_request(vm, "/snapshot/load", "PUT", Some(&load_snapshot)).await
 }
 
@@ -589,6 +921,8 @@ async fn send_api_request<E: VmmExecutor, S: ProcessSpawner, R: Runtime>(
     request_body: Option<impl Serialize>,
 ) -> Result<(), VmApiError> {
     let response_body: String = send_api_request_internal(vm, route, method, request_body).await?;
+
+    // Whitespace-only bodies, such as a lone trailing newline, don't carry any data and are treated as empty
     if response_body.trim().is_empty() {
         Ok(())
     } else {
@@ -606,41 +940,94 @@ async fn send_api_request_with_response<Resp: DeserializeOwned, E: VmmExecutor,
     serde_json::from_str(&response_json).map_err(VmApiError::SerdeError)
 }
 
+// Unlike send_api_request_with_response, the response body is deserialized from its chunks as they were received,
+// without being copied into a contiguous buffer and a string first, which matters for potentially large bodies
+async fn send_api_request_with_streamed_response<
+    Resp: DeserializeOwned,
+    E: VmmExecutor,
+    S: ProcessSpawner,
+    R: Runtime,
+>(
+    vm: &mut Vm<E, S, R>,
+    route: &str,
+    method: &str,
+    request_body: Option<impl Serialize>,
+) -> Result<Resp, VmApiError> {
+    let mut response = send_api_request_raw(vm, route, method, request_body).await?;
+
+    if !response.status().is_success() {
+        return Err(read_error_response(&mut response).await);
+    }
+
+    let response_chunks = response
+        .read_body_to_chunks()
+        .await
+        .map_err(VmApiError::ResponseBodyReceiveError)?;
+    serde_json::from_reader(response_chunks).map_err(VmApiError::SerdeError)
+}
+
 async fn send_api_request_internal<E: VmmExecutor, S: ProcessSpawner, R: Runtime>(
     vm: &mut Vm<E, S, R>,
     route: &str,
     method: &str,
     request_body: Option<impl Serialize>,
 ) -> Result<String, VmApiError> {
-    let request_builder = Request::builder().method(method);
-    let request = match request_body {
-        Some(body) => {
-            let request_json = serde_json::to_string(&body).map_err(VmApiError::SerdeError)?;
-            request_builder
-                .header(ACCEPT, "application/json")
-                .header(CONTENT_TYPE, "application/json")
-                .body(Full::new(Bytes::from(request_json)))
-        }
-        None => request_builder.body(Full::new(Bytes::new())),
+    let mut response = send_api_request_raw(vm, route, method, request_body).await?;
+
+    if !response.status().is_success() {
+        return Err(read_error_response(&mut response).await);
     }
-    .map_err(VmApiError::RequestBuildError)?;
-    let mut response = vm
-        .vmm_process
-        .send_api_request(route, request)
-        .await
-        .map_err(VmApiError::ConnectionError)?;
-    let response_json = response
+
+    // A 204 response has no body by definition, so whatever may have been sent along with it isn't response data
+    if response.status() == StatusCode::NO_CONTENT {
+        return Ok(String::new());
+    }
+
+    response
         .read_body_to_string()
         .await
-        .map_err(VmApiError::ResponseBodyReceiveError)?;
+        .map_err(VmApiError::ResponseBodyReceiveError)
+}
 
-    if !response.status().is_success() {
-        let api_error: ReprApiError = serde_json::from_str(&response_json).map_err(VmApiError::SerdeError)?;
-        return Err(VmApiError::ReceivedErrorResponse {
+async fn read_error_response(response: &mut Response<Incoming>) -> VmApiError {
+    let response_json = match response.read_body_to_string().await {
+        Ok(response_json) => response_json,
+        Err(err) => return VmApiError::ResponseBodyReceiveError(err),
+    };
+
+    match parse_api_error(&response_json) {
+        Ok(api_error) => VmApiError::ReceivedErrorResponse {
             status_code: response.status(),
             fault_message: api_error.fault_message,
-        });
+        },
+        Err(err) => VmApiError::SerdeError(err),
     }
+}
+
+async fn send_api_request_raw<E: VmmExecutor, S: ProcessSpawner, R: Runtime>(
+    vm: &mut Vm<E, S, R>,
+    route: &str,
+    method: &str,
+    request_body: Option<impl Serialize>,
+) -> Result<Response<Incoming>, VmApiError> {
+    let request = build_api_request(method, request_body)?;
+    vm.vmm_process
+        .send_api_request(route, request)
+        .await
+        .map_err(VmApiError::ConnectionError)
+}
 
-    Ok(response_json)
+fn build_api_request(method: &str, request_body: Option<impl Serialize>) -> Result<Request<Full<Bytes>>, VmApiError> {
+    // Responses are always JSON, while the content type only labels an actually present body
+    let request_builder = Request::builder().method(method).header(ACCEPT, JSON_CONTENT_TYPE);
+    match request_body {
+        Some(body) => {
+            let request_json = serde_json::to_string(&body).map_err(VmApiError::SerdeError)?;
+            request_builder
+                .header(CONTENT_TYPE, JSON_CONTENT_TYPE)
+                .body(Full::new(Bytes::from(request_json)))
+        }
+        None => request_builder.body(Full::new(Bytes::new())),
+    }
+    .map_err(VmApiError::RequestBuildError)
 }
@@ -1,12 +1,25 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::Serialize;
 
-use crate::vm::models::{
-    BalloonDevice, BootSource, CpuTemplate, Drive, EntropyDevice, LoadSnapshot, LoggerSystem, MachineConfiguration,
-    MemoryHotplugConfiguration, MetricsSystem, MmdsConfiguration, NetworkInterface, PmemDevice, VsockDevice,
+use crate::{
+    runtime::Runtime,
+    vm::models::{
+        BalloonDevice, BootSource, CpuTemplate, Drive, EntropyDevice, HugePages, LoadSnapshot, LoggerSystem,
+        MachineConfiguration, MemoryHotplugConfiguration, MetricsSystem, MmdsConfiguration, MmdsVersion,
+        NetworkInterface, PmemDevice, VsockDevice, validate_rate_limiters,
+    },
 };
 
+/// The maximum amount of vCPUs supported by Firecracker for a single VM.
+pub const MAX_VCPU_COUNT: u8 = 32;
+
+/// The path to the procfs file listing the host's mounts, which is used by [find_hugetlbfs_mount].
+pub const PROC_MOUNTS_PATH: &str = "/proc/mounts";
+
+/// The path to the sysfs file indicating whether SMT is active on the host, which is used by [is_host_smt_active].
+pub const SMT_ACTIVE_PATH: &str = "/sys/devices/system/cpu/smt/active";
+
 /// A configuration for a VM, either being new or having been restored from a snapshot. fctools seamlessly exposes
 /// the same amount of features for both new and restored VMs, and this layer abstracts away most snapshot-related
 /// work.
@@ -47,6 +60,17 @@ impl VmConfiguration {
             VmConfiguration::RestoredFromSnapshot { load_snapshot: _, data } => data,
         }
     }
+
+    /// Whether dirty page tracking, which is required for creating diff snapshots, is enabled in this configuration:
+    /// via the [MachineConfiguration] for a new VM, or via the [LoadSnapshot] for a VM restored from a snapshot.
+    pub fn is_dirty_page_tracking_enabled(&self) -> bool {
+        match self {
+            VmConfiguration::New { init_method: _, data } => data.machine_configuration.track_dirty_pages == Some(true),
+            VmConfiguration::RestoredFromSnapshot { load_snapshot, data: _ } => {
+                load_snapshot.track_dirty_pages == Some(true)
+            }
+        }
+    }
 }
 
 /// The full data of various devices associated with a VM. Even when restoring from a snapshot, this information
@@ -93,15 +117,508 @@ pub struct VmConfigurationData {
     pub entropy_device: Option<EntropyDevice>,
 }
 
+impl VmConfigurationData {
+    /// Validate this [VmConfigurationData] against the constraints imposed by Firecracker, so that invalid
+    /// configurations are rejected before boot instead of causing a boot failure.
+    pub fn validate(&self) -> Result<(), ConfigValidationError> {
+        validate_machine_configuration(&self.machine_configuration)?;
+
+        // Firecracker treats a PUT of an existing ID as an update, so a duplicate would silently replace a device
+        let duplicate_drive_ids = find_duplicate_ids(self.drives.iter().map(|drive| drive.drive_id.as_str()));
+        if !duplicate_drive_ids.is_empty() {
+            return Err(ConfigValidationError::DuplicateDriveIds(duplicate_drive_ids));
+        }
+
+        let duplicate_iface_ids = find_duplicate_ids(
+            self.network_interfaces
+                .iter()
+                .map(|network_interface| network_interface.iface_id.as_str()),
+        );
+        if !duplicate_iface_ids.is_empty() {
+            return Err(ConfigValidationError::DuplicateNetworkInterfaceIds(duplicate_iface_ids));
+        }
+
+        if let Some(ref balloon_device) = self.balloon_device {
+            if balloon_device.amount_mib.get() as usize > self.machine_configuration.mem_size_mib {
+                return Err(ConfigValidationError::BalloonSizeExceedsMemSize(
+                    balloon_device.amount_mib.get(),
+                ));
+            }
+        }
+
+        if let Some(ref mmds_configuration) = self.mmds_configuration {
+            validate_mmds_configuration(mmds_configuration, &self.network_interfaces)?;
+        }
+
+        validate_rate_limiters(
+            self.drives
+                .iter()
+                .map(|drive| &drive.rate_limiter)
+                .chain(self.network_interfaces.iter().flat_map(|network_interface| {
+                    [&network_interface.rx_rate_limiter, &network_interface.tx_rate_limiter]
+                }))
+                .chain(
+                    self.entropy_device
+                        .iter()
+                        .map(|entropy_device| &entropy_device.rate_limiter),
+                ),
+        )?;
+
+        Ok(())
+    }
+
+    /// Whether this [VmConfigurationData] requests guest memory to be backed by 2M huge pages. Firecracker allocates
+    /// such memory via an anonymous hugetlbfs-backed memfd, so no hugetlbfs mount is needed for booting the VM, but
+    /// the host must have enough free 2M huge pages reserved.
+    pub fn requires_hugetlbfs(&self) -> bool {
+        self.machine_configuration.huge_pages == Some(HugePages::Hugetlbfs2M)
+    }
+
+    /// Whether this [VmConfigurationData] enables SMT for the guest, in which case SMT must be active on the host,
+    /// since Firecracker rejects enabling SMT on hosts without it.
+    pub fn requires_host_smt(&self) -> bool {
+        self.machine_configuration.smt == Some(true)
+    }
+}
+
+/// Check whether SMT is active on the host by reading [SMT_ACTIVE_PATH] via the given [Runtime], returning [None] if
+/// the host's kernel doesn't expose this information.
+pub async fn is_host_smt_active<R: Runtime>(runtime: &R) -> Result<Option<bool>, std::io::Error> {
+    match runtime.fs_read_to_string(Path::new(SMT_ACTIVE_PATH)).await {
+        Ok(content) => Ok(Some(parse_smt_active(&content))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+fn parse_smt_active(content: &str) -> bool {
+    content.trim() == "1"
+}
+
+/// Find a hugetlbfs mount with 2M pages on the host by reading [PROC_MOUNTS_PATH] via the given [Runtime], returning
+/// the path of its mount point or [None] if no such mount exists. The resulting path can be passed to the jailed
+/// executor in order to bind-mount the hugetlbfs into the jail.
+pub async fn find_hugetlbfs_mount<R: Runtime>(runtime: &R) -> Result<Option<PathBuf>, std::io::Error> {
+    let mounts = runtime.fs_read_to_string(Path::new(PROC_MOUNTS_PATH)).await?;
+    Ok(parse_hugetlbfs_mount(&mounts))
+}
+
+fn parse_hugetlbfs_mount(mounts: &str) -> Option<PathBuf> {
+    mounts.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let (_, mount_point, fs_type, options) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+
+        if fs_type != "hugetlbfs" {
+            return None;
+        }
+
+        // a hugetlbfs mount without an explicit page size uses the default huge page size, which is 2M
+        match options.split(',').find_map(|option| option.strip_prefix("pagesize=")) {
+            Some("2M") | None => Some(PathBuf::from(unescape_mount_point(mount_point))),
+            Some(_) => None,
+        }
+    })
+}
+
+fn unescape_mount_point(mount_point: &str) -> String {
+    // procfs escapes spaces, tabs, newlines and backslashes in mount points as 3-digit octal sequences
+    let mut bytes = Vec::with_capacity(mount_point.len());
+    let mut remaining = mount_point.as_bytes();
+
+    while let Some((&byte, rest)) = remaining.split_first() {
+        if byte == b'\\' && rest.len() >= 3 && rest[..3].iter().all(|digit| (b'0'..=b'7').contains(digit)) {
+            bytes.push(rest[..3].iter().fold(0u8, |acc, digit| (acc << 3) | (digit - b'0')));
+            remaining = &rest[3..];
+        } else {
+            bytes.push(byte);
+            remaining = rest;
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// An error emitted by [VmConfigurationData::validate] when a constraint imposed by Firecracker is violated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigValidationError {
+    /// The vCPU count was outside of the 1 to [MAX_VCPU_COUNT] range.
+    VcpuCountOutOfRange(u8),
+    /// The vCPU count was odd and above 1 while SMT was enabled, which Firecracker doesn't allow.
+    VcpuCountIllegalWithSmt(u8),
+    /// The memory size was zero.
+    MemSizeIsZero,
+    /// The memory size in MiB wasn't a multiple of 2 while 2M huge pages were enabled.
+    MemSizeNotAlignedToHugePages(usize),
+    /// IMDS compatibility was enabled for the MMDS with an [MmdsVersion] that doesn't support it.
+    ImdsCompatUnsupported(MmdsVersion),
+    /// The balloon device's size in MiB exceeded the memory size of the VM.
+    BalloonSizeExceedsMemSize(u32),
+    /// A diff snapshot was requested for a VM that doesn't have dirty page tracking enabled.
+    DirtyPageTrackingDisabled,
+    /// The MMDS was configured to be reachable via a network interface with the given ID that isn't configured.
+    MmdsNetworkInterfaceMissing(String),
+    /// The field with the given name of a [TokenBucket](crate::vm::models::TokenBucket) was zero.
+    TokenBucketFieldIsZero(&'static str),
+    /// A snapshot of a VM with guest memory backed by huge pages was requested to be restored with a
+    /// [MemoryBackendType::File](crate::vm::models::MemoryBackendType::File) backend, which only supports UFFD.
+    HugePagesRequireUffdBackend,
+    /// A snapshot was requested to be created into the given effective path that already exists, while overwriting
+    /// existing snapshot files wasn't allowed.
+    SnapshotFileAlreadyExists(PathBuf),
+    /// SMT was enabled for the guest while SMT isn't active on the host, which Firecracker doesn't allow.
+    HostSmtInactive,
+    /// The contained drive IDs were each shared by multiple drives, which would make Firecracker silently replace the
+    /// earlier drives with the later ones.
+    DuplicateDriveIds(Vec<String>),
+    /// The contained interface IDs were each shared by multiple network interfaces, which would make Firecracker
+    /// silently replace the earlier network interfaces with the later ones.
+    DuplicateNetworkInterfaceIds(Vec<String>),
+    /// The [Resource](crate::vmm::resource::Resource) of a snapshot file or a file memory backend with the contained
+    /// initial path wasn't a moved resource, while Firecracker requires these pre-existing files inside its
+    /// environment.
+    SnapshotResourceNotMoved(PathBuf),
+    /// The contained interface IDs were each targeted by multiple network overrides of a [LoadSnapshot].
+    DuplicateNetworkOverrideIds(Vec<String>),
+    /// A network override of a [LoadSnapshot] targeted a network interface with the given ID that isn't configured.
+    NetworkOverrideInterfaceMissing(String),
+}
+
+impl std::error::Error for ConfigValidationError {}
+
+impl std::fmt::Display for ConfigValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigValidationError::VcpuCountOutOfRange(vcpu_count) => {
+                write!(
+                    f,
+                    "The vCPU count {vcpu_count} is outside of the 1-{MAX_VCPU_COUNT} range"
+                )
+            }
+            ConfigValidationError::VcpuCountIllegalWithSmt(vcpu_count) => {
+                write!(
+                    f,
+                    "The vCPU count {vcpu_count} is neither 1 nor even while SMT is enabled"
+                )
+            }
+            ConfigValidationError::MemSizeIsZero => write!(f, "The memory size is zero"),
+            ConfigValidationError::MemSizeNotAlignedToHugePages(mem_size_mib) => write!(
+                f,
+                "The memory size of {mem_size_mib} MiB is not a multiple of 2 while 2M huge pages are enabled"
+            ),
+            ConfigValidationError::ImdsCompatUnsupported(version) => {
+                write!(
+                    f,
+                    "IMDS compatibility was enabled for the MMDS with the unsupported {version:?} version"
+                )
+            }
+            ConfigValidationError::BalloonSizeExceedsMemSize(amount_mib) => {
+                write!(
+                    f,
+                    "The balloon size of {amount_mib} MiB exceeds the memory size of the VM"
+                )
+            }
+            ConfigValidationError::DirtyPageTrackingDisabled => write!(
+                f,
+                "A diff snapshot was requested while dirty page tracking is disabled for the VM"
+            ),
+            ConfigValidationError::MmdsNetworkInterfaceMissing(iface_id) => write!(
+                f,
+                "The MMDS is configured for the network interface \"{iface_id}\", which isn't configured"
+            ),
+            ConfigValidationError::TokenBucketFieldIsZero(field) => {
+                write!(f, "The \"{field}\" field of a rate limiter's token bucket is zero")
+            }
+            ConfigValidationError::HugePagesRequireUffdBackend => write!(
+                f,
+                "A snapshot of a VM with huge pages can only be restored with the UFFD memory backend"
+            ),
+            ConfigValidationError::SnapshotFileAlreadyExists(path) => {
+                write!(f, "The snapshot file at {} already exists", path.display())
+            }
+            ConfigValidationError::HostSmtInactive => {
+                write!(f, "SMT was enabled for the guest, but SMT is not active on the host")
+            }
+            ConfigValidationError::DuplicateDriveIds(drive_ids) => {
+                write!(f, "The drive IDs {drive_ids:?} are shared by multiple drives")
+            }
+            ConfigValidationError::DuplicateNetworkInterfaceIds(iface_ids) => {
+                write!(
+                    f,
+                    "The interface IDs {iface_ids:?} are shared by multiple network interfaces"
+                )
+            }
+            ConfigValidationError::SnapshotResourceNotMoved(path) => {
+                write!(f, "The snapshot resource at {} is not a moved resource", path.display())
+            }
+            ConfigValidationError::DuplicateNetworkOverrideIds(iface_ids) => {
+                write!(
+                    f,
+                    "The interface IDs {iface_ids:?} are targeted by multiple network overrides"
+                )
+            }
+            ConfigValidationError::NetworkOverrideInterfaceMissing(iface_id) => write!(
+                f,
+                "A network override targets the network interface \"{iface_id}\", which isn't configured"
+            ),
+        }
+    }
+}
+
+fn validate_machine_configuration(machine_configuration: &MachineConfiguration) -> Result<(), ConfigValidationError> {
+    let vcpu_count = machine_configuration.vcpu_count;
+
+    if vcpu_count == 0 || vcpu_count > MAX_VCPU_COUNT {
+        return Err(ConfigValidationError::VcpuCountOutOfRange(vcpu_count));
+    }
+
+    if machine_configuration.smt == Some(true) && vcpu_count > 1 && vcpu_count % 2 != 0 {
+        return Err(ConfigValidationError::VcpuCountIllegalWithSmt(vcpu_count));
+    }
+
+    if machine_configuration.mem_size_mib == 0 {
+        return Err(ConfigValidationError::MemSizeIsZero);
+    }
+
+    if machine_configuration.huge_pages == Some(HugePages::Hugetlbfs2M) && machine_configuration.mem_size_mib % 2 != 0 {
+        return Err(ConfigValidationError::MemSizeNotAlignedToHugePages(
+            machine_configuration.mem_size_mib,
+        ));
+    }
+
+    Ok(())
+}
+
+pub(crate) fn find_duplicate_ids<'a, I: Iterator<Item = &'a str>>(ids: I) -> Vec<String> {
+    let mut seen_ids = Vec::new();
+    let mut duplicate_ids = Vec::new();
+
+    for id in ids {
+        if seen_ids.contains(&id) {
+            if !duplicate_ids.iter().any(|duplicate_id| duplicate_id == id) {
+                duplicate_ids.push(id.to_owned());
+            }
+        } else {
+            seen_ids.push(id);
+        }
+    }
+
+    duplicate_ids
+}
+
+fn validate_mmds_configuration(
+    mmds_configuration: &MmdsConfiguration,
+    network_interfaces: &[NetworkInterface],
+) -> Result<(), ConfigValidationError> {
+    if mmds_configuration.imds_compat == Some(true) && !mmds_configuration.version.supports_imds_compat() {
+        return Err(ConfigValidationError::ImdsCompatUnsupported(mmds_configuration.version));
+    }
+
+    // Firecracker keeps a single MMDS data store for all interfaces, but only lets it be reached through
+    // interfaces that actually exist
+    if let Some(iface_id) = mmds_configuration.network_interfaces.iter().find(|iface_id| {
+        !network_interfaces
+            .iter()
+            .any(|network_interface| network_interface.iface_id == **iface_id)
+    }) {
+        return Err(ConfigValidationError::MmdsNetworkInterfaceMissing(iface_id.clone()));
+    }
+
+    Ok(())
+}
+
 /// A method of initialization used when booting a new (not restored from snapshot) VM.
 /// The performance differences between using both have proven negligible.
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub enum InitMethod {
-    /// Issue sequential calls to the Management API to perform initialization and boot.
+    /// Issue sequential calls to the Management API to perform initialization and boot. Since each call is a
+    /// separate round-trip, [InitMethod::ViaJsonConfiguration] should be preferred when boot latency matters for
+    /// VMs with many devices.
     #[default]
     ViaApiCalls,
     /// Create an intermittent Firecracker JSON configuration that is serialized to the
     /// given virtual path, and pass it to Firecracker in order for initialization and boot
-    /// to be performed automatically.
+    /// to be performed automatically. The virtual path controls the file name and location of
+    /// the configuration within the VMM's environment (for example, the jail), where the file is
+    /// retained after boot.
+    ///
+    /// Firecracker starts the VM on its own right after applying a JSON configuration, so the boot can't be deferred
+    /// to an "InstanceStart" action sent via the Management API, and devices can't be added afterwards. All runtime
+    /// changes, such as rate limiter, balloon or MMDS updates, can still be made via the Management API after boot,
+    /// which effectively combines the boot latency of a JSON configuration with API control at runtime.
     ViaJsonConfiguration(PathBuf),
+    /// Identical to [InitMethod::ViaJsonConfiguration], except that the written JSON configuration
+    /// file is removed once Firecracker's API socket becomes available, so that it doesn't linger
+    /// in the VMM's environment. The removal is best-effort and never fails the boot.
+    ViaTransientJsonConfiguration(PathBuf),
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vm::models::{
+        HugePages, MachineConfiguration, MmdsConfiguration, MmdsVersion, NetworkInterface, RateLimiter, TokenBucket,
+    };
+
+    use super::{
+        ConfigValidationError, find_duplicate_ids, parse_hugetlbfs_mount, parse_smt_active,
+        validate_machine_configuration, validate_mmds_configuration,
+    };
+
+    fn machine_configuration(vcpu_count: u8, mem_size_mib: usize) -> MachineConfiguration {
+        MachineConfiguration {
+            vcpu_count,
+            mem_size_mib,
+            smt: None,
+            track_dirty_pages: None,
+            huge_pages: None,
+        }
+    }
+
+    #[test]
+    fn vcpu_count_is_validated_against_range() {
+        assert_eq!(
+            validate_machine_configuration(&machine_configuration(0, 128)),
+            Err(ConfigValidationError::VcpuCountOutOfRange(0))
+        );
+        assert_eq!(
+            validate_machine_configuration(&machine_configuration(33, 128)),
+            Err(ConfigValidationError::VcpuCountOutOfRange(33))
+        );
+        assert_eq!(validate_machine_configuration(&machine_configuration(32, 128)), Ok(()));
+    }
+
+    #[test]
+    fn vcpu_count_is_validated_with_smt() {
+        let mut configuration = machine_configuration(3, 128);
+        configuration.smt = Some(true);
+        assert_eq!(
+            validate_machine_configuration(&configuration),
+            Err(ConfigValidationError::VcpuCountIllegalWithSmt(3))
+        );
+        configuration.vcpu_count = 1;
+        assert_eq!(validate_machine_configuration(&configuration), Ok(()));
+        configuration.vcpu_count = 4;
+        assert_eq!(validate_machine_configuration(&configuration), Ok(()));
+    }
+
+    #[test]
+    fn mem_size_is_validated() {
+        assert_eq!(
+            validate_machine_configuration(&machine_configuration(1, 0)),
+            Err(ConfigValidationError::MemSizeIsZero)
+        );
+
+        let mut configuration = machine_configuration(1, 127);
+        configuration.huge_pages = Some(HugePages::Hugetlbfs2M);
+        assert_eq!(
+            validate_machine_configuration(&configuration),
+            Err(ConfigValidationError::MemSizeNotAlignedToHugePages(127))
+        );
+        configuration.mem_size_mib = 128;
+        assert_eq!(validate_machine_configuration(&configuration), Ok(()));
+    }
+
+    #[test]
+    fn mmds_network_interfaces_are_validated() {
+        let network_interfaces = [NetworkInterface {
+            iface_id: "eth0".to_owned(),
+            host_dev_name: "tap0".to_owned(),
+            guest_mac: None,
+            rx_rate_limiter: None,
+            tx_rate_limiter: None,
+        }];
+        let mut mmds_configuration = MmdsConfiguration {
+            version: MmdsVersion::V2,
+            network_interfaces: vec!["eth0".to_owned()],
+            ipv4_address: None,
+            imds_compat: None,
+        };
+        assert_eq!(
+            validate_mmds_configuration(&mmds_configuration, &network_interfaces),
+            Ok(())
+        );
+
+        mmds_configuration.network_interfaces.push("eth1".to_owned());
+        assert_eq!(
+            validate_mmds_configuration(&mmds_configuration, &network_interfaces),
+            Err(ConfigValidationError::MmdsNetworkInterfaceMissing("eth1".to_owned()))
+        );
+    }
+
+    #[test]
+    fn imds_compat_is_only_allowed_with_session_tokens() {
+        assert!(!MmdsVersion::V1.supports_imds_compat());
+        assert!(MmdsVersion::V2.supports_imds_compat());
+
+        let mut mmds_configuration = MmdsConfiguration {
+            version: MmdsVersion::V2,
+            network_interfaces: Vec::new(),
+            ipv4_address: None,
+            imds_compat: Some(true),
+        };
+        assert_eq!(validate_mmds_configuration(&mmds_configuration, &[]), Ok(()));
+
+        mmds_configuration.version = MmdsVersion::V1;
+        assert_eq!(
+            validate_mmds_configuration(&mmds_configuration, &[]),
+            Err(ConfigValidationError::ImdsCompatUnsupported(MmdsVersion::V1))
+        );
+
+        mmds_configuration.imds_compat = Some(false);
+        assert_eq!(validate_mmds_configuration(&mmds_configuration, &[]), Ok(()));
+    }
+
+    #[test]
+    fn token_buckets_are_validated() {
+        let token_bucket = TokenBucket {
+            size: 1024,
+            one_time_burst: None,
+            refill_time: 100,
+        };
+        let mut rate_limiter = RateLimiter {
+            bandwidth: token_bucket.clone(),
+            ops: token_bucket,
+        };
+        assert_eq!(rate_limiter.validate(), Ok(()));
+
+        rate_limiter.ops.refill_time = 0;
+        assert_eq!(
+            rate_limiter.validate(),
+            Err(ConfigValidationError::TokenBucketFieldIsZero("refill_time"))
+        );
+
+        rate_limiter.bandwidth.size = 0;
+        assert_eq!(
+            rate_limiter.validate(),
+            Err(ConfigValidationError::TokenBucketFieldIsZero("size"))
+        );
+    }
+
+    #[test]
+    fn duplicate_ids_are_found_once_in_order() {
+        assert!(find_duplicate_ids(["rootfs", "data", "scratch"].into_iter()).is_empty());
+        assert_eq!(
+            find_duplicate_ids(["eth1", "eth0", "eth0", "eth1", "eth0"].into_iter()),
+            vec!["eth0".to_owned(), "eth1".to_owned()]
+        );
+    }
+
+    #[test]
+    fn host_smt_state_is_parsed_from_sysfs() {
+        assert!(parse_smt_active("1\n"));
+        assert!(!parse_smt_active("0\n"));
+        assert!(!parse_smt_active(""));
+    }
+
+    #[test]
+    fn hugetlbfs_mount_is_parsed_from_proc_mounts() {
+        let mounts = "proc /proc proc rw,nosuid,nodev,noexec,relatime 0 0
+hugetlbfs /dev/hugepages1G hugetlbfs rw,relatime,pagesize=1024M 0 0
+hugetlbfs /mnt/huge\\040pages hugetlbfs rw,relatime,pagesize=2M 0 0";
+        assert_eq!(parse_hugetlbfs_mount(mounts), Some("/mnt/huge pages".into()));
+        assert_eq!(
+            parse_hugetlbfs_mount("hugetlbfs /dev/hugepages hugetlbfs rw,relatime 0 0"),
+            Some("/dev/hugepages".into())
+        );
+        assert_eq!(parse_hugetlbfs_mount("proc /proc proc rw 0 0"), None);
+    }
 }
@@ -0,0 +1,118 @@
+use serde_json::{Map, Value};
+
+/// A builder for MMDS contents laid out in the EC2 IMDS-style hierarchy, which is expected by guests that access the
+/// MMDS with IMDS compatibility enabled (via
+/// [MmdsConfiguration::imds_compat](crate::vm::models::MmdsConfiguration::imds_compat)). The produced
+/// [serde_json::Value] nests all metadata under "latest/meta-data/" and the user data under "latest/user-data", and can
+/// be passed to [VmApi::create_mmds_untyped](crate::vm::api::VmApi::create_mmds_untyped) or its update counterpart.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MmdsImdsBuilder {
+    meta_data: Map<String, Value>,
+    user_data: Option<String>,
+}
+
+impl MmdsImdsBuilder {
+    /// Create a new [MmdsImdsBuilder] with no metadata and no user data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Insert a metadata entry at the given key, which may be a slash-separated path (for example,
+    /// "placement/availability-zone") that is expanded into nested objects under "latest/meta-data/". An existing
+    /// entry at the same key is overwritten, including a non-object entry at an intermediate segment of the path.
+    pub fn meta_data<K: AsRef<str>, V: Into<Value>>(mut self, key: K, value: V) -> Self {
+        let mut segments = key.as_ref().split('/').filter(|segment| !segment.is_empty()).peekable();
+        let mut map = &mut self.meta_data;
+
+        while let Some(segment) = segments.next() {
+            if segments.peek().is_none() {
+                map.insert(segment.to_owned(), value.into());
+                break;
+            }
+
+            let entry = map
+                .entry(segment.to_owned())
+                .or_insert_with(|| Value::Object(Map::new()));
+
+            if !entry.is_object() {
+                *entry = Value::Object(Map::new());
+            }
+
+            map = entry.as_object_mut().expect("entry was ensured to be an object");
+        }
+
+        self
+    }
+
+    /// Insert all metadata entries from the given iterator of key-value pairs, as if via [MmdsImdsBuilder::meta_data].
+    pub fn meta_data_entries<K: AsRef<str>, V: Into<Value>, I: IntoIterator<Item = (K, V)>>(self, entries: I) -> Self {
+        entries
+            .into_iter()
+            .fold(self, |builder, (key, value)| builder.meta_data(key, value))
+    }
+
+    /// Set the user data, which is exposed to the guest at "latest/user-data".
+    pub fn user_data<U: Into<String>>(mut self, user_data: U) -> Self {
+        self.user_data = Some(user_data.into());
+        self
+    }
+
+    /// Build the correctly nested [serde_json::Value] of the MMDS contents.
+    pub fn build(self) -> Value {
+        let mut latest = Map::new();
+        latest.insert("meta-data".to_owned(), Value::Object(self.meta_data));
+
+        if let Some(user_data) = self.user_data {
+            latest.insert("user-data".to_owned(), Value::String(user_data));
+        }
+
+        let mut root = Map::new();
+        root.insert("latest".to_owned(), Value::Object(latest));
+        Value::Object(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::MmdsImdsBuilder;
+
+    #[test]
+    fn mmds_imds_builder_nests_entries() {
+        let value = MmdsImdsBuilder::new()
+            .meta_data("instance-id", "i-1234")
+            .meta_data("/placement/availability-zone", "zone-a")
+            .meta_data_entries([("placement/region", "region-a")])
+            .user_data("#!/bin/sh")
+            .build();
+
+        assert_eq!(
+            value,
+            json!({
+                "latest": {
+                    "meta-data": {
+                        "instance-id": "i-1234",
+                        "placement": {
+                            "availability-zone": "zone-a",
+                            "region": "region-a"
+                        }
+                    },
+                    "user-data": "#!/bin/sh"
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn mmds_imds_builder_overwrites_non_object_segments() {
+        let value = MmdsImdsBuilder::new()
+            .meta_data("placement", "none")
+            .meta_data("placement/region", "region-a")
+            .build();
+        assert_eq!(
+            value,
+            json!({ "latest": { "meta-data": { "placement": { "region": "region-a" } } } })
+        );
+    }
+}
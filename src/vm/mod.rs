@@ -2,11 +2,21 @@
 //! that only need to concern themselves with the high-level details of a Firecracker VM.
 //! These abstractions is built on the `vmm-core`, `vmm-executor` and `vmm-process` features.
 
-use std::{path::PathBuf, process::ExitStatus, time::Duration};
+use std::{
+    net::Ipv4Addr,
+    path::PathBuf,
+    process::ExitStatus,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
 
 use api::VmApiError;
 use bytes::Bytes;
-use configuration::{InitMethod, VmConfiguration};
+use configuration::{ConfigValidationError, InitMethod, VmConfiguration, is_host_smt_active};
+use futures_util::{AsyncReadExt, Stream};
 use http::Uri;
 use http_body_util::Full;
 use hyper_client_sockets::{connector::UnixConnector, uri::UnixUri};
@@ -14,18 +24,23 @@ use shutdown::{VmShutdownAction, VmShutdownError, VmShutdownOutcome};
 
 use crate::{
     process_spawner::ProcessSpawner,
-    runtime::{Runtime, util::RuntimeHyperExecutor},
+    runtime::{Runtime, cancellation::CancellationToken, util::RuntimeHyperExecutor},
     vmm::{
-        executor::{VmmExecutor, process_handle::ProcessHandlePipes},
+        executor::{
+            VmmExecutor,
+            process_handle::{ProcessHandle, ProcessHandlePipes},
+        },
+        id::VmId,
         installation::VmmInstallation,
         ownership::{ChangeOwnerError, upgrade_owner},
         process::{VmmProcess, VmmProcessError, VmmProcessState},
-        resource::system::{ResourceSystem, ResourceSystemError},
+        resource::system::{ResourceSystem, ResourceSystemError, ResourceSystemStatistics},
     },
 };
 
 pub mod api;
 pub mod configuration;
+pub mod mmds;
 pub mod models;
 pub mod shutdown;
 pub mod snapshot;
@@ -41,6 +56,76 @@ pub struct Vm<E: VmmExecutor, S: ProcessSpawner, R: Runtime> {
     pub(crate) vmm_process: VmmProcess<E, S, R>,
     is_paused: bool,
     configuration: VmConfiguration,
+    id: VmId,
+    is_cleaned_up: bool,
+    started_at: Option<Instant>,
+    resource_report: ResourceReport,
+    timing: VmTiming,
+    is_shutdown_requested: Arc<AtomicBool>,
+    cancellation_token: Option<CancellationToken>,
+}
+
+const CONSOLE_READ_CHUNK_SIZE: usize = 4096;
+
+/// A summary of what a [Vm] has consumed over its lifecycle, accumulated as the [Vm] is prepared, run and cleaned up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResourceReport {
+    /// The [ResourceSystemStatistics] of the [Vm]'s [ResourceSystem], including the amount of bytes copied and files
+    /// created for the [Vm]'s resources.
+    pub resources: ResourceSystemStatistics,
+    /// The time spent in [Vm::prepare], including the initialization of all resources.
+    pub prepare_duration: Duration,
+    /// The time between the [Vm] being started and its exit being observed, or [None] if it hasn't both started and
+    /// exited yet.
+    pub run_duration: Option<Duration>,
+    /// The highest combined disk usage in bytes of the [Vm]'s initialized resources, as sampled after preparation
+    /// and right before cleanup.
+    pub peak_disk_usage: u64,
+}
+
+/// A breakdown of the time spent in each phase of preparing and starting a [Vm], which shows where the boot latency of
+/// the [Vm] originates from. Each phase is [None] until it has been completed successfully.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct VmTiming {
+    /// The time spent in [VmmProcess::prepare], which prepares the VMM's environment and initializes all resources.
+    pub prepare: Option<Duration>,
+    /// The time spent in [VmmProcess::invoke], which spawns the VMM process and initializes the resources it produces.
+    pub invoke: Option<Duration>,
+    /// The time between the VMM process having been invoked and its API socket becoming available.
+    pub socket_wait: Option<Duration>,
+    /// The time spent initializing the VM via the Management API once the socket became available, which includes
+    /// booting the VM or loading its snapshot. This is close to zero when a JSON configuration is used, since
+    /// Firecracker then boots the VM before its API server becomes available.
+    pub api_init: Option<Duration>,
+}
+
+/// The default IPv4 address of the MMDS within the guest, used by Firecracker when no other address is configured.
+const DEFAULT_MMDS_IPV4_ADDRESS: Ipv4Addr = Ipv4Addr::new(169, 254, 169, 254);
+
+/// A network interface of a [Vm] as seen from both the host and the guest, combining what is spread across the
+/// [NetworkInterface](models::NetworkInterface)s, the boot arguments and the
+/// [MmdsConfiguration](models::MmdsConfiguration) of the [Vm]'s configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuestInterface {
+    /// The ID of the network interface in Firecracker.
+    pub iface_id: String,
+    /// The name of the tap device on the host backing the network interface.
+    pub host_dev_name: String,
+    /// The name of the network interface within the guest, which is "ethN" for the Nth configured network interface,
+    /// as Firecracker attaches network devices in the order they were configured.
+    pub guest_dev_name: String,
+    /// The MAC address of the network interface within the guest, if one was configured.
+    pub guest_mac: Option<String>,
+    /// The IPv4 address of the network interface within the guest, if it is statically configured via an "ip="
+    /// kernel parameter in the boot arguments.
+    pub guest_ip: Option<Ipv4Addr>,
+    /// The IPv4 address at which the MMDS is reachable from the guest via this network interface, or [None] if the
+    /// MMDS isn't exposed on it.
+    pub mmds_ipv4_address: Option<Ipv4Addr>,
+    /// The [RateLimiter](models::RateLimiter) for received traffic, if one was configured.
+    pub rx_rate_limiter: Option<models::RateLimiter>,
+    /// The [RateLimiter](models::RateLimiter) for transmitted traffic, if one was configured.
+    pub tx_rate_limiter: Option<models::RateLimiter>,
 }
 
 /// The high-level state of a [Vm]. Unlike the state of a [VmmProcess], this state tracks the virtual machine and its operating state,
@@ -89,15 +174,69 @@ pub enum VmError {
     /// A future waiting for the Management API Unix socket to become available timed out in accordance with the
     /// provided timeout [Duration].
     SocketWaitTimeout,
+    /// The VMM process exited or crashed, transitioning into the contained [VmState], before the Management API Unix
+    /// socket became available.
+    ExitedDuringSocketWait(VmState),
+    /// A future waiting for the files of a [VmSnapshot](snapshot::VmSnapshot) to stop changing in size timed out in
+    /// accordance with the provided timeout [Duration].
+    SnapshotStabilityWaitTimeout,
     /// Using a [VmConfiguration] with a disabled Management API Unix socket was attempted, which is not supported
     /// by the VM layer.
     DisabledApiSocketIsUnsupported,
     /// A [ResourceSystemError] occurred.
     ResourceSystemError(ResourceSystemError),
+    /// The [VmConfiguration] was invalid according to a [ConfigValidationError].
+    InvalidConfiguration(ConfigValidationError),
+    /// A [VmQuiescer](snapshot::VmQuiescer) failed to quiesce or unquiesce the guest with the given boxed error.
+    QuiesceError(Box<dyn std::error::Error + Send + Sync>),
+    /// Cleaning up the [Vm] was attempted after it had already been cleaned up successfully.
+    AlreadyCleanedUp,
+    /// An I/O error occurred while setting up the background task watching for a guest-initiated shutdown.
+    ExitWatcherError(std::io::Error),
+    /// A [VmShutdownError] occurred while shutting down the [Vm] as part of another operation.
+    ShutdownError(VmShutdownError),
+    /// A [VmClockSynchronizer](snapshot::VmClockSynchronizer) failed to resynchronize the guest clock with the given
+    /// boxed error.
+    ClockSyncError(Box<dyn std::error::Error + Send + Sync>),
+    /// The operation was aborted since the [CancellationToken] set via [Vm::set_cancellation_token] was cancelled.
+    Cancelled,
 }
 
 impl std::error::Error for VmError {}
 
+impl VmError {
+    /// Get the HTTP status code that most appropriately represents this [VmError] when it is exposed by an HTTP API
+    /// built on top of fctools: for example, 409 for state check errors, 400 for invalid configurations and 503 for
+    /// an unavailable management API.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            VmError::ProcessError(err) => err.http_status(),
+            VmError::ApiError(err) => err.http_status(),
+            VmError::StateCheckError(_) | VmError::AlreadyCleanedUp => 409,
+            VmError::InvalidConfiguration(_) | VmError::DisabledApiSocketIsUnsupported => 400,
+            VmError::SocketWaitTimeout | VmError::SnapshotStabilityWaitTimeout => 504,
+            _ => 500,
+        }
+    }
+
+    /// Get a client-safe message describing this [VmError], which, unlike its [Display](std::fmt::Display)
+    /// implementation, doesn't leak any details such as host paths or internal error messages.
+    pub fn public_message(&self) -> &'static str {
+        match self {
+            VmError::ProcessError(err) => err.public_message(),
+            VmError::ApiError(err) => err.public_message(),
+            VmError::StateCheckError(_) | VmError::AlreadyCleanedUp => {
+                "The VM is in a state that doesn't permit this operation"
+            }
+            VmError::InvalidConfiguration(_) | VmError::DisabledApiSocketIsUnsupported => {
+                "The VM configuration is invalid"
+            }
+            VmError::SocketWaitTimeout | VmError::SnapshotStabilityWaitTimeout => "The VM operation timed out",
+            _ => "An internal error occurred while managing the VM",
+        }
+    }
+}
+
 impl std::fmt::Display for VmError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -114,11 +253,27 @@ impl std::fmt::Display for VmError {
                 write!(f, "Serialization of the transient JSON configuration failed: {err}")
             }
             VmError::SocketWaitTimeout => write!(f, "The wait for the API socket to become available timed out"),
+            VmError::ExitedDuringSocketWait(state) => write!(
+                f,
+                "The VMM process transitioned into the {state} state before the API socket became available"
+            ),
+            VmError::SnapshotStabilityWaitTimeout => {
+                write!(f, "The wait for the snapshot files to stop changing in size timed out")
+            }
             VmError::DisabledApiSocketIsUnsupported => write!(
                 f,
                 "Attempted to use a VM configuration with a disabled API socket, which is not supported"
             ),
             VmError::ResourceSystemError(err) => write!(f, "A resource system error occurred: {err}"),
+            VmError::InvalidConfiguration(err) => write!(f, "The VM configuration was invalid: {err}"),
+            VmError::QuiesceError(err) => write!(f, "Quiescing or unquiescing the guest failed: {err}"),
+            VmError::AlreadyCleanedUp => write!(f, "The VM has already been cleaned up"),
+            VmError::ExitWatcherError(err) => {
+                write!(f, "Setting up the watcher for a guest-initiated shutdown failed: {err}")
+            }
+            VmError::ShutdownError(err) => write!(f, "Shutting down the VM failed: {err}"),
+            VmError::ClockSyncError(err) => write!(f, "Resynchronizing the guest clock failed: {err}"),
+            VmError::Cancelled => write!(f, "The operation was cancelled via a cancellation token"),
         }
     }
 }
@@ -153,31 +308,82 @@ impl std::fmt::Display for VmStateCheckError {
 
 impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
     /// Prepare the full environment of a [Vm] without booting it. This requires a [VmConfiguration], in which all resources
-    /// are created within the given [ResourceSystem], a [VmmExecutor] and a [VmmInstallation].
+    /// are created within the given [ResourceSystem], a [VmmExecutor] and a [VmmInstallation]. A unique [VmId] is
+    /// generated for the [Vm].
     pub async fn prepare(
         executor: E,
         resource_system: ResourceSystem<S, R>,
         installation: VmmInstallation,
         configuration: VmConfiguration,
     ) -> Result<Self, VmError> {
+        Self::prepare_with_id(executor, resource_system, installation, configuration, VmId::generate()).await
+    }
+
+    /// Prepare the full environment of a [Vm] like with [Vm::prepare], but assign it the given user-provided [VmId].
+    /// In order to correlate the jail or Firecracker instance with the [Vm], the [VmmExecutor] derives the ID of the
+    /// VMM from the [VmId] via [VmmExecutor::derive_id], unless one was explicitly set on it.
+    pub async fn prepare_with_id(
+        mut executor: E,
+        resource_system: ResourceSystem<S, R>,
+        installation: VmmInstallation,
+        configuration: VmConfiguration,
+        id: VmId,
+    ) -> Result<Self, VmError> {
+        let prepare_start = Instant::now();
+        executor.derive_id(&id);
+
         if executor.get_socket_path(&installation).is_none() {
             return Err(VmError::DisabledApiSocketIsUnsupported);
         }
 
+        configuration
+            .get_data()
+            .validate()
+            .map_err(VmError::InvalidConfiguration)?;
+
+        if let VmConfiguration::RestoredFromSnapshot {
+            ref load_snapshot,
+            data: _,
+        } = configuration
+        {
+            load_snapshot.validate().map_err(VmError::InvalidConfiguration)?;
+        }
+
+        if configuration.get_data().requires_host_smt()
+            && is_host_smt_active(&resource_system.runtime)
+                .await
+                .map_err(VmError::FilesystemError)?
+                == Some(false)
+        {
+            return Err(VmError::InvalidConfiguration(ConfigValidationError::HostSmtInactive));
+        }
+
         let mut vmm_process = VmmProcess::new(executor, resource_system, installation);
 
         vmm_process.prepare().await.map_err(VmError::ProcessError)?;
 
-        Ok(Self {
+        let mut vm = Self {
             vmm_process,
             is_paused: false,
             configuration,
-        })
+            id,
+            is_cleaned_up: false,
+            started_at: None,
+            is_shutdown_requested: Arc::new(AtomicBool::new(false)),
+            cancellation_token: None,
+            timing: VmTiming::default(),
+            resource_report: ResourceReport {
+                prepare_duration: prepare_start.elapsed(),
+                ..Default::default()
+            },
+        };
+        vm.sample_disk_usage().await;
+        Ok(vm)
     }
 
     /// Retrieve the [VmState] of the [Vm], based on internal tracking and that being done by the [VmmProcess].
     pub fn get_state(&mut self) -> VmState {
-        match self.vmm_process.get_state() {
+        let state = match self.vmm_process.get_state() {
             VmmProcessState::Started => match self.is_paused {
                 true => VmState::Paused,
                 false => VmState::Running,
@@ -185,26 +391,95 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
             VmmProcessState::Exited => VmState::Exited,
             VmmProcessState::Crashed(exit_status) => VmState::Crashed(exit_status),
             _ => VmState::NotStarted,
+        };
+
+        if let (VmState::Exited | VmState::Crashed(_), Some(started_at)) = (state, self.started_at) {
+            self.resource_report
+                .run_duration
+                .get_or_insert_with(|| started_at.elapsed());
         }
+
+        state
+    }
+
+    /// Set the [CancellationToken] that is polled by long-running operations of this [Vm], such as [Vm::start], in
+    /// order to abort them early with a [VmError::Cancelled] once it is cancelled.
+    pub fn set_cancellation_token(&mut self, cancellation_token: CancellationToken) {
+        self.cancellation_token = Some(cancellation_token);
     }
 
     /// Start/boot the [Vm] and perform all necessary initialization steps according to the [VmConfiguration].
+    ///
+    /// If the [CancellationToken] set via [Vm::set_cancellation_token] is cancelled before the [Vm] has finished
+    /// starting, the VMM process is killed if it had already been spawned, and the [Vm] can then only be cleaned up.
     pub async fn start(&mut self, socket_wait_timeout: Duration) -> Result<(), VmError> {
+        let Some(cancellation_token) = self.cancellation_token.clone() else {
+            return self.start_inner(socket_wait_timeout).await;
+        };
+
+        match cancellation_token
+            .run_until_cancelled(self.start_inner(socket_wait_timeout))
+            .await
+        {
+            Some(result) => result,
+            None => {
+                // The VMM process must not outlive an aborted start, as nothing would otherwise ever shut it down
+                if self.vmm_process.get_state() == VmmProcessState::Started {
+                    let _ = self.vmm_process.send_sigkill();
+                    let _ = self.vmm_process.wait_for_exit().await;
+                }
+
+                Err(VmError::Cancelled)
+            }
+        }
+    }
+
+    async fn start_inner(&mut self, socket_wait_timeout: Duration) -> Result<(), VmError> {
         self.ensure_state(VmState::NotStarted)
             .map_err(VmError::StateCheckError)?;
+
+        if let VmConfiguration::New { ref mut data, .. } = self.configuration {
+            if let Some(ref boot_args_resource) = data.boot_source.boot_args_resource {
+                let boot_args_path = boot_args_resource
+                    .get_effective_path()
+                    .unwrap_or_else(|| boot_args_resource.get_initial_path());
+                let boot_args = self
+                    .vmm_process
+                    .resource_system
+                    .runtime
+                    .fs_read_to_string(boot_args_path)
+                    .await
+                    .map_err(VmError::FilesystemError)?;
+                data.boot_source.boot_args = Some(boot_args.trim().to_owned());
+            }
+        }
         let socket_path = self
             .vmm_process
             .get_socket_path()
             .ok_or(VmError::DisabledApiSocketIsUnsupported)?;
 
         let mut config_path = None;
-        if let VmConfiguration::New {
-            init_method: InitMethod::ViaJsonConfiguration(ref config_local_path),
-            ref data,
-        } = self.configuration
-        {
+        let mut transient_config_effective_path = None;
+        let json_configuration = match self.configuration {
+            VmConfiguration::New {
+                init_method: InitMethod::ViaJsonConfiguration(ref config_local_path),
+                ref data,
+            } => Some((config_local_path, data, false)),
+            VmConfiguration::New {
+                init_method: InitMethod::ViaTransientJsonConfiguration(ref config_local_path),
+                ref data,
+            } => Some((config_local_path, data, true)),
+            _ => None,
+        };
+
+        if let Some((config_local_path, data, is_transient)) = json_configuration {
             let config_effective_path = self.vmm_process.resolve_effective_path(config_local_path.clone());
             config_path = Some(config_local_path.clone());
+
+            if is_transient {
+                transient_config_effective_path = Some(config_effective_path.clone());
+            }
+
             upgrade_owner(
                 &config_effective_path,
                 self.vmm_process.resource_system.ownership_model,
@@ -229,15 +504,18 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
             .invoke(config_path)
             .await
             .map_err(VmError::ProcessError)?;
+        self.started_at = Some(Instant::now());
 
         let client = hyper_util::client::legacy::Builder::new(RuntimeHyperExecutor(
             self.vmm_process.resource_system.runtime.clone(),
         ))
         .build::<_, Full<Bytes>>(UnixConnector::<R::SocketBackend>::new());
 
-        self.vmm_process
-            .resource_system
-            .runtime
+        // The process handle tracks the actual "firecracker" process even when the jailer is daemonized and the spawned
+        // launcher has already exited successfully, so an exit observed here means that the VMM itself is gone
+        let runtime = self.vmm_process.resource_system.runtime.clone();
+        let vmm_process = &mut self.vmm_process;
+        runtime
             .timeout(socket_wait_timeout, async move {
                 loop {
                     if client
@@ -245,13 +523,40 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
                         .await
                         .is_ok()
                     {
-                        break;
+                        return Ok(());
+                    }
+
+                    match vmm_process.get_state() {
+                        VmmProcessState::Exited => return Err(VmError::ExitedDuringSocketWait(VmState::Exited)),
+                        VmmProcessState::Crashed(exit_status) => {
+                            return Err(VmError::ExitedDuringSocketWait(VmState::Crashed(exit_status)));
+                        }
+                        _ => {}
                     }
                 }
             })
             .await
-            .map_err(|_| VmError::SocketWaitTimeout)?;
+            .map_err(|_| VmError::SocketWaitTimeout)??;
+        self.timing.socket_wait = self.started_at.map(|started_at| started_at.elapsed());
+
+        // Firecracker has fully consumed the configuration file once its API server becomes available. The file's
+        // ownership was downgraded along with the environment, and failing to remove it mustn't fail a running VM.
+        if let Some(config_effective_path) = transient_config_effective_path {
+            let resource_system = &self.vmm_process.resource_system;
+            if upgrade_owner(
+                &config_effective_path,
+                resource_system.ownership_model,
+                &resource_system.process_spawner,
+                &resource_system.runtime,
+            )
+            .await
+            .is_ok()
+            {
+                let _ = resource_system.runtime.fs_remove_file(&config_effective_path).await;
+            }
+        }
 
+        let api_init_start = Instant::now();
         match self.configuration.clone() {
             VmConfiguration::New { init_method, data } => {
                 if init_method == InitMethod::ViaApiCalls {
@@ -265,6 +570,7 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
             }
         }
 
+        self.timing.api_init = Some(api_init_start.elapsed());
         Ok(())
     }
 
@@ -275,13 +581,105 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
         &mut self,
         actions: I,
     ) -> Result<VmShutdownOutcome, VmShutdownError> {
+        self.is_shutdown_requested.store(true, Ordering::Release);
         shutdown::apply(self, actions.into_iter()).await
     }
 
-    /// Clean up the full environment of this [Vm] after it being [VmState::Exited] or [VmState::Crashed].
+    /// Watch for a guest-initiated shutdown of this [Vm], such as the guest running "poweroff" or "reboot", in a
+    /// background task spawned onto the [Runtime], and invoke the given callback once the VMM process exits gracefully
+    /// because of it. This allows supervisors to react to the [Vm] transitioning into [VmState::Exited] without polling
+    /// [Vm::get_state].
+    ///
+    /// The callback isn't invoked if the VMM process crashes, or if a shutdown was requested via [Vm::shutdown] before
+    /// the exit, since such an exit isn't initiated by the guest. It also isn't invoked if the exit status of the VMM
+    /// process can't be determined because the process was already reaped, for example by a concurrent
+    /// [Vm::get_state] call, since a crash can't be ruled out then. The background task ends once the VMM process
+    /// exits.
+    pub fn watch_guest_shutdown<F: FnOnce() + Send + 'static>(&mut self, callback: F) -> Result<(), VmError> {
+        self.ensure_paused_or_running().map_err(VmError::StateCheckError)?;
+        let pid = self.vmm_process.get_pid().ok_or_else(|| {
+            VmError::ExitWatcherError(std::io::Error::other("The PID of the VMM process is unavailable"))
+        })?;
+
+        // a separate pidfd-backed handle is needed, since the VMM process's own handle can't be moved into the task
+        let runtime = self.vmm_process.resource_system.runtime.clone();
+        let mut process_handle = ProcessHandle::from_pidfd(pid, runtime.clone()).map_err(VmError::ExitWatcherError)?;
+        let is_shutdown_requested = self.is_shutdown_requested.clone();
+
+        runtime.spawn_task(async move {
+            if let Ok(Some(exit_status)) = process_handle.wait_for_exit_status().await {
+                if exit_status.success() && !is_shutdown_requested.load(Ordering::Acquire) {
+                    callback();
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    /// Clean up the full environment of this [Vm] after it being [VmState::Exited] or [VmState::Crashed]. Once this
+    /// succeeds, subsequent calls fail with [VmError::AlreadyCleanedUp], while a failed cleanup can be retried.
     pub async fn cleanup(&mut self) -> Result<(), VmError> {
         self.ensure_exited_or_crashed().map_err(VmError::StateCheckError)?;
-        self.vmm_process.cleanup().await.map_err(VmError::ProcessError)
+
+        if self.is_cleaned_up {
+            return Err(VmError::AlreadyCleanedUp);
+        }
+
+        self.sample_disk_usage().await;
+        self.vmm_process.cleanup().await.map_err(VmError::ProcessError)?;
+        self.is_cleaned_up = true;
+        Ok(())
+    }
+
+    /// Clean up the full environment of this [Vm] after it being [VmState::Exited] or [VmState::Crashed] in a
+    /// best-effort manner, which unlike [Vm::cleanup] continues past individual failures, such as a single resource
+    /// that couldn't be disposed, so that as much disk space as possible is reclaimed. All encountered errors are
+    /// returned, and only an empty [Vec] marks the [Vm] as cleaned up, so a partially failed cleanup can be retried.
+    pub async fn cleanup_best_effort(&mut self) -> Vec<VmError> {
+        if let Err(err) = self.ensure_exited_or_crashed() {
+            return vec![VmError::StateCheckError(err)];
+        }
+
+        if self.is_cleaned_up {
+            return vec![VmError::AlreadyCleanedUp];
+        }
+
+        self.sample_disk_usage().await;
+        let errors = self
+            .vmm_process
+            .cleanup_best_effort()
+            .await
+            .into_iter()
+            .map(VmError::ProcessError)
+            .collect::<Vec<_>>();
+        self.is_cleaned_up = errors.is_empty();
+        errors
+    }
+
+    /// Whether the environment of this [Vm] has been successfully cleaned up via [Vm::cleanup]. Unlike the [VmState],
+    /// which remains [VmState::Exited] or [VmState::Crashed] after cleanup, this distinguishes a [Vm] whose process
+    /// is gone but whose resources are still on disk from one that is fully cleaned up.
+    pub fn is_cleaned_up(&self) -> bool {
+        self.is_cleaned_up
+    }
+
+    /// Get the [ResourceReport] summarizing what this [Vm] has consumed so far. Once the [Vm] has been cleaned up, the
+    /// report is final and covers the [Vm]'s entire lifecycle.
+    pub fn resource_report(&self) -> ResourceReport {
+        ResourceReport {
+            resources: self.vmm_process.get_resource_system().get_statistics(),
+            ..self.resource_report
+        }
+    }
+
+    /// Get the [VmTiming] breakdown of the phases of preparing and starting this [Vm] that have been completed so far.
+    pub fn timing_breakdown(&self) -> VmTiming {
+        VmTiming {
+            prepare: self.vmm_process.get_prepare_duration(),
+            invoke: self.vmm_process.get_invoke_duration(),
+            ..self.timing
+        }
     }
 
     /// Take out the [ProcessHandlePipes] of the underlying process handle if possible.
@@ -290,11 +688,90 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
         self.vmm_process.take_pipes().map_err(VmError::ProcessError)
     }
 
+    /// Get a [Stream] of the live output of the guest's serial console, which Firecracker writes to the VMM's stdout
+    /// when "console=ttyS0" is passed in the boot arguments. Each item is a chunk of raw bytes in the order they
+    /// were read from the stdout pipe, and the [Stream] ends when the pipe is closed, typically after the VMM exits.
+    ///
+    /// This takes out the [ProcessHandlePipes] of the underlying process handle, so it can only be called once
+    /// and is mutually exclusive with [Vm::take_pipes]. The stdin and stderr pipes are kept open for as long as
+    /// the [Stream] isn't dropped.
+    pub fn console_stream(
+        &mut self,
+    ) -> Result<impl Stream<Item = Result<Bytes, std::io::Error>> + Send + use<E, S, R>, VmError> {
+        let pipes = self.take_pipes()?;
+
+        Ok(futures_util::stream::unfold(pipes, |mut pipes| async move {
+            let mut buf = vec![0; CONSOLE_READ_CHUNK_SIZE];
+
+            match pipes.stdout.read(&mut buf).await {
+                Ok(0) => None,
+                Ok(length) => {
+                    buf.truncate(length);
+                    Some((Ok(Bytes::from(buf)), pipes))
+                }
+                Err(err) => Some((Err(err), pipes)),
+            }
+        }))
+    }
+
+    /// Get a shared reference to the [Vm]'s [VmId].
+    pub fn get_id(&self) -> &VmId {
+        &self.id
+    }
+
     /// Get a shared reference to the [Vm]'s [VmConfiguration].
     pub fn get_configuration(&self) -> &VmConfiguration {
         &self.configuration
     }
 
+    /// Get a shared slice of the [Drive](models::Drive)s the [Vm] was configured with. These don't reflect updates made
+    /// to the drives after boot, for which [VmApi::get_drives](api::VmApi::get_drives) should be used instead.
+    pub fn get_configured_drives(&self) -> &[models::Drive] {
+        &self.configuration.get_data().drives
+    }
+
+    /// Get the [GuestInterface]s of the [Vm] in the order the network interfaces were configured. Like
+    /// [Vm::get_configured_drives], these are derived from the [Vm]'s configuration and don't reflect rate limiter
+    /// updates made after boot.
+    pub fn network_topology(&self) -> Vec<GuestInterface> {
+        let data = self.configuration.get_data();
+        let boot_ip = data.boot_source.boot_args.as_deref().and_then(parse_boot_args_ip);
+
+        data.network_interfaces
+            .iter()
+            .enumerate()
+            .map(|(index, network_interface)| {
+                let guest_dev_name = format!("eth{index}");
+                // An "ip=" parameter without a device name applies to the first network interface the kernel finds
+                let guest_ip = boot_ip.as_ref().and_then(|(ip, device)| match device {
+                    Some(device) if *device == guest_dev_name => Some(*ip),
+                    None if index == 0 => Some(*ip),
+                    _ => None,
+                });
+                let mmds_ipv4_address = data.mmds_configuration.as_ref().and_then(|mmds_configuration| {
+                    match mmds_configuration
+                        .network_interfaces
+                        .contains(&network_interface.iface_id)
+                    {
+                        true => Some(mmds_configuration.ipv4_address.unwrap_or(DEFAULT_MMDS_IPV4_ADDRESS)),
+                        false => None,
+                    }
+                });
+
+                GuestInterface {
+                    iface_id: network_interface.iface_id.clone(),
+                    host_dev_name: network_interface.host_dev_name.clone(),
+                    guest_dev_name,
+                    guest_mac: network_interface.guest_mac.clone(),
+                    guest_ip,
+                    mmds_ipv4_address,
+                    rx_rate_limiter: network_interface.rx_rate_limiter.clone(),
+                    tx_rate_limiter: network_interface.tx_rate_limiter.clone(),
+                }
+            })
+            .collect()
+    }
+
     /// Transforms a given local resource path into an effective resource path using the underlying [VmmProcess].
     /// This should be used with care and only in cases when the facilities of the [ResourceSystem] prove to be insufficient.
     pub fn resolve_effective_path<P: Into<PathBuf>>(&self, local_path: P) -> PathBuf {
@@ -311,6 +788,34 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
         self.vmm_process.get_resource_system_mut()
     }
 
+    /// Consume this [Vm] and return its underlying [VmmProcess], in order to downshift to the lower layer without
+    /// losing the running process, for example to perform an operation the [Vm] doesn't expose.
+    ///
+    /// All VM-level state tracking is abandoned at this point: the [VmConfiguration], pause state, [ResourceReport] and
+    /// any pending guest shutdown watch are dropped, so the [VmmProcess] has to be shut down and cleaned up via its own
+    /// facilities (e.g. [VmmProcess::send_ctrl_alt_del] and [VmmProcess::cleanup]).
+    pub fn into_vmm_process(self) -> VmmProcess<E, S, R> {
+        self.vmm_process
+    }
+
+    async fn sample_disk_usage(&mut self) {
+        let resource_system = self.vmm_process.get_resource_system();
+        let mut disk_usage = 0;
+
+        for resource in resource_system.get_resources() {
+            // resources that are missing or were already disposed of don't take up any disk space
+            if let Some(effective_path) = resource.get_effective_path() {
+                disk_usage += resource_system
+                    .runtime
+                    .fs_disk_usage(effective_path)
+                    .await
+                    .unwrap_or_default();
+            }
+        }
+
+        self.resource_report.peak_disk_usage = self.resource_report.peak_disk_usage.max(disk_usage);
+    }
+
     #[inline]
     fn ensure_state(&mut self, expected_state: VmState) -> Result<(), VmStateCheckError> {
         let current_state = self.get_state();
@@ -351,3 +856,16 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
         Err(VmStateCheckError::ExitedOrCrashed { actual: current_state })
     }
 }
+
+// The kernel's "ip=" parameter has the format of "ip=<client-ip>:<server-ip>:<gw-ip>:<netmask>:<hostname>:<device>:...",
+// where only the client IP and the optional device name are relevant. The last occurrence takes precedence.
+fn parse_boot_args_ip(boot_args: &str) -> Option<(Ipv4Addr, Option<&str>)> {
+    let value = boot_args
+        .split_whitespace()
+        .filter_map(|parameter| parameter.strip_prefix("ip="))
+        .next_back()?;
+    let mut fields = value.split(':');
+    let ip = fields.next()?.parse().ok()?;
+    let device = fields.nth(4).filter(|device| !device.is_empty());
+    Some((ip, device))
+}
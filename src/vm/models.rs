@@ -1,8 +1,14 @@
-use std::net::Ipv4Addr;
+use std::{net::Ipv4Addr, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use crate::vmm::{arguments::VmmLogLevel, resource::Resource};
+use crate::{
+    vm::configuration::{ConfigValidationError, VmConfiguration, VmConfigurationData, find_duplicate_ids},
+    vmm::{
+        arguments::VmmLogLevel,
+        resource::{Resource, ResourceType},
+    },
+};
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub(crate) struct ReprAction {
@@ -18,7 +24,7 @@ pub(crate) enum ReprActionType {
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct BalloonDevice {
-    pub amount_mib: i32,
+    pub amount_mib: BalloonSizeMib,
     pub deflate_on_oom: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stats_polling_interval_s: Option<i32>,
@@ -32,7 +38,75 @@ pub struct BalloonDevice {
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct UpdateBalloonDevice {
-    pub amount_mib: u16,
+    pub amount_mib: BalloonSizeMib,
+}
+
+/// The target size of a balloon device in MiB, which can never be negative. It is (de-)serialized as a plain integer,
+/// and the conversion from a signed integer is fallible, so that negative sizes are rejected before reaching
+/// Firecracker. Whether the size fits into the VM's memory is checked by
+/// [VmConfigurationData::validate](crate::vm::configuration::VmConfigurationData::validate).
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[serde(try_from = "i64", into = "u32")]
+pub struct BalloonSizeMib(u32);
+
+impl BalloonSizeMib {
+    /// Create a new [BalloonSizeMib] from the given non-negative amount of MiB.
+    pub const fn new(amount_mib: u32) -> Self {
+        Self(amount_mib)
+    }
+
+    /// Get the amount of MiB of this [BalloonSizeMib].
+    pub const fn get(&self) -> u32 {
+        self.0
+    }
+}
+
+/// An error emitted when converting a signed or too large integer into a [BalloonSizeMib].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidBalloonSizeError(pub i64);
+
+impl std::error::Error for InvalidBalloonSizeError {}
+
+impl std::fmt::Display for InvalidBalloonSizeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "The balloon size of {} MiB is negative or too large", self.0)
+    }
+}
+
+impl From<u16> for BalloonSizeMib {
+    fn from(value: u16) -> Self {
+        Self(value.into())
+    }
+}
+
+impl From<u32> for BalloonSizeMib {
+    fn from(value: u32) -> Self {
+        Self(value)
+    }
+}
+
+impl From<BalloonSizeMib> for u32 {
+    fn from(value: BalloonSizeMib) -> Self {
+        value.0
+    }
+}
+
+impl TryFrom<i32> for BalloonSizeMib {
+    type Error = InvalidBalloonSizeError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Self::try_from(i64::from(value))
+    }
+}
+
+impl TryFrom<i64> for BalloonSizeMib {
+    type Error = InvalidBalloonSizeError;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        u32::try_from(value)
+            .map(Self)
+            .map_err(|_| InvalidBalloonSizeError(value))
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
@@ -104,6 +178,20 @@ pub struct BootSource {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "initrd_path")]
     pub initrd: Option<Resource>,
+    #[serde(skip)]
+    pub boot_args_resource: Option<Resource>,
+}
+
+impl BootSource {
+    /// Source the boot arguments from the contents of the given [Resource] instead of the inline
+    /// [BootSource::boot_args], which keeps externally managed boot arguments out of the configuration. The
+    /// [Resource], which should usually be a moved resource, is read via the [Runtime](crate::runtime::Runtime) when
+    /// the [Vm](crate::vm::Vm) is started, and its contents with surrounding whitespace trimmed then replace the
+    /// inline boot arguments.
+    pub fn boot_args_from_resource(mut self, resource: Resource) -> Self {
+        self.boot_args_resource = Some(resource);
+        self
+    }
 }
 
 #[derive(Serialize, Debug, Clone, PartialEq, Eq)]
@@ -215,6 +303,19 @@ pub struct Drive {
     pub socket: Option<Resource>,
 }
 
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct DriveInfo {
+    pub drive_id: String,
+    pub is_root_device: bool,
+    pub cache_type: Option<DriveCacheType>,
+    pub partuuid: Option<String>,
+    pub is_read_only: Option<bool>,
+    pub path_on_host: Option<PathBuf>,
+    pub rate_limiter: Option<RateLimiter>,
+    pub io_engine: Option<DriveIoEngine>,
+    pub socket: Option<PathBuf>,
+}
+
 #[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct UpdateDrive {
     pub drive_id: String,
@@ -245,6 +346,14 @@ pub struct RateLimiter {
     pub ops: TokenBucket,
 }
 
+impl RateLimiter {
+    /// Validate both [TokenBucket]s of this [RateLimiter] via [TokenBucket::validate].
+    pub fn validate(&self) -> Result<(), ConfigValidationError> {
+        self.bandwidth.validate()?;
+        self.ops.validate()
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct TokenBucket {
     pub size: u64,
@@ -253,6 +362,31 @@ pub struct TokenBucket {
     pub refill_time: u64,
 }
 
+impl TokenBucket {
+    /// Validate that neither the size nor the refill time of this [TokenBucket] is zero, which Firecracker rejects.
+    pub fn validate(&self) -> Result<(), ConfigValidationError> {
+        if self.size == 0 {
+            return Err(ConfigValidationError::TokenBucketFieldIsZero("size"));
+        }
+
+        if self.refill_time == 0 {
+            return Err(ConfigValidationError::TokenBucketFieldIsZero("refill_time"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Validate each of the given optional [RateLimiter]s via [RateLimiter::validate].
+pub(crate) fn validate_rate_limiters<'a, I: IntoIterator<Item = &'a Option<RateLimiter>>>(
+    rate_limiters: I,
+) -> Result<(), ConfigValidationError> {
+    rate_limiters
+        .into_iter()
+        .flatten()
+        .try_for_each(|rate_limiter| rate_limiter.validate())
+}
+
 #[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct PmemDevice {
     pub id: String,
@@ -291,6 +425,46 @@ pub struct MachineConfiguration {
     pub huge_pages: Option<HugePages>,
 }
 
+impl MachineConfiguration {
+    /// Apply the fields that are set in the given [PatchMachineConfiguration] to this [MachineConfiguration], leaving
+    /// all other fields unchanged, in the same manner as Firecracker handles a PATCH of the machine configuration.
+    pub fn apply_patch(&mut self, patch: &PatchMachineConfiguration) {
+        if let Some(vcpu_count) = patch.vcpu_count {
+            self.vcpu_count = vcpu_count;
+        }
+
+        if let Some(mem_size_mib) = patch.mem_size_mib {
+            self.mem_size_mib = mem_size_mib;
+        }
+
+        if patch.smt.is_some() {
+            self.smt = patch.smt;
+        }
+
+        if patch.track_dirty_pages.is_some() {
+            self.track_dirty_pages = patch.track_dirty_pages;
+        }
+
+        if patch.huge_pages.is_some() {
+            self.huge_pages = patch.huge_pages;
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchMachineConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vcpu_count: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mem_size_mib: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smt: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub track_dirty_pages: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub huge_pages: Option<HugePages>,
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum HugePages {
     None,
@@ -343,6 +517,43 @@ pub enum MmdsVersion {
     V2,
 }
 
+impl MmdsVersion {
+    /// Whether guests must acquire a session token before accessing the MMDS under this version.
+    pub fn requires_session_token(&self) -> bool {
+        *self == MmdsVersion::V2
+    }
+
+    /// The guest-side path of the endpoint issuing session tokens via PUT requests, if this version uses them.
+    pub fn token_endpoint_path(&self) -> Option<&'static str> {
+        match self {
+            MmdsVersion::V1 => None,
+            MmdsVersion::V2 => Some("/latest/api/token"),
+        }
+    }
+
+    /// The header specifying the TTL in seconds of a requested session token, if this version uses them.
+    pub fn token_ttl_header(&self) -> Option<&'static str> {
+        match self {
+            MmdsVersion::V1 => None,
+            MmdsVersion::V2 => Some("X-metadata-token-ttl-seconds"),
+        }
+    }
+
+    /// The header carrying the session token in guest requests to the MMDS, if this version uses them.
+    pub fn token_header(&self) -> Option<&'static str> {
+        match self {
+            MmdsVersion::V1 => None,
+            MmdsVersion::V2 => Some("X-metadata-token"),
+        }
+    }
+
+    /// Whether the IMDS compatibility mode can be enabled under this version, which is only the case for
+    /// token-based sessions. Enabling it under any other version fails the validation of the VM's configuration.
+    pub fn supports_imds_compat(&self) -> bool {
+        self.requires_session_token()
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Default)]
 pub struct EntropyDevice {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -378,6 +589,11 @@ pub struct CreateSnapshot {
     pub snapshot: Resource,
     #[serde(rename = "mem_file_path")]
     pub mem_file: Resource,
+    /// Whether to allow overwriting an existing snapshot or memory file at the effective paths of the resources,
+    /// which is otherwise rejected before calling the API in order to never clobber a prior snapshot. This isn't
+    /// sent to Firecracker.
+    #[serde(skip)]
+    pub overwrite: bool,
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -402,6 +618,130 @@ pub struct LoadSnapshot {
     pub network_overrides: Vec<NetworkOverride>,
 }
 
+impl LoadSnapshot {
+    /// Create a [LoadSnapshot] from the [Resource] of a [VmSnapshot](crate::vm::snapshot::VmSnapshot)'s snapshot file,
+    /// to be restored with a memory backend of the given [MemoryBackendType] that is independent of how the snapshot
+    /// was created. For [MemoryBackendType::File], the backend [Resource] must be the snapshot's memory file, while for
+    /// [MemoryBackendType::Uffd], it must be the Unix socket of a userfaultfd handler serving that memory file, which
+    /// allows restoring a snapshot created with a memory file via UFFD, for example during live migration.
+    pub fn from_vm_snapshot(snapshot: Resource, backend_type: MemoryBackendType, backend: Resource) -> Self {
+        Self {
+            track_dirty_pages: None,
+            mem_backend: MemoryBackend { backend_type, backend },
+            snapshot,
+            resume_vm: None,
+            network_overrides: Vec::new(),
+        }
+    }
+
+    /// Validate this [LoadSnapshot] against the constraints imposed by Firecracker that don't depend on the
+    /// [VmConfigurationData] of the snapshotted VM.
+    pub fn validate(&self) -> Result<(), ConfigValidationError> {
+        ensure_moved_resource(&self.snapshot)?;
+
+        // a UFFD socket is served by a handler that may create it on its own, so only a memory file must be moved
+        if self.mem_backend.backend_type == MemoryBackendType::File {
+            ensure_moved_resource(&self.mem_backend.backend)?;
+        }
+
+        let duplicate_iface_ids = find_duplicate_ids(
+            self.network_overrides
+                .iter()
+                .map(|network_override| network_override.iface_id.as_str()),
+        );
+        if !duplicate_iface_ids.is_empty() {
+            return Err(ConfigValidationError::DuplicateNetworkOverrideIds(duplicate_iface_ids));
+        }
+
+        Ok(())
+    }
+}
+
+fn ensure_moved_resource(resource: &Resource) -> Result<(), ConfigValidationError> {
+    match resource.get_type() {
+        ResourceType::Moved(_) => Ok(()),
+        _ => Err(ConfigValidationError::SnapshotResourceNotMoved(
+            resource.get_initial_path().to_owned(),
+        )),
+    }
+}
+
+/// A builder for a [LoadSnapshot] that is validated when built, so that an invalid [LoadSnapshot] is rejected before
+/// the VMM process is spawned instead of making Firecracker fail to load the snapshot. Unlike with
+/// [LoadSnapshot::from_vm_snapshot], the VM is resumed after the snapshot is loaded by default, since a
+/// [Vm](crate::vm::Vm) considers itself to be running once it has been started.
+#[derive(Debug, Clone)]
+pub struct LoadSnapshotBuilder {
+    load_snapshot: LoadSnapshot,
+}
+
+impl LoadSnapshotBuilder {
+    /// Create a new [LoadSnapshotBuilder] from the [Resource] of a snapshot file and a memory backend of the given
+    /// [MemoryBackendType], which follow the same rules as the arguments of [LoadSnapshot::from_vm_snapshot].
+    pub fn new(snapshot: Resource, backend_type: MemoryBackendType, backend: Resource) -> Self {
+        Self {
+            load_snapshot: LoadSnapshot {
+                resume_vm: Some(true),
+                ..LoadSnapshot::from_vm_snapshot(snapshot, backend_type, backend)
+            },
+        }
+    }
+
+    /// Set whether to track dirty pages of the restored VM, which is required for creating diff snapshots of it.
+    pub fn track_dirty_pages(mut self, track_dirty_pages: bool) -> Self {
+        self.load_snapshot.track_dirty_pages = Some(track_dirty_pages);
+        self
+    }
+
+    /// Set whether to resume the VM right after the snapshot is loaded, which is the default. A VM that isn't resumed
+    /// must be resumed via the API before the [Vm](crate::vm::Vm) is used as running.
+    pub fn resume_vm(mut self, resume_vm: bool) -> Self {
+        self.load_snapshot.resume_vm = Some(resume_vm);
+        self
+    }
+
+    /// Add a [NetworkOverride] that makes the network interface with the given ID use the given host device after
+    /// the snapshot is loaded.
+    pub fn network_override<I: Into<String>, H: Into<String>>(mut self, iface_id: I, host_dev_name: H) -> Self {
+        self.load_snapshot.network_overrides.push(NetworkOverride {
+            iface_id: iface_id.into(),
+            host_dev_name: host_dev_name.into(),
+        });
+        self
+    }
+
+    /// Validate the [LoadSnapshot] via [LoadSnapshot::validate] and build it.
+    pub fn build(self) -> Result<LoadSnapshot, ConfigValidationError> {
+        self.load_snapshot.validate()?;
+        Ok(self.load_snapshot)
+    }
+
+    /// Build the [LoadSnapshot] like [LoadSnapshotBuilder::build] and pair it with the given [VmConfigurationData] of
+    /// the snapshotted VM into a [VmConfiguration::RestoredFromSnapshot], additionally validating the
+    /// [VmConfigurationData] and checking that the [LoadSnapshot] is compatible with it.
+    pub fn build_configuration(self, data: VmConfigurationData) -> Result<VmConfiguration, ConfigValidationError> {
+        let load_snapshot = self.build()?;
+        data.validate()?;
+
+        if let Some(network_override) = load_snapshot.network_overrides.iter().find(|network_override| {
+            !data
+                .network_interfaces
+                .iter()
+                .any(|network_interface| network_interface.iface_id == network_override.iface_id)
+        }) {
+            return Err(ConfigValidationError::NetworkOverrideInterfaceMissing(
+                network_override.iface_id.clone(),
+            ));
+        }
+
+        if data.requires_hugetlbfs() && load_snapshot.mem_backend.backend_type == MemoryBackendType::File {
+            return Err(ConfigValidationError::HugePagesRequireUffdBackend);
+        }
+
+        Ok(VmConfiguration::RestoredFromSnapshot { load_snapshot, data })
+    }
+}
+
 #[derive(Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct MemoryBackend {
     pub backend_type: MemoryBackendType,
@@ -467,7 +807,15 @@ pub(crate) enum ReprIsPaused {
     Paused,
 }
 
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ReprVmConfig {
+    #[serde(default)]
+    pub drives: Vec<DriveInfo>,
+}
+
+/// The details of an error returned by the Firecracker Management API in the body of an unsuccessful response,
+/// which can be parsed via [parse_api_error](crate::vm::api::parse_api_error).
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub(crate) struct ReprApiError {
+pub struct ApiErrorDetails {
     pub fault_message: String,
 }
@@ -1,12 +1,17 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
 use crate::{
     process_spawner::ProcessSpawner,
-    runtime::Runtime,
+    runtime::{Runtime, cancellation::CancellationToken},
     vm::{
-        Vm, VmError,
+        Vm, VmError, VmState,
+        api::VmApi,
         configuration::{VmConfiguration, VmConfigurationData},
-        models::{LoadSnapshot, MemoryBackend, MemoryBackendType, NetworkOverride},
+        models::{CreateSnapshot, LoadSnapshot, MemoryBackendType, NetworkOverride, SnapshotType},
+        shutdown::{VmShutdownAction, VmShutdownMethod},
     },
     vmm::{
         executor::VmmExecutor,
@@ -18,6 +23,10 @@ use crate::{
     },
 };
 
+/// The window during which the sizes of a [VmSnapshot]'s files must remain unchanged for them to be considered
+/// stable by [VmSnapshot::wait_until_stable].
+const STABILITY_WINDOW: Duration = Duration::from_millis(100);
+
 /// The data associated with a snapshot created for a [Vm].
 #[derive(Debug, Clone)]
 pub struct VmSnapshot {
@@ -30,6 +39,28 @@ pub struct VmSnapshot {
     pub configuration_data: VmConfigurationData,
 }
 
+/// A trait for bringing the guest of a [Vm] into an application-consistent state before a snapshot is created, and
+/// for reverting that afterwards, which is used by [Vm::snapshot_quiesced]. Implementations typically coordinate with
+/// an agent inside the guest, for example over vsock, in order to flush and freeze filesystems or pause workloads.
+pub trait VmQuiescer: Send {
+    /// Quiesce the guest while it is still running, before it is paused for the snapshot.
+    fn quiesce(&mut self) -> impl Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send;
+
+    /// Unquiesce the guest after the snapshot has been created and the guest has been resumed.
+    fn unquiesce(&mut self) -> impl Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send;
+}
+
+/// A trait for resynchronizing the clock of a guest after its [Vm] has been restored from a [VmSnapshot], which is used by
+/// [Vm::on_restore_sync_clock]. The guest clock stays frozen at the time the snapshot was created, so a restored guest
+/// otherwise observes a large time jump only once it notices the drift by itself, if ever. Implementations typically
+/// signal an agent inside the guest, for example over vsock, to step its clock via NTP or by reading the host's time.
+pub trait VmClockSynchronizer: Send {
+    /// Resynchronize the clock of the guest, which is already running.
+    fn synchronize_clock(
+        &mut self,
+    ) -> impl Future<Output = Result<(), Box<dyn std::error::Error + Send + Sync>>> + Send;
+}
+
 /// The data necessary to prepare a [Vm] from a [VmSnapshot].
 #[derive(Debug)]
 pub struct PrepareVmFromSnapshotOptions<E: VmmExecutor, S: ProcessSpawner, R: Runtime> {
@@ -49,6 +80,135 @@ pub struct PrepareVmFromSnapshotOptions<E: VmmExecutor, S: ProcessSpawner, R: Ru
     pub resume_vm: Option<bool>,
     /// A [Vec] of all [NetworkOverride]s to apply when restoring the VM.
     pub network_overrides: Vec<NetworkOverride>,
+    /// Whether to preallocate disk space for the memory file after it has been moved into the new [Vm]'s environment,
+    /// which reduces page-fault storms on resume at the cost of a slower preparation. If the memory file is hard
+    /// linked, this also preallocates the original memory file.
+    pub preallocate_mem_file: bool,
+}
+
+impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> Vm<E, S, R> {
+    /// Create an application-consistent snapshot of a running [Vm] by quiescing the guest via the given [VmQuiescer],
+    /// pausing the [Vm], creating the snapshot according to the [CreateSnapshot], resuming the [Vm] and finally
+    /// unquiescing the guest. Resumption and unquiescing are attempted even if a prior step failed, after which the
+    /// first encountered error is returned.
+    pub async fn snapshot_quiesced<Q: VmQuiescer>(
+        &mut self,
+        create_snapshot: CreateSnapshot,
+        quiescer: &mut Q,
+    ) -> Result<VmSnapshot, VmError> {
+        quiescer.quiesce().await.map_err(VmError::QuiesceError)?;
+
+        let snapshot_result = match self.pause().await {
+            Ok(()) => {
+                let snapshot_result = self.create_snapshot(create_snapshot).await.map_err(VmError::ApiError);
+                let resume_result = self.resume().await.map_err(VmError::ApiError);
+                snapshot_result.and_then(|snapshot| resume_result.map(|_| snapshot))
+            }
+            Err(err) => Err(VmError::ApiError(err)),
+        };
+
+        let unquiesce_result = quiescer.unquiesce().await.map_err(VmError::QuiesceError);
+        snapshot_result.and_then(|snapshot| unquiesce_result.map(|_| snapshot))
+    }
+
+    /// Resynchronize the guest clock of this running [Vm] via the given [VmClockSynchronizer] after it has been restored
+    /// from a [VmSnapshot] and started. This should be called right after [Vm::start] with a [LoadSnapshot] that resumes
+    /// the [Vm], as the snapshot can't be loaded and the guest can't be reached at the same time. A [Vm] that wasn't
+    /// restored from a snapshot has a correct clock, so the [VmClockSynchronizer] isn't invoked for it.
+    pub async fn on_restore_sync_clock<C: VmClockSynchronizer>(&mut self, synchronizer: &mut C) -> Result<(), VmError> {
+        self.ensure_state(VmState::Running).map_err(VmError::StateCheckError)?;
+
+        if let VmConfiguration::RestoredFromSnapshot { .. } = self.configuration {
+            synchronizer
+                .synchronize_clock()
+                .await
+                .map_err(VmError::ClockSyncError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Dump the guest memory of this paused or running [Vm] for post-mortem debugging, for example of a hung guest, by
+    /// creating a full snapshot whose memory file is a raw image of the guest's physical memory, into the given
+    /// snapshot and memory file paths (inside the VMM's environment, like for [CreateSnapshot]). A running [Vm] is
+    /// paused for the dump and resumed afterwards even if the dump failed, while a paused [Vm] is left paused, so that
+    /// the [Vm] ends up in its prior state. The returned [VmSnapshot] can be inspected directly or used to restore an
+    /// identical [Vm] for interactive debugging.
+    pub async fn dump_guest_memory<P: Into<PathBuf>, Q: Into<PathBuf>>(
+        &mut self,
+        snapshot_path: P,
+        mem_file_path: Q,
+    ) -> Result<VmSnapshot, VmError> {
+        self.ensure_paused_or_running().map_err(VmError::StateCheckError)?;
+
+        let create_snapshot = CreateSnapshot {
+            snapshot_type: Some(SnapshotType::Full),
+            snapshot: self
+                .vmm_process
+                .resource_system
+                .create_resource(snapshot_path, ResourceType::Produced)
+                .map_err(VmError::ResourceSystemError)?,
+            mem_file: self
+                .vmm_process
+                .resource_system
+                .create_resource(mem_file_path, ResourceType::Produced)
+                .map_err(VmError::ResourceSystemError)?,
+            overwrite: false,
+        };
+
+        if self.get_state() == VmState::Paused {
+            return self.create_snapshot(create_snapshot).await.map_err(VmError::ApiError);
+        }
+
+        self.pause().await.map_err(VmError::ApiError)?;
+        let snapshot_result = self.create_snapshot(create_snapshot).await.map_err(VmError::ApiError);
+        let resume_result = self.resume().await.map_err(VmError::ApiError);
+        snapshot_result.and_then(|snapshot| resume_result.map(|_| snapshot))
+    }
+
+    /// Revert this paused or running [Vm] to the state captured in the given [VmSnapshot], which allows reusing a
+    /// booted [Vm] from a warm pool after each workload instead of rebooting it.
+    ///
+    /// Firecracker only accepts loading a snapshot into a VMM that hasn't booted a VM yet, so an in-place reload is
+    /// impossible. Instead, the VMM process is killed, a new [Vm] is prepared from the [VmSnapshot] according to the
+    /// [PrepareVmFromSnapshotOptions] and started, and this [Vm] is replaced by the new [Vm] and cleaned up. The new
+    /// [Vm] is always resumed after the snapshot is loaded, so it ends up in [VmState::Running].
+    ///
+    /// Since this [Vm]'s environment is cleaned up, a [VmSnapshot] that is reverted to repeatedly must reside outside of
+    /// it (for example, having been copied out via [VmSnapshot::copy]) and must not be consumed by the
+    /// [MovedResourceType] of the [PrepareVmFromSnapshotOptions]. The given [VmmExecutor] must also use an environment
+    /// separate from this [Vm]'s one, such as a jail with a different ID.
+    pub async fn revert_to_snapshot(
+        &mut self,
+        snapshot: &VmSnapshot,
+        mut options: PrepareVmFromSnapshotOptions<E, S, R>,
+        socket_wait_timeout: Duration,
+    ) -> Result<(), VmError> {
+        self.ensure_paused_or_running().map_err(VmError::StateCheckError)?;
+        self.shutdown([kill_action()]).await.map_err(VmError::ShutdownError)?;
+
+        options.resume_vm = Some(true);
+        let mut new_vm = snapshot.clone().prepare_vm(self, options).await?;
+
+        if let Err(err) = new_vm.start(socket_wait_timeout).await {
+            // the new VM is torn down on a best-effort basis, since its own failure is more relevant
+            let _ = new_vm.shutdown([kill_action()]).await;
+            let _ = new_vm.cleanup().await;
+            return Err(err);
+        }
+
+        // swap the new VM in first, so that it isn't dropped while running if cleaning up the old one fails
+        let mut old_vm = std::mem::replace(self, new_vm);
+        old_vm.cleanup().await
+    }
+}
+
+fn kill_action() -> VmShutdownAction {
+    VmShutdownAction {
+        method: VmShutdownMethod::Kill,
+        timeout: None,
+        graceful: false,
+    }
 }
 
 impl VmSnapshot {
@@ -73,6 +233,109 @@ impl VmSnapshot {
         Ok(())
     }
 
+    /// Perform [VmSnapshot::copy] until the given [CancellationToken] is cancelled, in which case this [VmSnapshot]
+    /// is left unchanged, the new locations are removed on a best-effort basis and [ResourceSystemError::Cancelled]
+    /// is returned. Depending on the [Runtime], a blocking copy that was already in progress may still run to
+    /// completion in the background, but its output is unlinked and thus discarded.
+    pub async fn copy_until_cancelled<P: Into<PathBuf>, Q: Into<PathBuf>, R: Runtime>(
+        &mut self,
+        runtime: &R,
+        cancellation_token: &CancellationToken,
+        new_snapshot_path: P,
+        new_mem_file_path: Q,
+    ) -> Result<(), ResourceSystemError> {
+        let new_snapshot_path = new_snapshot_path.into();
+        let new_mem_file_path = new_mem_file_path.into();
+
+        match cancellation_token
+            .run_until_cancelled(self.copy(runtime, new_snapshot_path.clone(), new_mem_file_path.clone()))
+            .await
+        {
+            Some(result) => result,
+            None => {
+                // Either copy may not have created its file yet, so removal errors are expected and ignored
+                let _ = futures_util::join!(
+                    runtime.fs_remove_file(&new_snapshot_path),
+                    runtime.fs_remove_file(&new_mem_file_path)
+                );
+                Err(ResourceSystemError::Cancelled)
+            }
+        }
+    }
+
+    /// Get the effective paths of all files belonging to this [VmSnapshot]: the snapshot file followed by
+    /// the memory file.
+    pub fn files(&self) -> Vec<PathBuf> {
+        vec![self.snapshot_path.clone(), self.mem_file_path.clone()]
+    }
+
+    /// Get the guest CID of the vsock device of the snapshotted VM from this [VmSnapshot]'s [VmConfigurationData], so
+    /// that the CID of a restored guest can be known ahead of the restoration, for example to set up host-side vsock
+    /// routing. Firecracker restores the vsock device with the CID it was configured with, and the snapshot editor
+    /// doesn't expose device state, so the configuration is the source of truth. [None] is returned if the snapshotted
+    /// VM had no vsock device.
+    pub fn get_vsock_cid(&self) -> Option<u32> {
+        self.configuration_data
+            .vsock_device
+            .as_ref()
+            .map(|vsock_device| vsock_device.guest_cid)
+    }
+
+    /// Deduplicate the memory file of this [VmSnapshot] against the memory file of another snapshot of a similar VM,
+    /// such as a previous snapshot in a fleet created from the same base image, via the provided [Runtime], returning
+    /// the amount of deduplicated bytes. Pages that are identical to the reference memory file's pages at the same
+    /// offsets are shared with it on filesystems supporting reflinks, such as Btrfs and XFS, and zeroed pages are
+    /// turned into holes on all filesystems. The contents of the memory file remain unchanged, so this can be done at
+    /// any point after the snapshot has been created and has become stable.
+    pub async fn deduplicate_mem_file<P: AsRef<Path>, R: Runtime>(
+        &self,
+        reference_mem_file_path: P,
+        runtime: &R,
+    ) -> Result<u64, ResourceSystemError> {
+        runtime
+            .fs_deduplicate(&self.mem_file_path, reference_mem_file_path.as_ref())
+            .await
+            .map_err(ResourceSystemError::FilesystemError)
+    }
+
+    /// Delete all files belonging to this [VmSnapshot] via the provided [Runtime], consuming it.
+    pub async fn delete<R: Runtime>(self, runtime: &R) -> Result<(), ResourceSystemError> {
+        futures_util::try_join!(
+            runtime.fs_remove_file(&self.snapshot_path),
+            runtime.fs_remove_file(&self.mem_file_path)
+        )
+        .map_err(ResourceSystemError::FilesystemError)?;
+
+        Ok(())
+    }
+
+    /// Wait until the snapshot and memory files of this [VmSnapshot] stop changing in size for a short window, by
+    /// polling their sizes via the provided [Runtime]. This guards against reading or copying a partially written
+    /// snapshot on backends that flush the memory file lazily after the snapshot creation request has completed.
+    pub async fn wait_until_stable<R: Runtime>(&self, runtime: &R, timeout: Duration) -> Result<(), VmError> {
+        runtime
+            .timeout(timeout, async {
+                let mut last_sizes = None;
+
+                loop {
+                    let sizes = futures_util::try_join!(
+                        runtime.fs_file_size(&self.snapshot_path),
+                        runtime.fs_file_size(&self.mem_file_path)
+                    )
+                    .map_err(VmError::FilesystemError)?;
+
+                    if last_sizes == Some(sizes) {
+                        return Ok(());
+                    }
+
+                    last_sizes = Some(sizes);
+                    runtime.sleep(STABILITY_WINDOW).await;
+                }
+            })
+            .await
+            .map_err(|_| VmError::SnapshotStabilityWaitTimeout)?
+    }
+
     /// A helper that automates the most common cases of preparing a new [Vm] from a [VmSnapshot] using
     /// the options supported in [PrepareVmFromSnapshotOptions]. Everything done internally by this function
     /// is public, so custom alternatives that take care of more advanced cases are possible and encouraged.
@@ -105,13 +368,9 @@ impl VmSnapshot {
 
         let load_snapshot = LoadSnapshot {
             track_dirty_pages: options.track_dirty_pages,
-            mem_backend: MemoryBackend {
-                backend_type: MemoryBackendType::File,
-                backend: mem_file,
-            },
-            snapshot,
             resume_vm: options.resume_vm,
             network_overrides: options.network_overrides,
+            ..LoadSnapshot::from_vm_snapshot(snapshot, MemoryBackendType::File, mem_file.clone())
         };
 
         let configuration = VmConfiguration::RestoredFromSnapshot {
@@ -119,12 +378,27 @@ impl VmSnapshot {
             data: self.configuration_data,
         };
 
-        Vm::prepare(
+        let vm = Vm::prepare(
             options.executor,
             resource_system,
             old_vm.vmm_process.installation.clone(),
             configuration,
         )
-        .await
+        .await?;
+
+        if options.preallocate_mem_file {
+            let mem_file_path = mem_file.get_effective_path().ok_or_else(|| {
+                VmError::ResourceSystemError(ResourceSystemError::IncorrectState(ResourceState::Uninitialized))
+            })?;
+
+            vm.vmm_process
+                .resource_system
+                .runtime
+                .fs_preallocate(mem_file_path)
+                .await
+                .map_err(VmError::FilesystemError)?;
+        }
+
+        Ok(vm)
     }
 }
@@ -45,6 +45,207 @@ impl CommandModifier for NetnsCommandModifier {
     }
 }
 
+/// A [CommandModifier] that wraps the "firecracker"/"jailer" invocation behind coreutils' "env" command in order to
+/// set environment variables for the spawned process, such as "RUST_BACKTRACE" for diagnosing VMM-level panics.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EnvCommandModifier {
+    variables: Vec<(OsString, OsString)>,
+    env_path: PathBuf,
+}
+
+impl EnvCommandModifier {
+    /// Create a new [EnvCommandModifier] that doesn't set any environment variables yet.
+    pub fn new() -> Self {
+        Self {
+            variables: Vec::new(),
+            env_path: PathBuf::from("/usr/bin/env"),
+        }
+    }
+
+    /// Set the environment variable with the given key to the given value for the spawned process.
+    pub fn var<K: Into<OsString>, V: Into<OsString>>(mut self, key: K, value: V) -> Self {
+        self.variables.push((key.into(), value.into()));
+        self
+    }
+
+    /// Override the path to coreutils' "env" used by this [EnvCommandModifier]. The default one is "/usr/bin/env".
+    pub fn env_path<P: Into<PathBuf>>(mut self, env_path: P) -> Self {
+        self.env_path = env_path.into();
+        self
+    }
+}
+
+impl Default for EnvCommandModifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CommandModifier for EnvCommandModifier {
+    fn apply(&self, binary_path: &mut PathBuf, arguments: &mut Vec<OsString>) {
+        let original_binary_path = std::mem::replace(binary_path, self.env_path.clone());
+        let mut prefix = Vec::with_capacity(self.variables.len() + 1);
+
+        for (key, value) in &self.variables {
+            let mut variable = key.clone();
+            variable.push("=");
+            variable.push(value);
+            prefix.push(variable);
+        }
+
+        prefix.push(OsString::from(original_binary_path));
+        arguments.splice(0..0, prefix);
+    }
+}
+
+/// A [CommandModifier] that wraps the "firecracker"/"jailer" invocation behind numactl in order to bind the memory
+/// allocations of the spawned process, including the guest memory, to a single NUMA node of a multi-socket host. The
+/// memory policy is inherited across the "jailer" executing "firecracker", so this applies to jailed VMMs as well.
+/// This requires numactl to be installed on the host.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NumaCommandModifier {
+    node: u32,
+    bind_cpus: bool,
+    numactl_path: PathBuf,
+}
+
+impl NumaCommandModifier {
+    /// Create a new [NumaCommandModifier] that binds memory allocations to the NUMA node with the given index.
+    pub fn new(node: u32) -> Self {
+        Self {
+            node,
+            bind_cpus: false,
+            numactl_path: PathBuf::from("/usr/bin/numactl"),
+        }
+    }
+
+    /// Additionally restrict the spawned process to the CPUs of the same NUMA node, so that the vCPU threads always
+    /// access guest memory locally.
+    pub fn bind_cpus(mut self) -> Self {
+        self.bind_cpus = true;
+        self
+    }
+
+    /// Override the path to numactl used by this [NumaCommandModifier]. The default one is "/usr/bin/numactl".
+    pub fn numactl_path<P: Into<PathBuf>>(mut self, numactl_path: P) -> Self {
+        self.numactl_path = numactl_path.into();
+        self
+    }
+}
+
+impl CommandModifier for NumaCommandModifier {
+    fn apply(&self, binary_path: &mut PathBuf, arguments: &mut Vec<OsString>) {
+        let original_binary_path = std::mem::replace(binary_path, self.numactl_path.clone());
+        let mut prefix = vec![OsString::from(format!("--membind={}", self.node))];
+
+        if self.bind_cpus {
+            prefix.push(OsString::from(format!("--cpunodebind={}", self.node)));
+        }
+
+        prefix.push(OsString::from("--"));
+        prefix.push(OsString::from(original_binary_path));
+        arguments.splice(0..0, prefix);
+    }
+}
+
+/// A real-time scheduling policy that can be set by a [SchedulerCommandModifier].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SchedulingPolicy {
+    /// The SCHED_FIFO policy, under which a thread runs until it blocks or is preempted by a higher-priority thread.
+    Fifo,
+    /// The SCHED_RR policy, which is SCHED_FIFO with time slices between threads of the same priority.
+    RoundRobin,
+}
+
+/// A [CommandModifier] that wraps the "firecracker"/"jailer" invocation behind util-linux's "chrt" command in order to
+/// run the spawned process under a real-time [SchedulingPolicy] with the given priority, which benefits
+/// latency-sensitive guests such as network functions. The scheduling policy is set before the process is executed,
+/// so that all threads of the VMM, including the vCPU threads, inherit it, and is preserved across the "jailer"
+/// executing "firecracker". This requires util-linux's "chrt" to be installed on the host, and setting a real-time
+/// policy requires the CAP_SYS_NICE capability.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SchedulerCommandModifier {
+    policy: SchedulingPolicy,
+    priority: u8,
+    chrt_path: PathBuf,
+}
+
+impl SchedulerCommandModifier {
+    /// Create a new [SchedulerCommandModifier] from a [SchedulingPolicy] and a priority, which must be within the
+    /// 1 to 99 range permitted by Linux for real-time policies.
+    pub fn new(policy: SchedulingPolicy, priority: u8) -> Self {
+        Self {
+            policy,
+            priority,
+            chrt_path: PathBuf::from("/usr/bin/chrt"),
+        }
+    }
+
+    /// Override the path to "chrt" used by this [SchedulerCommandModifier]. The default one is "/usr/bin/chrt".
+    pub fn chrt_path<P: Into<PathBuf>>(mut self, chrt_path: P) -> Self {
+        self.chrt_path = chrt_path.into();
+        self
+    }
+}
+
+impl CommandModifier for SchedulerCommandModifier {
+    fn apply(&self, binary_path: &mut PathBuf, arguments: &mut Vec<OsString>) {
+        let original_binary_path = std::mem::replace(binary_path, self.chrt_path.clone());
+        let policy = match self.policy {
+            SchedulingPolicy::Fifo => "--fifo",
+            SchedulingPolicy::RoundRobin => "--rr",
+        };
+
+        arguments.splice(
+            0..0,
+            [
+                OsString::from(policy),
+                OsString::from(self.priority.to_string()),
+                OsString::from(original_binary_path),
+            ],
+        );
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn scheduler_command_modifier_performs_changes() {
+    let command_modifier = SchedulerCommandModifier::new(SchedulingPolicy::RoundRobin, 50).chrt_path("/bin/chrt");
+    let mut binary_path = PathBuf::from("/opt/binary");
+    let mut arguments = vec!["run".into()];
+    command_modifier.apply(&mut binary_path, &mut arguments);
+    assert_eq!(binary_path.to_str().unwrap(), "/bin/chrt");
+    assert_eq!(arguments, vec!["--rr", "50", "/opt/binary", "run"]);
+}
+
+#[cfg(test)]
+#[test]
+fn numa_command_modifier_performs_changes() {
+    let command_modifier = NumaCommandModifier::new(1).bind_cpus().numactl_path("/bin/numactl");
+    let mut binary_path = PathBuf::from("/opt/binary");
+    let mut arguments = vec!["run".into()];
+    command_modifier.apply(&mut binary_path, &mut arguments);
+    assert_eq!(binary_path.to_str().unwrap(), "/bin/numactl");
+    assert_eq!(
+        arguments,
+        vec!["--membind=1", "--cpunodebind=1", "--", "/opt/binary", "run"]
+    );
+}
+
+#[cfg(test)]
+#[test]
+fn env_command_modifier_performs_changes() {
+    let command_modifier = EnvCommandModifier::new()
+        .var("RUST_BACKTRACE", "1")
+        .var("KEY", "value")
+        .env_path("/bin/env");
+    let mut binary_path = PathBuf::from("/opt/binary");
+    let mut arguments = vec!["run".into()];
+    command_modifier.apply(&mut binary_path, &mut arguments);
+    assert_eq!(binary_path.to_str().unwrap(), "/bin/env");
+    assert_eq!(arguments, vec!["RUST_BACKTRACE=1", "KEY=value", "/opt/binary", "run"]);
+}
+
 #[cfg(test)]
 #[test]
 fn netns_command_modifier_performs_changes() {
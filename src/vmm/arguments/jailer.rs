@@ -4,19 +4,27 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::vmm::id::VmmId;
+use crate::{
+    runtime::Runtime,
+    vmm::id::{VmId, VmmId},
+};
+
+/// The path to the procfs file listing the cgroups of the current process, which is used by
+/// [JailerArguments::parent_cgroup_from_self].
+pub const PROC_SELF_CGROUP_PATH: &str = "/proc/self/cgroup";
 
 /// Arguments that can be passed into the "jailer" binary.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct JailerArguments {
     pub(crate) jail_id: VmmId,
+    derives_jail_id: bool,
     cgroup_values: HashMap<OsString, OsString>,
-    cgroup_version: Option<JailerCgroupVersion>,
+    pub(crate) cgroup_version: Option<JailerCgroupVersion>,
     pub(crate) chroot_base_dir: Option<PathBuf>,
     pub(crate) daemonize: bool,
     network_namespace_path: Option<PathBuf>,
     pub(crate) exec_in_new_pid_ns: bool,
-    parent_cgroup: Option<OsString>,
+    pub(crate) parent_cgroup: Option<OsString>,
     max_file_size_limit: Option<u64>,
     max_fd_limit: Option<u64>,
 }
@@ -26,6 +34,7 @@ impl JailerArguments {
     pub fn new(jail_id: VmmId) -> Self {
         Self {
             jail_id,
+            derives_jail_id: false,
             cgroup_values: HashMap::new(),
             cgroup_version: None,
             chroot_base_dir: None,
@@ -38,6 +47,22 @@ impl JailerArguments {
         }
     }
 
+    /// Create new [JailerArguments] whose jail ID is derived from the [VmId] of the VM they are used for once it is
+    /// prepared. Until then, for example when pre-creating the jail, a placeholder jail ID generated via
+    /// [VmId::generate] is used.
+    pub fn with_derived_jail_id() -> Self {
+        Self {
+            derives_jail_id: true,
+            ..Self::new(VmId::generate().into())
+        }
+    }
+
+    pub(crate) fn derive_jail_id(&mut self, vm_id: &VmId) {
+        if self.derives_jail_id {
+            self.jail_id = vm_id.clone().into();
+        }
+    }
+
     /// Add a cgroup key-value pair to the [JailerArguments].
     pub fn cgroup<K: Into<OsString>, V: Into<OsString>>(mut self, key: K, value: V) -> Self {
         self.cgroup_values.insert(key.into(), value.into());
@@ -50,6 +75,13 @@ impl JailerArguments {
         self
     }
 
+    /// Limit the maximum amount of processes within the jail's cgroup via the "pids.max" cgroup value, which has the
+    /// same name and format under both [JailerCgroupVersion]s. This caps the amount of processes that can be forked
+    /// by a compromised Firecracker process.
+    pub fn max_pids(self, max_pids: u64) -> Self {
+        self.cgroup("pids.max", max_pids.to_string())
+    }
+
     /// Specify the jailer's [JailerCgroupVersion].
     pub fn cgroup_version(mut self, cgroup_version: JailerCgroupVersion) -> Self {
         self.cgroup_version = Some(cgroup_version);
@@ -68,6 +100,13 @@ impl JailerArguments {
         self
     }
 
+    /// Whether the "jailer" is configured in a way that makes the directly spawned process exit successfully once it has
+    /// launched "firecracker", instead of becoming the "firecracker" process itself. This is the case when the
+    /// "jailer" is daemonized or executes "firecracker" in a new PID namespace.
+    pub(crate) fn detaches_vmm(&self) -> bool {
+        self.daemonize || self.exec_in_new_pid_ns
+    }
+
     /// Specify the path (usually located within either /var/run/netns or /run/netns when using iproute2 or fcnet
     /// for setting up microVM networking) to the network namespace for the jailer to enter.
     pub fn network_namespace_path<P: Into<PathBuf>>(mut self, network_namespace_path: P) -> Self {
@@ -87,6 +126,33 @@ impl JailerArguments {
         self
     }
 
+    /// Specify the parent of the current process's own cgroups v2 cgroup, read from [PROC_SELF_CGROUP_PATH] via the
+    /// given [Runtime], as the parent cgroup for the jailer and switch to [JailerCgroupVersion::V2], so that the jails'
+    /// cgroups become siblings of the current process's cgroup. If that parent is the root cgroup, no parent cgroup is
+    /// specified. An [std::io::ErrorKind::Unsupported] error is returned if the current process isn't in a cgroups v2
+    /// hierarchy.
+    ///
+    /// Cgroups v2 forbids a cgroup with enabled controllers from containing processes, so the current process's own
+    /// cgroup can't be the jails' parent. Instead, the current process should move itself into a leaf cgroup of the
+    /// cgroup that was delegated to it, for example by systemd via "Delegate=yes", and then use this method from
+    /// there, so that the jails' cgroups are nested under the delegated cgroup.
+    pub async fn parent_cgroup_from_self<R: Runtime>(mut self, runtime: &R) -> Result<Self, std::io::Error> {
+        let cgroups = runtime.fs_read_to_string(Path::new(PROC_SELF_CGROUP_PATH)).await?;
+        let cgroup = parse_unified_cgroup(&cgroups).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "The current process isn't in a cgroups v2 hierarchy",
+            )
+        })?;
+
+        if let Some(parent_cgroup) = get_parent_cgroup(cgroup) {
+            self.parent_cgroup = Some(parent_cgroup.into());
+        }
+
+        self.cgroup_version = Some(JailerCgroupVersion::V2);
+        Ok(self)
+    }
+
     /// Specify the limit on the maximum size of files created by the Firecracker process spawned by the jailer,
     /// in bytes.
     pub fn max_file_size_limit(mut self, max_file_size_limit: u64) -> Self {
@@ -166,6 +232,17 @@ impl JailerArguments {
     }
 }
 
+fn parse_unified_cgroup(cgroups: &str) -> Option<&str> {
+    // the cgroups v2 hierarchy is always listed with the ID 0 and no controllers, as in "0::/system.slice/app.service"
+    cgroups.lines().find_map(|line| line.strip_prefix("0::"))
+}
+
+fn get_parent_cgroup(cgroup: &str) -> Option<&str> {
+    // the jailer expects the parent cgroup relative to the cgroup mount, and the root cgroup is already the default
+    let (parent_cgroup, _) = cgroup.trim_end_matches('/').rsplit_once('/')?;
+    Some(parent_cgroup.trim_start_matches('/')).filter(|parent_cgroup| !parent_cgroup.is_empty())
+}
+
 /// The cgroup version used by the jailer, v1 by default.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum JailerCgroupVersion {
@@ -179,8 +256,8 @@ pub enum JailerCgroupVersion {
 mod tests {
     use std::{ffi::OsString, path::PathBuf};
 
-    use super::{JailerArguments, JailerCgroupVersion};
-    use crate::vmm::id::VmmId;
+    use super::{JailerArguments, JailerCgroupVersion, get_parent_cgroup, parse_unified_cgroup};
+    use crate::vmm::id::{VmId, VmmId};
 
     fn new() -> JailerArguments {
         JailerArguments::new(VmmId::new("jail-id").unwrap())
@@ -191,11 +268,34 @@ mod tests {
         check(new(), ["--uid", "1", "--gid", "--id", "jail-id"]);
     }
 
+    #[test]
+    fn jail_id_is_only_derived_when_not_set() {
+        let vm_id = VmId::new("vm-id").unwrap();
+
+        let mut arguments = JailerArguments::with_derived_jail_id();
+        arguments.derive_jail_id(&vm_id);
+        check(arguments, ["--id", "vm-id"]);
+
+        let mut arguments = new();
+        arguments.derive_jail_id(&vm_id);
+        check(arguments, ["--id", "jail-id"]);
+    }
+
     #[test]
     fn cgroup_values_can_be_set() {
         check(new().cgroup("key", "value"), ["--cgroup", "key=value"]);
     }
 
+    #[test]
+    fn max_pids_can_be_set() {
+        for cgroup_version in [JailerCgroupVersion::V1, JailerCgroupVersion::V2] {
+            check(
+                new().cgroup_version(cgroup_version).max_pids(64),
+                ["--cgroup", "pids.max=64"],
+            );
+        }
+    }
+
     #[test]
     fn cgroup_version_can_be_set() {
         for (cgroup_version, matcher) in [(JailerCgroupVersion::V1, "1"), (JailerCgroupVersion::V2, "2")] {
@@ -237,6 +337,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unified_cgroup_is_parsed_from_proc_self_cgroup() {
+        assert_eq!(
+            parse_unified_cgroup("0::/system.slice/app.service\n"),
+            Some("/system.slice/app.service")
+        );
+        assert_eq!(
+            parse_unified_cgroup("12:pids:/user.slice\n1:name=systemd:/user.slice\n0::/user.slice\n"),
+            Some("/user.slice")
+        );
+        assert_eq!(parse_unified_cgroup("12:pids:/user.slice\n"), None);
+    }
+
+    #[test]
+    fn parent_of_unified_cgroup_is_used() {
+        assert_eq!(get_parent_cgroup(parse_unified_cgroup("0::/a/b\n").unwrap()), Some("a"));
+        assert_eq!(get_parent_cgroup("/a/b/c"), Some("a/b"));
+        assert_eq!(get_parent_cgroup("/a"), None);
+        assert_eq!(get_parent_cgroup("/"), None);
+    }
+
     #[test]
     fn max_file_size_limit_can_be_set() {
         check(new().max_file_size_limit(250), ["--resource-limit", "fsize=250"]);
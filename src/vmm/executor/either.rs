@@ -6,7 +6,11 @@ use super::{
     process_handle::ProcessHandle,
     unrestricted::UnrestrictedVmmExecutor,
 };
-use crate::{process_spawner::ProcessSpawner, runtime::Runtime, vmm::installation::VmmInstallation};
+use crate::{
+    process_spawner::ProcessSpawner,
+    runtime::Runtime,
+    vmm::{id::VmId, installation::VmmInstallation},
+};
 
 /// [EitherVmmExecutor] encapsulates either an [UnrestrictedVmmExecutor] or a [JailedVmmExecutor]
 /// with the given [VirtualPathResolver] behind an enum with [VmmExecutor] implemented on it. fctools was
@@ -44,6 +48,13 @@ impl<V: VirtualPathResolver> VmmExecutor for EitherVmmExecutor<V> {
         }
     }
 
+    fn derive_id(&mut self, vm_id: &VmId) {
+        match self {
+            EitherVmmExecutor::Unrestricted(executor) => executor.derive_id(vm_id),
+            EitherVmmExecutor::Jailed(executor) => executor.derive_id(vm_id),
+        }
+    }
+
     async fn prepare<S: ProcessSpawner, R: Runtime>(
         &self,
         context: VmmExecutorContext<'_, S, R>,
@@ -8,13 +8,24 @@ use crate::{
     process_spawner::ProcessSpawner,
     runtime::{Runtime, RuntimeChild},
     vmm::{
-        arguments::{VmmApiSocket, VmmArguments, command_modifier::CommandModifier, jailer::JailerArguments},
+        arguments::{
+            VmmApiSocket, VmmArguments,
+            command_modifier::{
+                CommandModifier, EnvCommandModifier, NumaCommandModifier, SchedulerCommandModifier, SchedulingPolicy,
+            },
+            jailer::{JailerArguments, JailerCgroupVersion},
+        },
+        id::VmId,
         installation::VmmInstallation,
         ownership::{PROCESS_GID, PROCESS_UID, downgrade_owner_recursively, upgrade_owner},
-        resource::ResourceType,
+        resource::{Resource, ResourceState, ResourceType, system::ResourceSystem},
     },
 };
 
+const PRE_CREATED_JAIL_MARKER_NAME: &str = ".pre-created";
+
+const HUGETLBFS_MOUNTED_MARKER_NAME: &str = ".hugetlbfs-mounted";
+
 /// A [VmmExecutor] that uses the "jailer" binary for maximum security and isolation, dropping privileges to then
 /// run "firecracker". The "jailer", by design, can only run as "root", even though the "firecracker" process itself
 /// won't do so unless explicitly configured to run as UID 0 and GID 0, which corresponds to "root".
@@ -25,6 +36,8 @@ pub struct JailedVmmExecutor<V: VirtualPathResolver> {
     jailer_arguments: JailerArguments,
     virtual_path_resolver: V,
     command_modifier_chain: Vec<Box<dyn CommandModifier>>,
+    hugetlbfs_mount_path: Option<PathBuf>,
+    exec_file_alias: Option<String>,
 }
 
 impl<V: VirtualPathResolver> JailedVmmExecutor<V> {
@@ -36,6 +49,8 @@ impl<V: VirtualPathResolver> JailedVmmExecutor<V> {
             jailer_arguments,
             virtual_path_resolver,
             command_modifier_chain: Vec::new(),
+            hugetlbfs_mount_path: None,
+            exec_file_alias: None,
         }
     }
 
@@ -50,6 +65,123 @@ impl<V: VirtualPathResolver> JailedVmmExecutor<V> {
         self.command_modifier_chain.extend(command_modifiers);
         self
     }
+
+    /// Append an [EnvCommandModifier] setting "RUST_BACKTRACE=1" to the [CommandModifier] chain.
+    pub fn with_backtrace(self) -> Self {
+        self.command_modifier(EnvCommandModifier::new().var("RUST_BACKTRACE", "1"))
+    }
+
+    /// Append a [NumaCommandModifier] for the NUMA node with the given index to the [CommandModifier] chain.
+    pub fn with_numa_node(self, node: u32) -> Self {
+        self.command_modifier(NumaCommandModifier::new(node))
+    }
+
+    /// Append a [SchedulerCommandModifier] with the given [SchedulingPolicy] and priority to the [CommandModifier] chain.
+    pub fn with_scheduler(self, policy: SchedulingPolicy, priority: u8) -> Self {
+        self.command_modifier(SchedulerCommandModifier::new(policy, priority))
+    }
+
+    /// Recursively get the disk space in bytes currently allocated for this [JailedVmmExecutor]'s jail via the given
+    /// [Runtime], which allows a supervisor to externally enforce disk quotas for jails. Hard-linked moved resources
+    /// are counted as well, even though they don't occupy any extra disk space outside of the jail.
+    pub async fn disk_usage<R: Runtime>(
+        &self,
+        installation: &VmmInstallation,
+        runtime: &R,
+    ) -> Result<u64, std::io::Error> {
+        runtime.fs_disk_usage(&self.get_paths(installation).1).await
+    }
+
+    /// Read the effective [CgroupStats] of the cgroup that the "jailer" created for this [JailedVmmExecutor]'s jail
+    /// via the given [Runtime], reflecting the VM's actual CPU and memory usage. Only cgroups v2 mounted at
+    /// "/sys/fs/cgroup" are supported, so an [std::io::ErrorKind::Unsupported] error is returned if the "jailer" is
+    /// configured to use cgroups v1.
+    pub async fn cgroup_stats<R: Runtime>(
+        &self,
+        installation: &VmmInstallation,
+        runtime: &R,
+    ) -> Result<CgroupStats, std::io::Error> {
+        if self.jailer_arguments.cgroup_version != Some(JailerCgroupVersion::V2) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "Reading cgroup stats is only supported for cgroups v2",
+            ));
+        }
+
+        let cgroup_path = self.get_cgroup_path(installation);
+        let memory_current = runtime.fs_read_to_string(&cgroup_path.join("memory.current")).await?;
+        let cpu_stat = runtime.fs_read_to_string(&cgroup_path.join("cpu.stat")).await?;
+        CgroupStats::parse(&memory_current, &cpu_stat)
+    }
+
+    /// Bind-mount the hugetlbfs mounted at the given host path into the jail at the same path, so that it is
+    /// accessible to the jailed "firecracker" process, for example for a UFFD handler sharing huge pages with it.
+    /// Firecracker itself doesn't need a hugetlbfs mount for backing guest memory with huge pages. The bind mount is
+    /// performed during preparation and reverted during cleanup via elevated "mount" and "umount" processes spawned
+    /// through the [ProcessSpawner], with the latter only being spawned if the bind mount was actually made.
+    pub fn bind_mount_hugetlbfs<P: Into<PathBuf>>(mut self, hugetlbfs_mount_path: P) -> Self {
+        self.hugetlbfs_mount_path = Some(hugetlbfs_mount_path.into());
+        self
+    }
+
+    /// Make the "jailer" execute the "firecracker" binary under the given file name instead of that of the
+    /// [VmmInstallation]'s binary, so that the running process, as well as the jail directory and parent cgroup named
+    /// after it, can be told apart from those of other VMs, for example in "ps" output. The "jailer" requires the
+    /// file name to contain "firecracker" and resolves symlinks, so the binary is hard-linked (or copied if that
+    /// fails) under this file name next to the jail during preparation, which fails if the file name is empty, contains
+    /// a "/" or refers to a directory.
+    pub fn exec_file_alias<N: Into<String>>(mut self, name: N) -> Self {
+        self.exec_file_alias = Some(name.into());
+        self
+    }
+
+    /// Create this [JailedVmmExecutor]'s jail via the given [Runtime] ahead of time and move all uninitialized moved
+    /// resources of the given [ResourceSystem] and of the [VmmArguments] into it, which are the static resources of a
+    /// VM, such as its kernel and rootfs. The jail includes the directory of the API socket and, if an
+    /// [JailedVmmExecutor::exec_file_alias] is set, the aliased binary. The next preparation with the same
+    /// [ResourceSystem] then reuses the pre-created jail and the pre-moved resources instead of recreating them, so
+    /// that this slow filesystem setup can be performed, for example, while a VM sits in a warm pool instead of right
+    /// before it is spawned.
+    ///
+    /// Unlike during preparation, no ownership upgrades are performed for the jail, so the current process must be
+    /// able to write into the chroot base directory. A pre-created jail is only reused once, and only if it still
+    /// exists, since otherwise it is recreated, in which case the preparation fails for the already pre-moved
+    /// resources, as these can't be moved into the new jail again.
+    pub async fn pre_create_jail<S: ProcessSpawner, R: Runtime>(
+        &self,
+        installation: &VmmInstallation,
+        runtime: &R,
+        resource_system: &mut ResourceSystem<S, R>,
+    ) -> Result<(), VmmExecutorError> {
+        self.validate_exec_file_alias()?;
+        self.create_jail_tree(installation, runtime).await?;
+
+        let jail_path = self.get_paths(installation).1;
+        let moved_resources = resource_system
+            .get_resources()
+            .iter()
+            .chain(self.vmm_arguments.get_resources())
+            .filter(|resource| {
+                matches!(resource.get_type(), ResourceType::Moved(_))
+                    && resource.get_state() == ResourceState::Uninitialized
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+
+        for resource in moved_resources.iter() {
+            self.start_resource_initialization(resource, &jail_path)?;
+        }
+
+        resource_system
+            .synchronize_subset(&moved_resources)
+            .await
+            .map_err(VmmExecutorError::ResourceSystemError)?;
+
+        runtime
+            .fs_create_file(&self.get_marker_path(installation, PRE_CREATED_JAIL_MARKER_NAME))
+            .await
+            .map_err(VmmExecutorError::FilesystemError)
+    }
 }
 
 impl<V: VirtualPathResolver> VmmExecutor for JailedVmmExecutor<V> {
@@ -64,11 +196,15 @@ impl<V: VirtualPathResolver> VmmExecutor for JailedVmmExecutor<V> {
         self.get_paths(installation).1.jail_join(&local_path)
     }
 
+    fn derive_id(&mut self, vm_id: &VmId) {
+        self.jailer_arguments.derive_jail_id(vm_id);
+    }
+
     async fn prepare<S: ProcessSpawner, R: Runtime>(
         &self,
         context: VmmExecutorContext<'_, S, R>,
     ) -> Result<(), VmmExecutorError> {
-        // Create the jail and delete the previous one if necessary
+        self.validate_exec_file_alias()?;
         let (chroot_base_dir, jail_path) = self.get_paths(&context.installation);
         upgrade_owner(
             &chroot_base_dir,
@@ -79,49 +215,69 @@ impl<V: VirtualPathResolver> VmmExecutor for JailedVmmExecutor<V> {
         .await
         .map_err(VmmExecutorError::ChangeOwnerError)?;
 
-        if context
+        // Reuse a jail that was pre-created via JailedVmmExecutor::pre_create_jail exactly once, or create it otherwise.
+        // The marker outlives a jail that was removed in the meantime, so it is only trusted if the jail still exists.
+        let pre_created_marker_path = self.get_marker_path(&context.installation, PRE_CREATED_JAIL_MARKER_NAME);
+        let is_pre_created_marker_present = context
             .runtime
-            .fs_exists(&jail_path)
+            .fs_exists(&pre_created_marker_path)
             .await
-            .map_err(VmmExecutorError::FilesystemError)?
-        {
+            .map_err(VmmExecutorError::FilesystemError)?;
+        let reuses_pre_created_jail = is_pre_created_marker_present
+            && context
+                .runtime
+                .fs_exists(&jail_path)
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?;
+
+        if is_pre_created_marker_present {
             context
                 .runtime
-                .fs_remove_dir_all(&jail_path)
+                .fs_remove_file(&pre_created_marker_path)
                 .await
                 .map_err(VmmExecutorError::FilesystemError)?;
         }
 
-        context
-            .runtime
-            .fs_create_dir_all(&jail_path)
-            .await
-            .map_err(VmmExecutorError::FilesystemError)?;
+        if !reuses_pre_created_jail {
+            self.create_jail_tree(&context.installation, &context.runtime).await?;
+        }
 
-        // Ensure that the socket parent directory exists so that the firecracker process can bind inside of it
-        if let VmmApiSocket::Enabled(ref socket_path) = self.vmm_arguments.api_socket {
-            if let Some(socket_parent_dir) = socket_path.parent() {
-                context
-                    .runtime
-                    .fs_create_dir_all(&jail_path.jail_join(socket_parent_dir))
-                    .await
-                    .map_err(VmmExecutorError::FilesystemError)?;
-            }
+        if let Some(ref hugetlbfs_mount_path) = self.hugetlbfs_mount_path {
+            let jailed_mount_path = jail_path.jail_join(hugetlbfs_mount_path);
+            context
+                .runtime
+                .fs_create_dir_all(&jailed_mount_path)
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?;
+
+            run_mount_process(
+                "mount",
+                &[
+                    OsString::from("--bind"),
+                    OsString::from(hugetlbfs_mount_path),
+                    OsString::from(jailed_mount_path),
+                ],
+                &context,
+            )
+            .await?;
+
+            context
+                .runtime
+                .fs_create_file(&self.get_marker_path(&context.installation, HUGETLBFS_MOUNTED_MARKER_NAME))
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?;
         }
 
         for resource in context.resources.iter().chain(self.vmm_arguments.get_resources()) {
-            match resource.get_type() {
-                ResourceType::Moved(_) => {
-                    let virtual_path = self
-                        .virtual_path_resolver
-                        .resolve_virtual_path(resource.get_initial_path())
-                        .map_err(VmmExecutorError::VirtualPathResolverError)?;
-                    let effective_path = jail_path.jail_join(&virtual_path);
-                    resource.start_initialization(effective_path, Some(virtual_path))
-                }
-                _ => resource.start_initialization(jail_path.jail_join(resource.get_initial_path()), None),
+            // Moved resources were already moved into a reused jail by JailedVmmExecutor::pre_create_jail
+            if reuses_pre_created_jail
+                && matches!(resource.get_type(), ResourceType::Moved(_))
+                && resource.get_state() == ResourceState::Initialized
+            {
+                continue;
             }
-            .map_err(VmmExecutorError::ResourceSystemError)?
+
+            self.start_resource_initialization(resource, &jail_path)?;
         }
 
         Ok(())
@@ -147,7 +303,7 @@ impl<V: VirtualPathResolver> VmmExecutor for JailedVmmExecutor<V> {
 
         let mut arguments = self
             .jailer_arguments
-            .join(uid, gid, context.installation.get_firecracker_path());
+            .join(uid, gid, &self.get_exec_file_path(&context.installation));
         let mut binary_path = context.installation.get_jailer_path().to_owned();
         arguments.push(OsString::from("--"));
         arguments.extend(self.vmm_arguments.join(config_path));
@@ -163,17 +319,11 @@ impl<V: VirtualPathResolver> VmmExecutor for JailedVmmExecutor<V> {
             .await
             .map_err(VmmExecutorError::ProcessSpawnFailed)?;
 
-        if self.jailer_arguments.daemonize || self.jailer_arguments.exec_in_new_pid_ns {
+        // A detached "firecracker" process is re-parented, so the launcher exiting with a zero status is expected here and
+        // the returned process handle must track "firecracker" via its PID file instead of the exited launcher
+        if self.jailer_arguments.detaches_vmm() {
             let (_, jail_path) = self.get_paths(&context.installation);
-            let pid_file_path = jail_path.join(format!(
-                "{}.pid",
-                context
-                    .installation
-                    .get_firecracker_path()
-                    .file_name()
-                    .and_then(|f| f.to_str())
-                    .unwrap_or("firecracker")
-            ));
+            let pid_file_path = jail_path.join(format!("{}.pid", self.get_exec_file_name(&context.installation)));
 
             let exit_status = process.wait().await.map_err(VmmExecutorError::ProcessWaitError)?;
             if !exit_status.success() {
@@ -209,6 +359,31 @@ impl<V: VirtualPathResolver> VmmExecutor for JailedVmmExecutor<V> {
     ) -> Result<(), VmmExecutorError> {
         let (_, jail_path) = self.get_paths(&context.installation);
 
+        // The bind mount must be reverted before removing the jail, as otherwise the hugetlbfs' contents are removed.
+        // Unmounting when the bind mount never happened would fail and leave the jail behind, so a marker is checked.
+        if let Some(ref hugetlbfs_mount_path) = self.hugetlbfs_mount_path {
+            let hugetlbfs_mounted_marker_path =
+                self.get_marker_path(&context.installation, HUGETLBFS_MOUNTED_MARKER_NAME);
+            if context
+                .runtime
+                .fs_exists(&hugetlbfs_mounted_marker_path)
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?
+            {
+                run_mount_process(
+                    "umount",
+                    &[OsString::from(jail_path.jail_join(hugetlbfs_mount_path))],
+                    &context,
+                )
+                .await?;
+                context
+                    .runtime
+                    .fs_remove_file(&hugetlbfs_mounted_marker_path)
+                    .await
+                    .map_err(VmmExecutorError::FilesystemError)?;
+            }
+        }
+
         upgrade_owner(
             &jail_path,
             context.ownership_model,
@@ -222,6 +397,13 @@ impl<V: VirtualPathResolver> VmmExecutor for JailedVmmExecutor<V> {
             return Err(VmmExecutorError::ExpectedDirectoryParentMissing(jail_path));
         };
 
+        // The devices of snapshotted moved resources would outlive the jail if they weren't removed beforehand
+        for resource in context.resources.iter().chain(self.vmm_arguments.get_resources()) {
+            resource
+                .remove_snapshot_device()
+                .map_err(VmmExecutorError::FilesystemError)?;
+        }
+
         context
             .runtime
             .fs_remove_dir_all(jail_parent_path)
@@ -231,6 +413,101 @@ impl<V: VirtualPathResolver> VmmExecutor for JailedVmmExecutor<V> {
 }
 
 impl<V: VirtualPathResolver> JailedVmmExecutor<V> {
+    // The alias is joined onto the paths of the jail, so anything but a plain file name would escape them
+    fn validate_exec_file_alias(&self) -> Result<(), VmmExecutorError> {
+        match self.exec_file_alias {
+            Some(ref exec_file_alias)
+                if exec_file_alias.is_empty()
+                    || exec_file_alias.contains('/')
+                    || exec_file_alias == "."
+                    || exec_file_alias == ".." =>
+            {
+                Err(VmmExecutorError::InvalidExecFileAlias(exec_file_alias.clone()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn start_resource_initialization(&self, resource: &Resource, jail_path: &Path) -> Result<(), VmmExecutorError> {
+        match resource.get_type() {
+            ResourceType::Moved(_) => {
+                let virtual_path = self
+                    .virtual_path_resolver
+                    .resolve_virtual_path(resource.get_initial_path())
+                    .map_err(VmmExecutorError::VirtualPathResolverError)?;
+                let effective_path = jail_path.jail_join(&virtual_path);
+                resource.start_initialization(effective_path, Some(virtual_path))
+            }
+            _ => resource.start_initialization(jail_path.jail_join(resource.get_initial_path()), None),
+        }
+        .map_err(VmmExecutorError::ResourceSystemError)
+    }
+
+    async fn create_jail_tree<R: Runtime>(
+        &self,
+        installation: &VmmInstallation,
+        runtime: &R,
+    ) -> Result<(), VmmExecutorError> {
+        // Create the jail and delete the previous one if necessary
+        let jail_path = self.get_paths(installation).1;
+
+        if runtime
+            .fs_exists(&jail_path)
+            .await
+            .map_err(VmmExecutorError::FilesystemError)?
+        {
+            runtime
+                .fs_remove_dir_all(&jail_path)
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?;
+        }
+
+        runtime
+            .fs_create_dir_all(&jail_path)
+            .await
+            .map_err(VmmExecutorError::FilesystemError)?;
+
+        if self.exec_file_alias.is_some() {
+            let exec_file_path = self.get_exec_file_path(installation);
+
+            if runtime
+                .fs_exists(&exec_file_path)
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?
+            {
+                runtime
+                    .fs_remove_file(&exec_file_path)
+                    .await
+                    .map_err(VmmExecutorError::FilesystemError)?;
+            }
+
+            let firecracker_path = installation.get_firecracker_path();
+            if runtime.fs_hard_link(firecracker_path, &exec_file_path).await.is_err() {
+                runtime
+                    .fs_copy(firecracker_path, &exec_file_path)
+                    .await
+                    .map_err(VmmExecutorError::FilesystemError)?;
+            }
+        }
+
+        // Ensure that the socket parent directory exists so that the firecracker process can bind inside of it
+        if let VmmApiSocket::Enabled(ref socket_path) = self.vmm_arguments.api_socket {
+            if let Some(socket_parent_dir) = socket_path.parent() {
+                runtime
+                    .fs_create_dir_all(&jail_path.jail_join(socket_parent_dir))
+                    .await
+                    .map_err(VmmExecutorError::FilesystemError)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn get_marker_path(&self, installation: &VmmInstallation, marker_name: &str) -> PathBuf {
+        // Markers are placed next to the jail's root, so that they are invisible to the "firecracker" process
+        self.get_paths(installation).1.with_file_name(marker_name)
+    }
+
     fn get_paths(&self, installation: &VmmInstallation) -> (PathBuf, PathBuf) {
         let chroot_base_dir = self
             .jailer_arguments
@@ -240,18 +517,109 @@ impl<V: VirtualPathResolver> JailedVmmExecutor<V> {
 
         // Example of a resulting jail_path: /srv/jailer/firecracker/1/root
         let jail_path = chroot_base_dir
-            .join(
-                installation
-                    .get_firecracker_path()
-                    .file_name()
-                    .and_then(|f| f.to_str())
-                    .unwrap_or("firecracker"),
-            )
+            .join(self.get_exec_file_name(installation))
             .join(self.jailer_arguments.jail_id.as_ref())
             .join("root");
 
         (chroot_base_dir, jail_path)
     }
+
+    fn get_exec_file_name<'a>(&'a self, installation: &'a VmmInstallation) -> &'a str {
+        match self.exec_file_alias {
+            Some(ref exec_file_alias) => exec_file_alias,
+            None => installation
+                .get_firecracker_path()
+                .file_name()
+                .and_then(|f| f.to_str())
+                .unwrap_or("firecracker"),
+        }
+    }
+
+    fn get_exec_file_path(&self, installation: &VmmInstallation) -> PathBuf {
+        match self.exec_file_alias {
+            // The aliased binary is placed next to the jail's root, so that it is removed alongside the jail
+            Some(ref exec_file_alias) => {
+                let jail_path = self.get_paths(installation).1;
+                jail_path.with_file_name(exec_file_alias)
+            }
+            None => installation.get_firecracker_path().to_owned(),
+        }
+    }
+
+    fn get_cgroup_path(&self, installation: &VmmInstallation) -> PathBuf {
+        // The jailer places the jail's cgroup into a parent cgroup named after the executable unless overridden
+        let parent_cgroup = match self.jailer_arguments.parent_cgroup {
+            Some(ref parent_cgroup) => PathBuf::from(parent_cgroup),
+            None => PathBuf::from(self.get_exec_file_name(installation)),
+        };
+
+        PathBuf::from("/sys/fs/cgroup")
+            .jail_join(&parent_cgroup)
+            .join(self.jailer_arguments.jail_id.as_ref())
+    }
+}
+
+/// The effective resource usage statistics of a jail's cgroup, as read from its "memory.current" and "cpu.stat" files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct CgroupStats {
+    /// The total amount of memory in bytes currently used by the cgroup.
+    pub memory_current: u64,
+    /// The total CPU time in microseconds consumed by the cgroup.
+    pub cpu_usage_usec: u64,
+    /// The CPU time in microseconds consumed by the cgroup in user mode.
+    pub cpu_user_usec: u64,
+    /// The CPU time in microseconds consumed by the cgroup in kernel mode.
+    pub cpu_system_usec: u64,
+}
+
+impl CgroupStats {
+    fn parse(memory_current: &str, cpu_stat: &str) -> Result<Self, std::io::Error> {
+        fn parse_value(value: &str) -> Result<u64, std::io::Error> {
+            value
+                .trim()
+                .parse()
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+        }
+
+        let mut stats = Self {
+            memory_current: parse_value(memory_current)?,
+            ..Default::default()
+        };
+
+        for line in cpu_stat.lines() {
+            let Some((key, value)) = line.split_once(' ') else {
+                continue;
+            };
+
+            match key {
+                "usage_usec" => stats.cpu_usage_usec = parse_value(value)?,
+                "user_usec" => stats.cpu_user_usec = parse_value(value)?,
+                "system_usec" => stats.cpu_system_usec = parse_value(value)?,
+                _ => {}
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+async fn run_mount_process<S: ProcessSpawner, R: Runtime>(
+    binary: &str,
+    arguments: &[OsString],
+    context: &VmmExecutorContext<'_, S, R>,
+) -> Result<(), VmmExecutorError> {
+    let mut process = context
+        .process_spawner
+        .spawn(&PathBuf::from(binary), arguments, true, &context.runtime)
+        .await
+        .map_err(VmmExecutorError::ProcessSpawnFailed)?;
+    let exit_status = process.wait().await.map_err(VmmExecutorError::ProcessWaitError)?;
+
+    if !exit_status.success() {
+        return Err(VmmExecutorError::ProcessExitedWithNonZeroStatus(exit_status));
+    }
+
+    Ok(())
 }
 
 /// An error that can be emitted by a [VirtualPathResolver] implementation.
@@ -281,7 +649,9 @@ impl std::fmt::Display for VirtualPathResolverError {
 }
 
 /// A trait defining a method of resolving a resource's virtual path from its initial path. This conversion
-/// should always produce the same virtual path (or error) for the same given initial path.
+/// should always produce the same virtual path (or error) for the same given initial path, regardless of the order
+/// of resolutions or any other state, so that identical configurations are always mapped to identical jails, which
+/// keeps snapshots portable between them.
 pub trait VirtualPathResolver: Send + Sync {
     /// Convert the provided initial path to a virtual path within the jail.
     fn resolve_virtual_path(&self, initial_path: &Path) -> Result<PathBuf, VirtualPathResolverError>;
@@ -289,17 +659,16 @@ pub trait VirtualPathResolver: Send + Sync {
 
 /// A [VirtualPathResolver] that transforms an initial path with filename (including extension) "p" into a
 /// "/p" virtual path. Given that files have unique names, this should be sufficient for most production scenarios.
+/// The filename is preserved byte-for-byte, so that filenames that aren't valid UTF-8 are never conflated.
 #[derive(Debug, Clone, Default)]
 pub struct FlatVirtualPathResolver;
 
 impl VirtualPathResolver for FlatVirtualPathResolver {
     fn resolve_virtual_path(&self, outside_path: &Path) -> Result<PathBuf, VirtualPathResolverError> {
-        Ok(PathBuf::from(
-            "/".to_owned()
-                + &outside_path
-                    .file_name()
-                    .ok_or(VirtualPathResolverError::InitialPathHasNoFilename)?
-                    .to_string_lossy(),
+        Ok(Path::new("/").join(
+            outside_path
+                .file_name()
+                .ok_or(VirtualPathResolverError::InitialPathHasNoFilename)?,
         ))
     }
 }
@@ -309,19 +678,28 @@ trait JailJoin {
     fn jail_join(&self, other_path: &Path) -> PathBuf;
 }
 
-impl JailJoin for PathBuf {
+impl JailJoin for Path {
     fn jail_join(&self, other_path: &Path) -> PathBuf {
-        let other_path = other_path.to_string_lossy();
-        self.join(other_path.trim_start_matches("/"))
+        // Stripping the root component instead of a string prefix keeps paths that aren't valid UTF-8 intact
+        self.join(other_path.strip_prefix("/").unwrap_or(other_path))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::path::PathBuf;
-
-    use super::{FlatVirtualPathResolver, VirtualPathResolver};
-    use crate::vmm::executor::jailed::JailJoin;
+    use std::{
+        ffi::OsString,
+        os::unix::ffi::{OsStrExt, OsStringExt},
+        path::PathBuf,
+    };
+
+    use super::{CgroupStats, FlatVirtualPathResolver, JailedVmmExecutor, VirtualPathResolver};
+    use crate::vmm::{
+        arguments::{VmmApiSocket, VmmArguments, jailer::JailerArguments},
+        executor::{VmmExecutorError, jailed::JailJoin},
+        id::VmmId,
+        installation::VmmInstallation,
+    };
 
     #[test]
     fn jail_join_performs_correctly() {
@@ -339,6 +717,111 @@ mod tests {
         assert_virtual_path_resolver(&resolver, "/some/complex/outside/path/filename.ext4", "/filename.ext4");
     }
 
+    #[test]
+    fn cgroup_stats_are_parsed_correctly() {
+        let stats = CgroupStats::parse(
+            "52428800\n",
+            "usage_usec 1500\nuser_usec 1000\nsystem_usec 500\nnr_periods 0\nnr_throttled 0\n",
+        )
+        .unwrap();
+        assert_eq!(
+            stats,
+            CgroupStats {
+                memory_current: 52428800,
+                cpu_usage_usec: 1500,
+                cpu_user_usec: 1000,
+                cpu_system_usec: 500,
+            }
+        );
+        assert!(CgroupStats::parse("max", "").is_err());
+    }
+
+    #[test]
+    fn virtual_paths_are_resolved_deterministically() {
+        let initial_paths = [
+            PathBuf::from("/opt/rootfs.ext4"),
+            PathBuf::from("/tmp/kernel"),
+            PathBuf::from(OsString::from_vec(b"/opt/\xFFdrive".to_vec())),
+            PathBuf::from(OsString::from_vec(b"/opt/\xFEdrive".to_vec())),
+        ];
+        let resolve_all = |initial_paths: &[PathBuf]| {
+            initial_paths
+                .iter()
+                .map(|initial_path| {
+                    let virtual_path = FlatVirtualPathResolver.resolve_virtual_path(initial_path).unwrap();
+                    let effective_path = PathBuf::from("/jail").jail_join(&virtual_path);
+                    (virtual_path, effective_path)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mappings = resolve_all(&initial_paths);
+        let mut reversed_initial_paths = initial_paths.clone();
+        reversed_initial_paths.reverse();
+        let mut reversed_mappings = resolve_all(&reversed_initial_paths);
+        reversed_mappings.reverse();
+
+        assert_eq!(mappings, resolve_all(&initial_paths));
+        assert_eq!(mappings, reversed_mappings);
+        assert_ne!(mappings[2], mappings[3]);
+        assert_eq!(mappings[2].1.as_os_str().as_bytes(), b"/jail/\xFFdrive".as_slice());
+    }
+
+    #[test]
+    fn exec_file_alias_is_used_for_paths() {
+        let installation = VmmInstallation::new("/opt/firecracker", "/opt/jailer", "/opt/snapshot-editor");
+        let executor = JailedVmmExecutor::new(
+            VmmArguments::new(VmmApiSocket::Disabled),
+            JailerArguments::new(VmmId::new("1").unwrap()).chroot_base_dir("/jails"),
+            FlatVirtualPathResolver,
+        );
+        assert_eq!(
+            executor.get_paths(&installation).1,
+            PathBuf::from("/jails/firecracker/1/root")
+        );
+        assert_eq!(
+            executor.get_exec_file_path(&installation),
+            PathBuf::from("/opt/firecracker")
+        );
+
+        let executor = executor.exec_file_alias("firecracker-web");
+        assert_eq!(
+            executor.get_paths(&installation).1,
+            PathBuf::from("/jails/firecracker-web/1/root")
+        );
+        assert_eq!(
+            executor.get_exec_file_path(&installation),
+            PathBuf::from("/jails/firecracker-web/1/firecracker-web")
+        );
+        assert_eq!(
+            executor.get_cgroup_path(&installation),
+            PathBuf::from("/sys/fs/cgroup/firecracker-web/1")
+        );
+    }
+
+    #[test]
+    fn exec_file_alias_must_be_a_file_name() {
+        let executor = || {
+            JailedVmmExecutor::new(
+                VmmArguments::new(VmmApiSocket::Disabled),
+                JailerArguments::new(VmmId::new("1").unwrap()),
+                FlatVirtualPathResolver,
+            )
+        };
+        executor().validate_exec_file_alias().unwrap();
+        executor()
+            .exec_file_alias("firecracker-web")
+            .validate_exec_file_alias()
+            .unwrap();
+
+        for exec_file_alias in ["", "/firecracker", "jails/firecracker", "..", "."] {
+            assert!(matches!(
+                executor().exec_file_alias(exec_file_alias).validate_exec_file_alias(),
+                Err(VmmExecutorError::InvalidExecFileAlias(alias)) if alias == exec_file_alias
+            ));
+        }
+    }
+
     fn assert_virtual_path_resolver<V: VirtualPathResolver>(resolver: &V, path: &str, expectation: &str) {
         assert_eq!(
             resolver
@@ -0,0 +1,302 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use super::{VmmExecutor, VmmExecutorContext, VmmExecutorError, process_handle::ProcessHandle};
+use crate::{
+    process_spawner::ProcessSpawner,
+    runtime::Runtime,
+    vmm::{
+        arguments::{
+            VmmApiSocket, VmmArguments,
+            command_modifier::{
+                CommandModifier, EnvCommandModifier, NumaCommandModifier, SchedulerCommandModifier, SchedulingPolicy,
+            },
+        },
+        id::{VmId, VmmId},
+        installation::VmmInstallation,
+        ownership::{downgrade_owner_recursively, upgrade_owner},
+        resource::ResourceType,
+    },
+};
+
+/// A [VmmExecutor] that uses the "firecracker" binary directly, assuming that the current process is already running
+/// inside of the target cgroup and namespaces provided by its environment, which is the case for Firecracker running
+/// inside of a container (for example, a Kubernetes pod). Unlike with the "jailer", no cgroup, namespace or chroot setup
+/// is performed, but unlike with unrestricted execution, moved resources are placed into a working directory that is
+/// exclusively owned by this executor, has its ownership downgraded before invocation and is removed during cleanup.
+#[derive(Debug)]
+pub struct ManagedVmmExecutor {
+    vmm_arguments: VmmArguments,
+    working_dir: PathBuf,
+    command_modifier_chain: Vec<Box<dyn CommandModifier>>,
+    disable_pipes: bool,
+    id: Option<VmmId>,
+}
+
+impl ManagedVmmExecutor {
+    /// Create a new [ManagedVmmExecutor] from a [VmmArguments] instance and a working directory [PathBuf], into which
+    /// moved resources will be placed with their filenames preserved, meaning that the filenames of all moved
+    /// resources must be distinct.
+    pub fn new<P: Into<PathBuf>>(vmm_arguments: VmmArguments, working_dir: P) -> Self {
+        Self {
+            vmm_arguments,
+            working_dir: working_dir.into(),
+            command_modifier_chain: Vec::new(),
+            disable_pipes: false,
+            id: None,
+        }
+    }
+
+    /// Add a [CommandModifier] implementation to the end of the [CommandModifier] chain.
+    pub fn command_modifier<C: CommandModifier>(mut self, command_modifier: C) -> Self {
+        self.command_modifier_chain.push(Box::new(command_modifier));
+        self
+    }
+
+    /// Sequentially insert an iterator of boxed [CommandModifier]s to the end of the [CommandModifier] chain.
+    pub fn command_modifiers<I: IntoIterator<Item = Box<dyn CommandModifier>>>(mut self, command_modifiers: I) -> Self {
+        self.command_modifier_chain.extend(command_modifiers);
+        self
+    }
+
+    /// Append an [EnvCommandModifier] setting "RUST_BACKTRACE=1" to the [CommandModifier] chain.
+    pub fn with_backtrace(self) -> Self {
+        self.command_modifier(EnvCommandModifier::new().var("RUST_BACKTRACE", "1"))
+    }
+
+    /// Append a [NumaCommandModifier] for the NUMA node with the given index to the [CommandModifier] chain.
+    pub fn with_numa_node(self, node: u32) -> Self {
+        self.command_modifier(NumaCommandModifier::new(node))
+    }
+
+    /// Append a [SchedulerCommandModifier] with the given [SchedulingPolicy] and priority to the [CommandModifier] chain.
+    pub fn with_scheduler(self, policy: SchedulingPolicy, priority: u8) -> Self {
+        self.command_modifier(SchedulerCommandModifier::new(policy, priority))
+    }
+
+    /// Configure the [ManagedVmmExecutor] to disable the pipes of the [ProcessHandle]'s process, meaning that
+    /// they won't be accessible via a [ProcessHandle::get_pipes] call.
+    pub fn disable_pipes(mut self) -> Self {
+        self.disable_pipes = true;
+        self
+    }
+
+    /// Set an optional [VmmId] for Firecracker to use. If not specified, it is derived from the [VmId] of the VM being
+    /// prepared, or a default value decided on by Firecracker itself is used outside of a VM.
+    pub fn id(mut self, id: VmmId) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    /// Get a shared reference to the working directory of this [ManagedVmmExecutor].
+    pub fn get_working_dir(&self) -> &Path {
+        &self.working_dir
+    }
+}
+
+impl VmmExecutor for ManagedVmmExecutor {
+    fn get_socket_path(&self, _installation: &VmmInstallation) -> Option<PathBuf> {
+        match &self.vmm_arguments.api_socket {
+            VmmApiSocket::Disabled => None,
+            VmmApiSocket::Enabled(path) => Some(path.clone()),
+        }
+    }
+
+    fn resolve_effective_path(&self, _installation: &VmmInstallation, local_path: PathBuf) -> PathBuf {
+        local_path
+    }
+
+    fn derive_id(&mut self, vm_id: &VmId) {
+        if self.id.is_none() {
+            self.id = Some(vm_id.clone().into());
+        }
+    }
+
+    async fn prepare<S: ProcessSpawner, R: Runtime>(
+        &self,
+        context: VmmExecutorContext<'_, S, R>,
+    ) -> Result<(), VmmExecutorError> {
+        // Moved resources are placed into the working directory by their filenames alone, so resources with the same
+        // filename would silently overwrite each other there
+        let mut moved_file_names = HashSet::new();
+
+        for resource in context.resources.iter().chain(self.vmm_arguments.get_resources()) {
+            if let ResourceType::Moved(_) = resource.get_type() {
+                let Some(file_name) = resource.get_initial_path().file_name() else {
+                    return Err(VmmExecutorError::ResourcePathHasNoFilename(
+                        resource.get_initial_path().to_owned(),
+                    ));
+                };
+
+                if !moved_file_names.insert(file_name) {
+                    return Err(VmmExecutorError::ResourcePathCollision(
+                        self.working_dir.join(file_name),
+                    ));
+                }
+            }
+        }
+
+        upgrade_owner(
+            &self.working_dir,
+            context.ownership_model,
+            &context.process_spawner,
+            &context.runtime,
+        )
+        .await
+        .map_err(VmmExecutorError::ChangeOwnerError)?;
+
+        if context
+            .runtime
+            .fs_exists(&self.working_dir)
+            .await
+            .map_err(VmmExecutorError::FilesystemError)?
+        {
+            context
+                .runtime
+                .fs_remove_dir_all(&self.working_dir)
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?;
+        }
+
+        context
+            .runtime
+            .fs_create_dir_all(&self.working_dir)
+            .await
+            .map_err(VmmExecutorError::FilesystemError)?;
+
+        if let VmmApiSocket::Enabled(ref socket_path) = self.vmm_arguments.api_socket {
+            upgrade_owner(
+                socket_path,
+                context.ownership_model,
+                &context.process_spawner,
+                &context.runtime,
+            )
+            .await
+            .map_err(VmmExecutorError::ChangeOwnerError)?;
+
+            if context
+                .runtime
+                .fs_exists(socket_path)
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?
+            {
+                context
+                    .runtime
+                    .fs_remove_file(socket_path)
+                    .await
+                    .map_err(VmmExecutorError::FilesystemError)?;
+            }
+        }
+
+        for resource in context.resources.iter().chain(self.vmm_arguments.get_resources()) {
+            match resource.get_type() {
+                ResourceType::Moved(_) => {
+                    let Some(file_name) = resource.get_initial_path().file_name() else {
+                        return Err(VmmExecutorError::ResourcePathHasNoFilename(
+                            resource.get_initial_path().to_owned(),
+                        ));
+                    };
+
+                    // Firecracker isn't chrooted, so the virtual path is the same as the effective path
+                    let effective_path = self.working_dir.join(file_name);
+                    resource.start_initialization(effective_path.clone(), Some(effective_path))
+                }
+                _ => resource.start_initialization_with_same_path(),
+            }
+            .map_err(VmmExecutorError::ResourceSystemError)?;
+        }
+
+        Ok(())
+    }
+
+    async fn invoke<S: ProcessSpawner, R: Runtime>(
+        &self,
+        context: VmmExecutorContext<'_, S, R>,
+        config_path: Option<PathBuf>,
+    ) -> Result<ProcessHandle<R>, VmmExecutorError> {
+        downgrade_owner_recursively(&self.working_dir, context.ownership_model, &context.runtime)
+            .await
+            .map_err(VmmExecutorError::ChangeOwnerError)?;
+
+        let mut arguments = self.vmm_arguments.join(config_path);
+        let mut binary_path = context.installation.get_firecracker_path().to_owned();
+
+        for command_modifier in self.command_modifier_chain.iter() {
+            command_modifier.apply(&mut binary_path, &mut arguments);
+        }
+
+        if let Some(ref id) = self.id {
+            arguments.push("--id".into());
+            arguments.push(id.as_ref().into());
+        }
+
+        let child = context
+            .process_spawner
+            .spawn(&binary_path, arguments.as_slice(), self.disable_pipes, &context.runtime)
+            .await
+            .map_err(VmmExecutorError::ProcessSpawnFailed)?;
+        Ok(ProcessHandle::from_child(child, self.disable_pipes))
+    }
+
+    async fn cleanup<S: ProcessSpawner, R: Runtime>(
+        &self,
+        context: VmmExecutorContext<'_, S, R>,
+    ) -> Result<(), VmmExecutorError> {
+        if let VmmApiSocket::Enabled(ref socket_path) = self.vmm_arguments.api_socket {
+            upgrade_owner(
+                socket_path,
+                context.ownership_model,
+                &context.process_spawner,
+                &context.runtime,
+            )
+            .await
+            .map_err(VmmExecutorError::ChangeOwnerError)?;
+
+            if context
+                .runtime
+                .fs_exists(socket_path)
+                .await
+                .map_err(VmmExecutorError::FilesystemError)?
+            {
+                context
+                    .runtime
+                    .fs_remove_file(socket_path)
+                    .await
+                    .map_err(VmmExecutorError::FilesystemError)?;
+            }
+        }
+
+        for resource in context.resources.iter().chain(self.vmm_arguments.get_resources()) {
+            if !matches!(resource.get_type(), ResourceType::Moved(_)) {
+                resource
+                    .start_disposal()
+                    .map_err(VmmExecutorError::ResourceSystemError)?;
+            }
+        }
+
+        // Moved resources are removed together with the working directory, while the devices of snapshotted ones
+        // would outlive it if they weren't removed beforehand
+        for resource in context.resources.iter().chain(self.vmm_arguments.get_resources()) {
+            resource
+                .remove_snapshot_device()
+                .map_err(VmmExecutorError::FilesystemError)?;
+        }
+
+        upgrade_owner(
+            &self.working_dir,
+            context.ownership_model,
+            &context.process_spawner,
+            &context.runtime,
+        )
+        .await
+        .map_err(VmmExecutorError::ChangeOwnerError)?;
+
+        context
+            .runtime
+            .fs_remove_dir_all(&self.working_dir)
+            .await
+            .map_err(VmmExecutorError::FilesystemError)
+    }
+}
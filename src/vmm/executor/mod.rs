@@ -5,6 +5,7 @@ use jailed::VirtualPathResolverError;
 use process_handle::ProcessHandle;
 
 use super::{
+    id::VmId,
     installation::VmmInstallation,
     ownership::{ChangeOwnerError, VmmOwnershipModel},
     resource::{Resource, system::ResourceSystemError},
@@ -17,6 +18,9 @@ pub mod either;
 #[cfg(feature = "jailed-vmm-executor")]
 #[cfg_attr(docsrs, doc(cfg(feature = "jailed-vmm-executor")))]
 pub mod jailed;
+#[cfg(feature = "managed-vmm-executor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "managed-vmm-executor")))]
+pub mod managed;
 #[cfg(feature = "unrestricted-vmm-executor")]
 #[cfg_attr(docsrs, doc(cfg(feature = "unrestricted-vmm-executor")))]
 pub mod unrestricted;
@@ -43,10 +47,20 @@ pub enum VmmExecutorError {
     /// The given owned [PathBuf] was expected to have a directory parent, yet it was located at the root
     /// of the filesystem.
     ExpectedDirectoryParentMissing(PathBuf),
+    /// The initial path of a resource that had to be placed into a directory had no filename.
+    ResourcePathHasNoFilename(PathBuf),
+    /// The initial paths of multiple resources that had to be placed into a directory had the same filename, so they
+    /// would have been placed at the same given [PathBuf].
+    ResourcePathCollision(PathBuf),
     /// A [VirtualPathResolverError] occurred.
     #[cfg(feature = "jailed-vmm-executor")]
     #[cfg_attr(docsrs, doc(cfg(feature = "jailed-vmm-executor")))]
     VirtualPathResolverError(VirtualPathResolverError),
+    /// The given file name that the "firecracker" binary was configured to be executed under is empty or isn't a
+    /// plain file name.
+    #[cfg(feature = "jailed-vmm-executor")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "jailed-vmm-executor")))]
+    InvalidExecFileAlias(String),
     /// Another type of error occurred within the [VmmExecutor] implementation's code. This error variant is
     /// reserved for custom [VmmExecutor] implementations and isn't used by the built-in ones.
     Other(Box<dyn std::error::Error + Send + Sync>),
@@ -73,11 +87,25 @@ impl std::fmt::Display for VmmExecutorError {
             VmmExecutorError::ExpectedDirectoryParentMissing(path) => {
                 write!(f, "A parent of a directory is missing: {}", path.display())
             }
+            VmmExecutorError::ResourcePathHasNoFilename(path) => {
+                write!(f, "The initial path of a resource has no filename: {}", path.display())
+            }
+            VmmExecutorError::ResourcePathCollision(path) => {
+                write!(
+                    f,
+                    "Multiple resources would be placed at the same path: {}",
+                    path.display()
+                )
+            }
             VmmExecutorError::ProcessSpawnFailed(err) => write!(f, "Spawning a process failed: {err}"),
             #[cfg(feature = "jailed-vmm-executor")]
             VmmExecutorError::VirtualPathResolverError(err) => {
                 write!(f, "Invoking the virtual path resolver failed: {err}")
             }
+            #[cfg(feature = "jailed-vmm-executor")]
+            VmmExecutorError::InvalidExecFileAlias(exec_file_alias) => {
+                write!(f, "The exec file alias is not a valid file name: {exec_file_alias:?}")
+            }
             VmmExecutorError::ProcessExitedWithNonZeroStatus(exit_status) => {
                 write!(f, "A watched process exited with a non-zero exit status: {exit_status}")
             }
@@ -95,6 +123,10 @@ pub trait VmmExecutor: Send + Sync {
     /// Resolve an effective path of a resource from its virtual path.
     fn resolve_effective_path(&self, installation: &VmmInstallation, local_path: PathBuf) -> PathBuf;
 
+    /// Derive the ID of the VMM, such as the jail ID, from the given [VmId] of the VM it is executed for, unless an ID
+    /// was explicitly set. This is called before the VM is prepared, and does nothing by default.
+    fn derive_id(&mut self, _vm_id: &VmId) {}
+
     /// Prepare all transient resources for the VMM invocation. It is assumed that an implementation of this function
     /// appropriately schedules the initialization of all [Resource]s inside the given [VmmExecutorContext] to effective
     /// and virtual paths according to the executor's discretion. It will therefore be necessary to manually synchronize
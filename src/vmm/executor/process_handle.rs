@@ -66,12 +66,19 @@ enum ProcessHandleInner<R: Runtime> {
         pipes_dropped: bool,
     },
     Pidfd {
+        pid: i32,
         raw_pidfd: RawFd,
-        exited_rx: futures_channel::oneshot::Receiver<ExitStatus>,
-        exited: Option<ExitStatus>,
+        exited_rx: futures_channel::oneshot::Receiver<Option<ExitStatus>>,
+        exited: Option<Option<ExitStatus>>,
     },
 }
 
+// The exit status of a detached process that has already been reaped can't be determined, in which case a successful
+// one is assumed for compatibility, unless the unknown status is explicitly requested
+fn assume_exit_status(exit_status: Option<ExitStatus>) -> ExitStatus {
+    exit_status.unwrap_or(ExitStatus::from_raw(0))
+}
+
 impl<R: Runtime> ProcessHandle<R> {
     /// Create a [ProcessHandle] from a [RuntimeChild] that is attached to the current process.
     pub fn from_child(child: R::Child, pipes_dropped: bool) -> Self {
@@ -87,7 +94,7 @@ impl<R: Runtime> ProcessHandle<R> {
         let async_pidfd = runtime.create_async_fd(pidfd)?;
 
         runtime.clone().spawn_task(async move {
-            let mut exit_status = ExitStatus::from_raw(0);
+            let mut exit_status = None;
 
             if async_pidfd.readable().await.is_ok() {
                 if let Ok(content) = runtime
@@ -95,7 +102,7 @@ impl<R: Runtime> ProcessHandle<R> {
                     .await
                 {
                     if let Some(status_raw) = content.split_whitespace().last().and_then(|value| value.parse().ok()) {
-                        exit_status = ExitStatus::from_raw(status_raw);
+                        exit_status = Some(ExitStatus::from_raw(status_raw));
                     }
                 }
             }
@@ -104,12 +111,29 @@ impl<R: Runtime> ProcessHandle<R> {
         });
 
         Ok(Self(ProcessHandleInner::Pidfd {
+            pid,
             raw_pidfd,
             exited_rx,
             exited: None,
         }))
     }
 
+    /// Get the PID of the process, or [None] if an attached process has already been reaped.
+    pub fn get_pid(&self) -> Option<i32> {
+        match self.0 {
+            ProcessHandleInner::Child {
+                ref child,
+                pipes_dropped: _,
+            } => child.id().map(|pid| pid as i32),
+            ProcessHandleInner::Pidfd {
+                pid,
+                raw_pidfd: _,
+                exited_rx: _,
+                exited: _,
+            } => Some(pid),
+        }
+    }
+
     /// Send a SIGKILL signal to the process.
     pub fn send_sigkill(&mut self) -> Result<(), std::io::Error> {
         match self.0 {
@@ -118,6 +142,7 @@ impl<R: Runtime> ProcessHandle<R> {
                 pipes_dropped: _,
             } => child.kill(),
             ProcessHandleInner::Pidfd {
+                pid: _,
                 raw_pidfd,
                 exited_rx: _,
                 exited,
@@ -131,14 +156,22 @@ impl<R: Runtime> ProcessHandle<R> {
         }
     }
 
-    /// Wait for the process to have exited.
+    /// Wait for the process to have exited. If the process is detached and its [ExitStatus] can't be determined
+    /// since it has already been reaped by its parent, a successful [ExitStatus] is assumed.
     pub async fn wait(&mut self) -> Result<ExitStatus, std::io::Error> {
+        self.wait_for_exit_status().await.map(assume_exit_status)
+    }
+
+    /// Wait for the process to have exited like with [ProcessHandle::wait], but return [None] instead of assuming a
+    /// successful [ExitStatus] if the process is detached and has already been reaped by its parent.
+    pub async fn wait_for_exit_status(&mut self) -> Result<Option<ExitStatus>, std::io::Error> {
         match self.0 {
             ProcessHandleInner::Child {
                 ref mut child,
                 pipes_dropped: _,
-            } => child.wait().await,
+            } => child.wait().await.map(Some),
             ProcessHandleInner::Pidfd {
+                pid: _,
                 raw_pidfd: _,
                 ref mut exited_rx,
                 ref mut exited,
@@ -164,17 +197,18 @@ impl<R: Runtime> ProcessHandle<R> {
                 pipes_dropped: _,
             } => child.try_wait(),
             ProcessHandleInner::Pidfd {
+                pid: _,
                 raw_pidfd: _,
                 ref mut exited_rx,
                 ref mut exited,
             } => {
                 if let Some(exited) = exited {
-                    return Ok(Some(*exited));
+                    return Ok(Some(assume_exit_status(*exited)));
                 }
 
                 if let Ok(Some(exit_status)) = exited_rx.try_recv() {
                     *exited = Some(exit_status);
-                    Ok(Some(exit_status))
+                    Ok(Some(assume_exit_status(exit_status)))
                 } else {
                     Ok(None)
                 }
@@ -185,8 +219,40 @@ impl<R: Runtime> ProcessHandle<R> {
     /// Try to get the [ProcessHandlePipes] for this process. Only possible for attached (child)
     /// processes that haven't had their pipes dropped when creating.
     pub fn get_pipes(&mut self) -> Result<ProcessHandlePipes<R::Child>, ProcessHandlePipesError> {
+        let child = self.get_child_with_pipes()?;
+        let stdout = child
+            .take_stdout()
+            .ok_or(ProcessHandlePipesError::PipesWereAlreadyTaken)?;
+        let stderr = child
+            .take_stderr()
+            .ok_or(ProcessHandlePipesError::PipesWereAlreadyTaken)?;
+        let stdin = child
+            .take_stdin()
+            .ok_or(ProcessHandlePipesError::PipesWereAlreadyTaken)?;
+
+        Ok(ProcessHandlePipes { stdout, stderr, stdin })
+    }
+
+    /// Try to take out only the stdout pipe of this process, under the same conditions as [ProcessHandle::get_pipes].
+    /// Subsequent calls to [ProcessHandle::get_pipes] will fail once this succeeds.
+    pub fn take_stdout(&mut self) -> Result<<R::Child as RuntimeChild>::Stdout, ProcessHandlePipesError> {
+        self.get_child_with_pipes()?
+            .take_stdout()
+            .ok_or(ProcessHandlePipesError::PipesWereAlreadyTaken)
+    }
+
+    /// Try to take out only the stderr pipe of this process, under the same conditions as [ProcessHandle::get_pipes].
+    /// Subsequent calls to [ProcessHandle::get_pipes] will fail once this succeeds.
+    pub fn take_stderr(&mut self) -> Result<<R::Child as RuntimeChild>::Stderr, ProcessHandlePipesError> {
+        self.get_child_with_pipes()?
+            .take_stderr()
+            .ok_or(ProcessHandlePipesError::PipesWereAlreadyTaken)
+    }
+
+    fn get_child_with_pipes(&mut self) -> Result<&mut R::Child, ProcessHandlePipesError> {
         match self.0 {
             ProcessHandleInner::Pidfd {
+                pid: _,
                 raw_pidfd: _,
                 exited_rx: _,
                 exited: _,
@@ -194,23 +260,10 @@ impl<R: Runtime> ProcessHandle<R> {
             ProcessHandleInner::Child {
                 ref mut child,
                 pipes_dropped,
-            } => {
-                if pipes_dropped {
-                    return Err(ProcessHandlePipesError::PipesWereDropped);
-                }
-
-                let stdout = child
-                    .take_stdout()
-                    .ok_or(ProcessHandlePipesError::PipesWereAlreadyTaken)?;
-                let stderr = child
-                    .take_stderr()
-                    .ok_or(ProcessHandlePipesError::PipesWereAlreadyTaken)?;
-                let stdin = child
-                    .take_stdin()
-                    .ok_or(ProcessHandlePipesError::PipesWereAlreadyTaken)?;
-
-                Ok(ProcessHandlePipes { stdout, stderr, stdin })
-            }
+            } => match pipes_dropped {
+                true => Err(ProcessHandlePipesError::PipesWereDropped),
+                false => Ok(child),
+            },
         }
     }
 }
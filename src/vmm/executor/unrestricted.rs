@@ -5,8 +5,13 @@ use crate::{
     process_spawner::ProcessSpawner,
     runtime::Runtime,
     vmm::{
-        arguments::{VmmApiSocket, VmmArguments, command_modifier::CommandModifier},
-        id::VmmId,
+        arguments::{
+            VmmApiSocket, VmmArguments,
+            command_modifier::{
+                CommandModifier, EnvCommandModifier, NumaCommandModifier, SchedulerCommandModifier, SchedulingPolicy,
+            },
+        },
+        id::{VmId, VmmId},
         installation::VmmInstallation,
         ownership::upgrade_owner,
         resource::ResourceType,
@@ -47,6 +52,21 @@ impl UnrestrictedVmmExecutor {
         self
     }
 
+    /// Append an [EnvCommandModifier] setting "RUST_BACKTRACE=1" to the [CommandModifier] chain.
+    pub fn with_backtrace(self) -> Self {
+        self.command_modifier(EnvCommandModifier::new().var("RUST_BACKTRACE", "1"))
+    }
+
+    /// Append a [NumaCommandModifier] for the NUMA node with the given index to the [CommandModifier] chain.
+    pub fn with_numa_node(self, node: u32) -> Self {
+        self.command_modifier(NumaCommandModifier::new(node))
+    }
+
+    /// Append a [SchedulerCommandModifier] with the given [SchedulingPolicy] and priority to the [CommandModifier] chain.
+    pub fn with_scheduler(self, policy: SchedulingPolicy, priority: u8) -> Self {
+        self.command_modifier(SchedulerCommandModifier::new(policy, priority))
+    }
+
     /// Configure the [UnrestrictedVmmExecutor] to disable the pipes of the [ProcessHandle]'s process, meaning that
     /// they won't be accessible via a [ProcessHandle::get_pipes] call.
     pub fn disable_pipes(mut self) -> Self {
@@ -54,8 +74,8 @@ impl UnrestrictedVmmExecutor {
         self
     }
 
-    /// Set an optional [VmmId] for Firecracker to use. If not specified, a default value decided on by Firecracker itself
-    /// will be used instead.
+    /// Set an optional [VmmId] for Firecracker to use. If not specified, it is derived from the [VmId] of the VM being
+    /// prepared, or a default value decided on by Firecracker itself is used outside of a VM.
     pub fn id(mut self, id: VmmId) -> Self {
         self.id = Some(id);
         self
@@ -74,6 +94,12 @@ impl VmmExecutor for UnrestrictedVmmExecutor {
         local_path
     }
 
+    fn derive_id(&mut self, vm_id: &VmId) {
+        if self.id.is_none() {
+            self.id = Some(vm_id.clone().into());
+        }
+    }
+
     async fn prepare<S: ProcessSpawner, R: Runtime>(
         &self,
         context: VmmExecutorContext<'_, S, R>,
@@ -76,9 +76,68 @@ impl TryFrom<String> for VmmId {
     }
 }
 
+/// A [VmId] is a stable identifier of a single VM, assigned to it when it is prepared, that allows correlating its
+/// jail directory, logs, metrics and traces. It is subject to the same restrictions as a [VmmId] and can be converted
+/// into one, so that it can also be used as the jail ID or as the ID of the Firecracker instance.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct VmId(VmmId);
+
+impl VmId {
+    /// Try to construct a user-provided [VmId] from a [String] by validating the [String]'s contents.
+    pub fn new<I: Into<String>>(id: I) -> Result<VmId, VmmIdError> {
+        VmmId::new(id).map(Self)
+    }
+
+    /// Generate a new [VmId] that is unique within the host, being derived from the current time, the current
+    /// process' PID and a process-wide counter.
+    pub fn generate() -> VmId {
+        static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or_default();
+        let counter = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+        Self(VmmId(format!("vm-{timestamp:x}-{:x}-{counter:x}", std::process::id())))
+    }
+}
+
+impl AsRef<str> for VmId {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl std::fmt::Display for VmId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.0)
+    }
+}
+
+impl From<VmmId> for VmId {
+    fn from(value: VmmId) -> Self {
+        Self(value)
+    }
+}
+
+impl From<VmId> for VmmId {
+    fn from(value: VmId) -> Self {
+        value.0
+    }
+}
+
+impl TryFrom<String> for VmId {
+    type Error = VmmIdError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::vmm::id::{VmmId, VmmIdError};
+    use crate::vmm::id::{VmId, VmmId, VmmIdError};
 
     #[test]
     fn vmm_id_rejects_when_too_short() {
@@ -110,4 +169,15 @@ mod tests {
             VmmId::new(str).unwrap();
         }
     }
+
+    #[test]
+    fn vm_id_generates_unique_valid_ids() {
+        let first = VmId::generate();
+        let second = VmId::generate();
+        assert_ne!(first, second);
+
+        for id in [first, second] {
+            VmmId::new(id.to_string()).unwrap();
+        }
+    }
 }
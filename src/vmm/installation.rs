@@ -4,6 +4,8 @@ use std::{
     sync::Arc,
 };
 
+use futures_util::AsyncReadExt;
+
 use crate::runtime::Runtime;
 
 /// A [VmmInstallation] encapsulates release binaries of the most important automatable VMM components:
@@ -35,6 +37,9 @@ pub enum VmmInstallationVerificationError {
     BinaryIsOfIncorrectType,
     /// An installation binary didn't match the expected version.
     BinaryDoesNotMatchExpectedVersion,
+    /// An installation binary is an ELF executable for the given ELF machine type, which doesn't match the host's
+    /// architecture, such as an x86_64 binary being installed onto an aarch64 host.
+    BinaryHasIncorrectArchitecture(u16),
 }
 
 impl std::error::Error for VmmInstallationVerificationError {}
@@ -57,6 +62,13 @@ impl std::fmt::Display for VmmInstallationVerificationError {
             VmmInstallationVerificationError::BinaryDoesNotMatchExpectedVersion => {
                 write!(f, "A binary inside the installation does not match the given version")
             }
+            VmmInstallationVerificationError::BinaryHasIncorrectArchitecture(machine) => {
+                let host_architecture = std::env::consts::ARCH;
+                write!(
+                    f,
+                    "A binary inside the installation was built for ELF machine {machine}, not for {host_architecture}"
+                )
+            }
         }
     }
 }
@@ -87,8 +99,9 @@ impl VmmInstallation {
         &self.0.snapshot_editor_path
     }
 
-    /// Verify the [VmmInstallation] using the given [Runtime] by ensuring all binaries exist,
-    /// are executable and yield the correct type and version when spawned and waited on with "--version".
+    /// Verify the [VmmInstallation] using the given [Runtime] by ensuring all binaries exist, were built for the host's
+    /// architecture according to their ELF headers, are executable and yield the correct type and version when spawned
+    /// and waited on with "--version".
     pub async fn verify<R: Runtime, V: AsRef<str>>(
         &self,
         expected_version: V,
@@ -111,6 +124,57 @@ impl VmmInstallation {
         )?;
         Ok(())
     }
+
+    /// Preload the "firecracker" and "jailer" binaries of this [VmmInstallation] into the host's page cache by fully
+    /// reading them via the given [Runtime], returning the total amount of preloaded bytes.
+    pub async fn preload<R: Runtime>(&self, runtime: &R) -> Result<u64, std::io::Error> {
+        let (firecracker_size, jailer_size) = futures_util::try_join!(
+            preload_imp(runtime, &self.0.firecracker_path),
+            preload_imp(runtime, &self.0.jailer_path)
+        )?;
+        Ok(firecracker_size + jailer_size)
+    }
+}
+
+async fn preload_imp<R: Runtime>(runtime: &R, path: &Path) -> Result<u64, std::io::Error> {
+    let file = runtime.fs_open_file_for_read(path).await?;
+    futures_util::io::copy(file, &mut futures_util::io::sink()).await
+}
+
+/// The ELF machine type of the host architecture, which is only known for the architectures supported by Firecracker.
+const HOST_ELF_MACHINE: Option<u16> = if cfg!(target_arch = "x86_64") {
+    Some(62) // EM_X86_64
+} else if cfg!(target_arch = "aarch64") {
+    Some(183) // EM_AARCH64
+} else {
+    None
+};
+
+/// Read the machine type from the ELF header of the binary at the given [Path], returning [None] if it isn't a
+/// little-endian ELF file, in which case executing it reveals whether it's usable.
+async fn read_elf_machine<R: Runtime>(
+    runtime: &R,
+    path: &Path,
+) -> Result<Option<u16>, VmmInstallationVerificationError> {
+    let mut file = runtime
+        .fs_open_file_for_read(path)
+        .await
+        .map_err(VmmInstallationVerificationError::FilesystemError)?;
+    // e_ident is 16 bytes long and is followed by the 2-byte e_type and the 2-byte e_machine
+    let mut header = [0; 20];
+
+    match file.read_exact(&mut header).await {
+        Ok(()) => {}
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(VmmInstallationVerificationError::FilesystemError(err)),
+    }
+
+    // EI_DATA at index 5 is ELFDATA2LSB (1) for little-endian binaries, which both x86_64 and aarch64 binaries are
+    if !header.starts_with(b"\x7fELF") || header[5] != 1 {
+        return Ok(None);
+    }
+
+    Ok(Some(u16::from_le_bytes([header[18], header[19]])))
 }
 
 async fn verify_imp<R: Runtime>(
@@ -127,6 +191,14 @@ async fn verify_imp<R: Runtime>(
         return Err(VmmInstallationVerificationError::BinaryMissing);
     }
 
+    if let (Some(machine), Some(host_machine)) = (read_elf_machine(runtime, path).await?, HOST_ELF_MACHINE) {
+        if machine != host_machine {
+            return Err(VmmInstallationVerificationError::BinaryHasIncorrectArchitecture(
+                machine,
+            ));
+        }
+    }
+
     let output = runtime
         .run_process(path.as_os_str(), &[OsString::from("--version")], true, false)
         .await
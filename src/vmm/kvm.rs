@@ -0,0 +1,92 @@
+use std::{
+    os::fd::AsRawFd,
+    path::{Path, PathBuf},
+};
+
+use crate::runtime::Runtime;
+
+/// The default path to the KVM device on Linux hosts.
+pub const KVM_DEVICE_PATH: &str = "/dev/kvm";
+
+/// The only stable KVM API version, which is the one expected by Firecracker.
+pub const KVM_STABLE_API_VERSION: i32 = 12;
+
+/// Information about the host's KVM device, as reported by [check_kvm].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KvmInfo {
+    /// The path to the KVM device that was checked.
+    pub device_path: PathBuf,
+    /// Whether the KVM device exists on the host.
+    pub exists: bool,
+    /// Whether the current process has permission to open the KVM device for reading and writing.
+    pub has_permission: bool,
+    /// The KVM API version reported by the device, or [None] if the device couldn't be opened.
+    pub api_version: Option<i32>,
+}
+
+impl KvmInfo {
+    /// Whether the KVM device is accessible to the current process and reports the stable API version,
+    /// meaning that Firecracker should be able to use it.
+    pub fn is_accessible(&self) -> bool {
+        self.exists && self.has_permission && self.api_version == Some(KVM_STABLE_API_VERSION)
+    }
+}
+
+/// An error that can be emitted by [check_kvm].
+#[derive(Debug)]
+pub enum KvmError {
+    /// An I/O error occurred while interacting with the filesystem.
+    FilesystemError(std::io::Error),
+    /// The KVM_GET_API_VERSION ioctl failed on the opened KVM device.
+    IoctlError(std::io::Error),
+}
+
+impl std::error::Error for KvmError {}
+
+impl std::fmt::Display for KvmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KvmError::FilesystemError(err) => write!(f, "A filesystem operation backed by the runtime failed: {err}"),
+            KvmError::IoctlError(err) => write!(f, "The KVM_GET_API_VERSION ioctl failed: {err}"),
+        }
+    }
+}
+
+/// Check the availability of KVM on the host at the default [KVM_DEVICE_PATH] via the given [Runtime] and
+/// the syscall backend, reporting whether it exists, whether the current process has permission to use it and
+/// its API version. This is useful for producing an early and clear error before creating any VM.
+pub async fn check_kvm<R: Runtime>(runtime: &R) -> Result<KvmInfo, KvmError> {
+    check_kvm_at(runtime, KVM_DEVICE_PATH).await
+}
+
+/// Check the availability of KVM on the host at a custom device path, which is otherwise identical to [check_kvm].
+pub async fn check_kvm_at<R: Runtime, P: AsRef<Path>>(runtime: &R, device_path: P) -> Result<KvmInfo, KvmError> {
+    let device_path = device_path.as_ref();
+    let mut info = KvmInfo {
+        device_path: device_path.to_owned(),
+        exists: false,
+        has_permission: false,
+        api_version: None,
+    };
+
+    if !runtime
+        .fs_exists(device_path)
+        .await
+        .map_err(KvmError::FilesystemError)?
+    {
+        return Ok(info);
+    }
+
+    info.exists = true;
+
+    // opening a character device doesn't block, so no runtime-specific file opening is needed
+    let file = match std::fs::OpenOptions::new().read(true).write(true).open(device_path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => return Ok(info),
+        Err(err) => return Err(KvmError::FilesystemError(err)),
+    };
+
+    info.has_permission = true;
+    info.api_version = Some(crate::syscall::kvm_get_api_version(file.as_raw_fd()).map_err(KvmError::IoctlError)?);
+    Ok(info)
+}
@@ -7,12 +7,13 @@
 //! - VMM installations (including the possibility to verify them at runtime).
 //! - VMM resource management (resources and resource systems).
 //! - VMM ownership models and implementation helpers.
+//! - KVM availability and API version checks for the host.
 //!
 //! With the `vmm-executor` feature, a VMM executor trait is additionally available that abstracts
 //! away the details of possibly jailing or not jailing a VMM, as well as other details of a VMM's lifecycle.
 //!
-//! The `unrestricted-vmm-executor`, `jailed-vmm-executor` and `either-vmm-executor` features enable the
-//! respective default implementations of VMM executors.
+//! The `unrestricted-vmm-executor`, `jailed-vmm-executor`, `managed-vmm-executor` and `either-vmm-executor` features
+//! enable the respective default implementations of VMM executors.
 //!
 //! With the `vmm-process` feature, a VMM process abstraction that works on top of a VMM executor
 //! and provides additional useful functionality like an HTTP connection pool is additionally available.
@@ -27,6 +28,8 @@ pub mod installation;
 
 pub mod ownership;
 
+pub mod kvm;
+
 #[cfg(feature = "vmm-executor")]
 #[cfg_attr(docsrs, doc(cfg(feature = "vmm-executor")))]
 pub mod executor;
@@ -1,7 +1,15 @@
-use std::{future::Future, path::PathBuf, process::ExitStatus};
+use std::{
+    collections::VecDeque,
+    future::Future,
+    path::PathBuf,
+    process::ExitStatus,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use async_once_cell::OnceCell;
 use bytes::{Bytes, BytesMut};
+use futures_util::{AsyncRead, AsyncReadExt};
 use http::{Request, Response, StatusCode, Uri, uri::InvalidUri};
 use http_body_util::{BodyExt, Full};
 use hyper::body::{Body, Incoming};
@@ -14,7 +22,10 @@ use super::{
         process_handle::{ProcessHandle, ProcessHandlePipes, ProcessHandlePipesError},
     },
     ownership::{ChangeOwnerError, upgrade_owner},
-    resource::system::{ResourceSystem, ResourceSystemError},
+    resource::{
+        ResourceState, ResourceType,
+        system::{ResourceSystem, ResourceSystemError},
+    },
 };
 use crate::{
     process_spawner::ProcessSpawner,
@@ -35,6 +46,73 @@ pub struct VmmProcess<E: VmmExecutor, S: ProcessSpawner, R: Runtime> {
     process_handle: Option<ProcessHandle<R>>,
     state: VmmProcessState,
     hyper_client: OnceCell<Client<UnixConnector<R::SocketBackend>, Full<Bytes>>>,
+    preconstructed_hyper_client: Option<Client<UnixConnector<R::SocketBackend>, Full<Bytes>>>,
+    skip_api_socket_owner_upgrade: bool,
+    sigkill_target: VmmProcessSigkillTarget,
+    diagnostic_capture_config: Option<DiagnosticCaptureConfig>,
+    diagnostic_stdout: Option<DiagnosticBuffer>,
+    diagnostic_stderr: Option<DiagnosticBuffer>,
+    prepare_duration: Option<Duration>,
+    invoke_duration: Option<Duration>,
+}
+
+type DiagnosticBuffer = Arc<Mutex<VecDeque<u8>>>;
+
+/// The configuration of capturing the output of a [VmmProcess] for crash diagnostics, set via
+/// [VmmProcess::set_diagnostic_capture].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DiagnosticCaptureConfig {
+    /// The maximum amount of bytes retained for each captured pipe. Once it is exceeded, the oldest bytes are
+    /// discarded, so that only the last bytes, which are the most relevant to a crash, are retained.
+    pub buffer_size: usize,
+    /// Whether to capture the stdout pipe in addition to the stderr pipe. Since Firecracker writes the guest's serial
+    /// console to its stdout, this is disabled by default.
+    pub capture_stdout: bool,
+}
+
+impl Default for DiagnosticCaptureConfig {
+    fn default() -> Self {
+        Self {
+            buffer_size: 64 * 1024,
+            capture_stdout: false,
+        }
+    }
+}
+
+/// The output of a [VmmProcess] captured according to its [DiagnosticCaptureConfig].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DiagnosticOutput {
+    /// The last bytes written to the stdout pipe, which is empty if stdout isn't captured.
+    pub stdout: Vec<u8>,
+    /// The last bytes written to the stderr pipe.
+    pub stderr: Vec<u8>,
+}
+
+/// The target of the SIGKILL signal sent by [VmmProcess::send_sigkill].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VmmProcessSigkillTarget {
+    /// Only the process pointed to by the [ProcessHandle] is killed, either via its pidfd or as a child process.
+    #[default]
+    Process,
+    /// The entire process group of the process pointed to by the [ProcessHandle] is killed. This ensures that no
+    /// process is left orphaned when the jailer is daemonized and spawns Firecracker into a new PID namespace, since
+    /// killing only the jailer's process doesn't necessarily kill all of its descendants.
+    ///
+    /// If the process shares its process group with the control process (which is the case when the jailer isn't
+    /// daemonized), only the process itself is killed in order not to kill the control process as well.
+    ProcessGroup,
+}
+
+/// The seccomp mode of a [VmmProcess]'s underlying process, as reported by the kernel in the "Seccomp" field of the
+/// process' procfs status file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SeccompMode {
+    /// The process isn't running under seccomp.
+    Disabled,
+    /// The process is running under strict seccomp, which only permits a minimal fixed set of syscalls.
+    Strict,
+    /// The process is running under seccomp with a BPF filter, which is the mode Firecracker applies its filters in.
+    Filter,
 }
 
 /// The state of a [VmmProcess]. Keep in mind that the [VmmProcess] lifecycle is not that of the VM!
@@ -105,10 +183,40 @@ pub enum VmmProcessError {
     /// A [ResourceSystemError] occurred while performing manual synchronization with the [ResourceSystem]
     /// after a [VmmExecutor] prepare/invoke/cleanup invocation.
     ResourceSystemError(ResourceSystemError),
+    /// An I/O error occurred while reading or parsing the procfs status file of the process.
+    ProcfsStatusError(std::io::Error),
 }
 
 impl std::error::Error for VmmProcessError {}
 
+impl VmmProcessError {
+    /// Get the HTTP status code that most appropriately represents this [VmmProcessError] when it is exposed by an
+    /// HTTP API built on top of fctools.
+    pub fn http_status(&self) -> u16 {
+        match self {
+            VmmProcessError::IncorrectState(_) | VmmProcessError::ProcessHandlePipesError(_) => 409,
+            VmmProcessError::ApiSocketDisabled => 501,
+            VmmProcessError::InvalidUri { uri: _, error: _ } => 400,
+            VmmProcessError::RequestError(_) => 503,
+            VmmProcessError::CtrlAltDelRequestDenied(_) => 502,
+            _ => 500,
+        }
+    }
+
+    /// Get a client-safe message describing this [VmmProcessError], which, unlike its [Display](std::fmt::Display)
+    /// implementation, doesn't leak any details such as host paths or internal error messages.
+    pub fn public_message(&self) -> &'static str {
+        match self.http_status() {
+            409 => "The VM is in a state that doesn't permit this operation",
+            501 => "The VM's management API is disabled",
+            400 => "The request to the VM's management API was invalid",
+            503 => "The VM's management API is unavailable",
+            502 => "The VM rejected the request",
+            _ => "An internal error occurred while managing the VM",
+        }
+    }
+}
+
 impl std::fmt::Display for VmmProcessError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -142,6 +250,9 @@ impl std::fmt::Display for VmmProcessError {
             VmmProcessError::ResourceSystemError(err) => {
                 write!(f, "An error occurred within the resource system: {err}")
             }
+            VmmProcessError::ProcfsStatusError(err) => {
+                write!(f, "Reading the procfs status of the process failed: {err}")
+            }
         }
     }
 }
@@ -158,12 +269,79 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
             process_handle: None,
             state: VmmProcessState::AwaitingPrepare,
             hyper_client: OnceCell::new(),
+            preconstructed_hyper_client: None,
+            skip_api_socket_owner_upgrade: false,
+            sigkill_target: VmmProcessSigkillTarget::default(),
+            diagnostic_capture_config: None,
+            diagnostic_stdout: None,
+            diagnostic_stderr: None,
+            prepare_duration: None,
+            invoke_duration: None,
+        }
+    }
+
+    /// Supply a preconstructed hyper [Client] that will be used for API requests instead of the one lazily built
+    /// by default. This allows sharing a tuned [Client] across multiple [VmmProcess]es or supplying one with custom
+    /// middleware. The ownership of the API socket will still be upgraded before the first API request is sent.
+    pub fn with_api_client(mut self, client: Client<UnixConnector<R::SocketBackend>, Full<Bytes>>) -> Self {
+        self.preconstructed_hyper_client = Some(client);
+        self
+    }
+
+    /// Skip upgrading the ownership of the API socket before the first API request is sent, which is useful when
+    /// the socket is known to already be accessible to the control process. The upgrade is always skipped when the
+    /// [VmmOwnershipModel](crate::vmm::ownership::VmmOwnershipModel) doesn't require it (for example, when it is
+    /// shared), so this only needs to be used with upgrading ownership models in order to avoid an unnecessary chown
+    /// process that may fail with a [ChangeOwnerError] due to lacking permissions.
+    pub fn skip_api_socket_owner_upgrade(mut self) -> Self {
+        self.skip_api_socket_owner_upgrade = true;
+        self
+    }
+
+    /// Set the [VmmProcessSigkillTarget] that determines which processes are killed by [VmmProcess::send_sigkill].
+    /// By default, only the process itself is killed.
+    pub fn sigkill_target(mut self, sigkill_target: VmmProcessSigkillTarget) -> Self {
+        self.sigkill_target = sigkill_target;
+        self
+    }
+
+    /// Capture the output of the [VmmProcess] once it is invoked according to the given [DiagnosticCaptureConfig], so
+    /// that it can be retrieved via [VmmProcess::get_diagnostic_output] after a crash. The captured pipes are read in
+    /// a background task spawned onto the [Runtime] into bounded buffers, and can thus no longer be taken out via
+    /// [VmmProcess::take_pipes]. Capturing is only possible for attached processes whose pipes weren't dropped by the
+    /// [VmmExecutor], and is silently skipped otherwise.
+    pub fn set_diagnostic_capture(&mut self, diagnostic_capture_config: DiagnosticCaptureConfig) {
+        self.diagnostic_capture_config = Some(diagnostic_capture_config);
+    }
+
+    /// Get the [DiagnosticOutput] captured so far according to the [DiagnosticCaptureConfig], or [None] if no capture
+    /// is being performed. Allowed in any [VmmProcessState].
+    pub fn get_diagnostic_output(&self) -> Option<DiagnosticOutput> {
+        fn read_buffer(buffer: &Option<DiagnosticBuffer>) -> Vec<u8> {
+            buffer
+                .as_ref()
+                .map(|buffer| {
+                    buffer
+                        .lock()
+                        .expect("Diagnostic buffer mutex was poisoned")
+                        .iter()
+                        .copied()
+                        .collect()
+                })
+                .unwrap_or_default()
         }
+
+        self.diagnostic_stderr.as_ref()?;
+        Some(DiagnosticOutput {
+            stdout: read_buffer(&self.diagnostic_stdout),
+            stderr: read_buffer(&self.diagnostic_stderr),
+        })
     }
 
     /// Prepare the [VmmProcess] environment. Allowed in [VmmProcessState::AwaitingPrepare], will result in [VmmProcessState::AwaitingStart].
     pub async fn prepare(&mut self) -> Result<(), VmmProcessError> {
         self.ensure_state(VmmProcessState::AwaitingPrepare)?;
+        let prepare_start = Instant::now();
         self.executor
             .prepare(self.executor_context())
             .await
@@ -173,6 +351,7 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
             .await
             .map_err(VmmProcessError::ResourceSystemError)?;
         self.state = VmmProcessState::AwaitingStart;
+        self.prepare_duration = Some(prepare_start.elapsed());
         Ok(())
     }
 
@@ -180,17 +359,39 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
     /// will result in [VmmProcessState::Started].
     pub async fn invoke(&mut self, config_path: Option<PathBuf>) -> Result<(), VmmProcessError> {
         self.ensure_state(VmmProcessState::AwaitingStart)?;
-        self.process_handle = Some(
-            self.executor
-                .invoke(self.executor_context(), config_path)
-                .await
-                .map_err(VmmProcessError::ExecutorError)?,
-        );
+        let invoke_start = Instant::now();
+        let mut process_handle = self
+            .executor
+            .invoke(self.executor_context(), config_path)
+            .await
+            .map_err(VmmProcessError::ExecutorError)?;
+
+        if let Some(diagnostic_capture_config) = self.diagnostic_capture_config {
+            let runtime = &self.resource_system.runtime;
+
+            if let Ok(stderr) = process_handle.take_stderr() {
+                self.diagnostic_stderr = Some(spawn_diagnostic_capture(
+                    runtime,
+                    stderr,
+                    diagnostic_capture_config.buffer_size,
+                ));
+
+                if diagnostic_capture_config.capture_stdout {
+                    self.diagnostic_stdout = process_handle
+                        .take_stdout()
+                        .ok()
+                        .map(|stdout| spawn_diagnostic_capture(runtime, stdout, diagnostic_capture_config.buffer_size));
+                }
+            }
+        }
+
+        self.process_handle = Some(process_handle);
         self.resource_system
             .synchronize()
             .await
             .map_err(VmmProcessError::ResourceSystemError)?;
         self.state = VmmProcessState::Started;
+        self.invoke_duration = Some(invoke_start.elapsed());
         Ok(())
     }
 
@@ -204,25 +405,7 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
         self.ensure_state(VmmProcessState::Started)?;
         let route = uri.as_ref();
         let socket_path = self.get_socket_path().ok_or(VmmProcessError::ApiSocketDisabled)?;
-
-        let hyper_client = self
-            .hyper_client
-            .get_or_try_init(async {
-                upgrade_owner(
-                    &socket_path,
-                    self.resource_system.ownership_model,
-                    &self.resource_system.process_spawner,
-                    &self.resource_system.runtime,
-                )
-                .await
-                .map_err(VmmProcessError::ChangeOwnerError)?;
-
-                Ok(
-                    Client::builder(RuntimeHyperExecutor(self.resource_system.runtime.clone()))
-                        .build(UnixConnector::new()),
-                )
-            })
-            .await?;
+        let hyper_client = self.get_api_client().await?;
 
         *request.uri_mut() = Uri::unix(socket_path, route).map_err(|error| VmmProcessError::InvalidUri {
             uri: route.to_owned(),
@@ -235,6 +418,36 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
             .map_err(|err| VmmProcessError::RequestError(Box::new(err)))
     }
 
+    /// Get the hyper client used for sending requests to the Firecracker API server, lazily initializing it (and
+    /// upgrading the ownership of the API socket) the first time. Allowed in [VmmProcessState::Started].
+    pub(crate) async fn get_api_client(
+        &mut self,
+    ) -> Result<&Client<UnixConnector<R::SocketBackend>, Full<Bytes>>, VmmProcessError> {
+        self.ensure_state(VmmProcessState::Started)?;
+        let socket_path = self.get_socket_path().ok_or(VmmProcessError::ApiSocketDisabled)?;
+
+        self.hyper_client
+            .get_or_try_init(async {
+                if !self.skip_api_socket_owner_upgrade {
+                    upgrade_owner(
+                        &socket_path,
+                        self.resource_system.ownership_model,
+                        &self.resource_system.process_spawner,
+                        &self.resource_system.runtime,
+                    )
+                    .await
+                    .map_err(VmmProcessError::ChangeOwnerError)?;
+                }
+
+                Ok(match self.preconstructed_hyper_client {
+                    Some(ref client) => client.clone(),
+                    None => Client::builder(RuntimeHyperExecutor(self.resource_system.runtime.clone()))
+                        .build(UnixConnector::new()),
+                })
+            })
+            .await
+    }
+
     /// Take out the stdout, stdin, stderr pipes of the underlying process. This can be only done once,
     /// if some code takes out the pipes, it now owns them for the remaining lifespan of the process.
     /// Allowed in [VmmProcessState::Started].
@@ -272,15 +485,25 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
         Ok(())
     }
 
-    /// Send an immediate forceful shutdown request in the form of a SIGKILL signal to the [VmmProcess].
+    /// Send an immediate forceful shutdown request in the form of a SIGKILL signal to the [VmmProcess], or to its
+    /// process group according to the configured [VmmProcessSigkillTarget].
     /// Allowed in [VmmProcessState::Started] state, will result in [VmmProcessState::Crashed] state.
     pub fn send_sigkill(&mut self) -> Result<(), VmmProcessError> {
         self.ensure_state(VmmProcessState::Started)?;
-        self.process_handle
-            .as_mut()
-            .expect("No child while running")
-            .send_sigkill()
-            .map_err(VmmProcessError::SigkillError)
+        let process_handle = self.process_handle.as_mut().expect("No child while running");
+
+        if self.sigkill_target == VmmProcessSigkillTarget::ProcessGroup {
+            if let Some(pid) = process_handle.get_pid() {
+                let pgid = crate::syscall::getpgid(pid).map_err(VmmProcessError::SigkillError)?;
+                let own_pgid = crate::syscall::getpgid(0).map_err(VmmProcessError::SigkillError)?;
+
+                if pgid != own_pgid {
+                    return crate::syscall::killpg_sigkill(pgid).map_err(VmmProcessError::SigkillError);
+                }
+            }
+        }
+
+        process_handle.send_sigkill().map_err(VmmProcessError::SigkillError)
     }
 
     /// Wait until the [VmmProcess] exits. Careful not to wait forever! Allowed in [VmmProcessState::Started], will result
@@ -295,6 +518,55 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
             .map_err(VmmProcessError::ProcessWaitFailed)
     }
 
+    /// Get the time spent in a successful [VmmProcess::prepare], or [None] if the [VmmProcess] hasn't been prepared.
+    /// Allowed in any [VmmProcessState].
+    pub fn get_prepare_duration(&self) -> Option<Duration> {
+        self.prepare_duration
+    }
+
+    /// Get the time spent in a successful [VmmProcess::invoke], which includes spawning the process and initializing
+    /// the resources it produces, or [None] if the [VmmProcess] hasn't been invoked. Allowed in any [VmmProcessState].
+    pub fn get_invoke_duration(&self) -> Option<Duration> {
+        self.invoke_duration
+    }
+
+    /// Get the PID of the [VmmProcess]'s underlying process, which is either the "firecracker" or the "jailer" process
+    /// depending on the [VmmExecutor]. Returns [None] if the process hasn't been invoked or was already reaped.
+    pub fn get_pid(&self) -> Option<i32> {
+        self.process_handle
+            .as_ref()
+            .and_then(|process_handle| process_handle.get_pid())
+    }
+
+    /// Query the [SeccompMode] that the [VmmProcess]'s underlying process is actually running under, by reading its
+    /// procfs status file via the [Runtime], which allows proving that the configured seccomp filters are in effect.
+    /// Firecracker applies its filters right after startup, so the [SeccompMode] should be queried after the API
+    /// socket has become available. When the "jailer" forks "firecracker" into a new PID namespace, the queried
+    /// process is the "jailer" itself instead of "firecracker". Allowed in [VmmProcessState::Started].
+    pub async fn seccomp_mode(&mut self) -> Result<SeccompMode, VmmProcessError> {
+        self.ensure_state(VmmProcessState::Started)?;
+        let pid = self.get_pid().ok_or_else(|| {
+            VmmProcessError::ProcfsStatusError(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "The process has no PID",
+            ))
+        })?;
+
+        let status = self
+            .resource_system
+            .runtime
+            .fs_read_to_string(&PathBuf::from(format!("/proc/{pid}/status")))
+            .await
+            .map_err(VmmProcessError::ProcfsStatusError)?;
+
+        parse_seccomp_mode(&status).ok_or_else(|| {
+            VmmProcessError::ProcfsStatusError(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "The procfs status doesn't contain a valid \"Seccomp\" field",
+            ))
+        })
+    }
+
     /// Retrieve the current [VmmProcessState] of the [VmmProcess]. Needs mutable access (as well as most other
     /// [VmmProcess] methods relying on it) in order to query the underlying [ProcessHandle] for whether the process
     /// has exited. Allowed in any [VmmProcessState].
@@ -326,6 +598,42 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
             .map_err(VmmProcessError::ResourceSystemError)
     }
 
+    /// Cleans up the [VmmProcess]'s environment in a best-effort manner: unlike [VmmProcess::cleanup], this doesn't
+    /// stop at the first error, but also disposes the resources that a failed [VmmExecutor] cleanup didn't get to, and
+    /// returns all encountered errors, which means that a completely successful cleanup returns an empty [Vec]. Allowed
+    /// in [VmmProcessState::Exited] or [VmmProcessState::Crashed].
+    pub async fn cleanup_best_effort(&mut self) -> Vec<VmmProcessError> {
+        if let Err(err) = self.ensure_exited_or_crashed() {
+            return vec![err];
+        }
+
+        let mut errors = Vec::new();
+
+        if let Err(err) = self.executor.cleanup(self.executor_context()).await {
+            errors.push(VmmProcessError::ExecutorError(err));
+
+            // The disposals scheduled by the executor before it failed must complete first, since scheduling another
+            // disposal of the same resource would race with them
+            if let Err(err) = self.resource_system.synchronize().await {
+                push_resource_system_errors(&mut errors, err);
+            }
+
+            for resource in self.resource_system.get_resources() {
+                if resource.get_state() == ResourceState::Initialized
+                    && !matches!(resource.get_type(), ResourceType::Moved(_))
+                {
+                    let _ = resource.start_disposal();
+                }
+            }
+        }
+
+        if let Err(err) = self.resource_system.synchronize().await {
+            push_resource_system_errors(&mut errors, err);
+        }
+
+        errors
+    }
+
     /// Transforms a given local resource path into an effective resource path using the underlying [VmmExecutor].
     /// This should be used with care and only in cases when the facilities of the [ResourceSystem] prove to be insufficient.
     pub fn resolve_effective_path<P: Into<PathBuf>>(&self, local_path: P) -> PathBuf {
@@ -343,6 +651,22 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
         &mut self.resource_system
     }
 
+    /// Get a shared reference to the [ProcessHandle] of the [VmmProcess]'s underlying process, or [None] if it hasn't
+    /// been invoked yet. This is an escape hatch for integrations that need functionality not surfaced by the
+    /// [VmmProcess], such as custom waiting logic. The [ProcessHandle] API is less stable than that of the
+    /// [VmmProcess] and may change in minor releases.
+    pub fn get_process_handle(&self) -> Option<&ProcessHandle<R>> {
+        self.process_handle.as_ref()
+    }
+
+    /// Get a mutable reference to the [ProcessHandle] of the [VmmProcess]'s underlying process, or [None] if it hasn't
+    /// been invoked yet. The same stability caveat as for [VmmProcess::get_process_handle] applies. Additionally,
+    /// reaping the process or taking its pipes via the [ProcessHandle] bypasses the [VmmProcess]'s state tracking, so
+    /// [VmmProcess::get_state] may not observe an exit that was already observed through the [ProcessHandle].
+    pub fn get_process_handle_mut(&mut self) -> Option<&mut ProcessHandle<R>> {
+        self.process_handle.as_mut()
+    }
+
     #[inline]
     fn ensure_state(&mut self, expected: VmmProcessState) -> Result<(), VmmProcessError> {
         match self.get_state() == expected {
@@ -378,12 +702,120 @@ impl<E: VmmExecutor, S: ProcessSpawner, R: Runtime> VmmProcess<E, S, R> {
     }
 }
 
+fn parse_seccomp_mode(status: &str) -> Option<SeccompMode> {
+    let value = status.lines().find_map(|line| line.strip_prefix("Seccomp:"))?.trim();
+
+    match value {
+        "0" => Some(SeccompMode::Disabled),
+        "1" => Some(SeccompMode::Strict),
+        "2" => Some(SeccompMode::Filter),
+        _ => None,
+    }
+}
+
+fn push_resource_system_errors(errors: &mut Vec<VmmProcessError>, err: ResourceSystemError) {
+    match err {
+        ResourceSystemError::ErrorChain(chain) => {
+            for err in chain {
+                push_resource_system_errors(errors, err);
+            }
+        }
+        err => errors.push(VmmProcessError::ResourceSystemError(err)),
+    }
+}
+
+fn spawn_diagnostic_capture<R: Runtime, P: AsyncRead + Unpin + Send + 'static>(
+    runtime: &R,
+    mut pipe: P,
+    buffer_size: usize,
+) -> DiagnosticBuffer {
+    let buffer = Arc::new(Mutex::new(VecDeque::new()));
+    let task_buffer = buffer.clone();
+
+    runtime.spawn_task(async move {
+        let mut chunk = [0u8; 4096];
+
+        // the pipe must be drained continuously regardless of the buffer size, since the process blocks on a full pipe
+        while let Ok(read) = pipe.read(&mut chunk).await {
+            if read == 0 {
+                break;
+            }
+
+            let mut buffer = task_buffer.lock().expect("Diagnostic buffer mutex was poisoned");
+            buffer.extend(&chunk[..read]);
+            let excess = buffer.len().saturating_sub(buffer_size);
+            buffer.drain(..excess);
+        }
+    });
+
+    buffer
+}
+
+/// The body of a hyper [Response] received as a sequence of [Bytes] chunks in the order they arrived, which can be
+/// consumed via its [std::io::Read] implementation without being copied into a single contiguous buffer first. This
+/// keeps the peak memory usage of deserializing large response bodies, such as a multi-megabyte MMDS, low.
+#[derive(Debug, Clone, Default)]
+pub struct ResponseBodyChunks(VecDeque<Bytes>);
+
+impl std::io::Read for ResponseBodyChunks {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        while let Some(chunk) = self.0.front_mut() {
+            if chunk.is_empty() {
+                self.0.pop_front();
+                continue;
+            }
+
+            let length = chunk.len().min(buf.len());
+            buf[..length].copy_from_slice(&chunk[..length]);
+            *chunk = chunk.slice(length..);
+            return Ok(length);
+        }
+
+        Ok(0)
+    }
+}
+
+/// An error that can be emitted by [HyperResponseExt::read_body_to_buffer_limited].
+#[derive(Debug)]
+pub enum LimitedResponseBodyError {
+    /// Receiving the response body failed due to a [hyper::Error].
+    ReceiveError(hyper::Error),
+    /// The response body exceeded the contained maximum amount of bytes.
+    LimitExceeded(usize),
+}
+
+impl std::error::Error for LimitedResponseBodyError {}
+
+impl std::fmt::Display for LimitedResponseBodyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitedResponseBodyError::ReceiveError(err) => write!(f, "Receiving the response body failed: {err}"),
+            LimitedResponseBodyError::LimitExceeded(max_bytes) => {
+                write!(f, "The response body exceeded the limit of {max_bytes} bytes")
+            }
+        }
+    }
+}
+
 /// An extension to a hyper [Response] of [Incoming] (returned by the Firecracker API socket) that allows
-/// easy streaming of the response body into a [String] or [BytesMut].
+/// easy streaming of the response body into a [String], [BytesMut] or [ResponseBodyChunks].
 pub trait HyperResponseExt: Send {
-    /// Stream the entire response body into a [BytesMut] byte buffer.
+    /// Stream the entire response body into a [BytesMut] byte buffer. The buffer grows for as long as the body
+    /// continues, so this should only be used for responses from trusted servers, such as the Firecracker API server.
     fn read_body_to_buffer(&mut self) -> impl Future<Output = Result<BytesMut, hyper::Error>> + Send;
 
+    /// Stream the entire response body into a [BytesMut] byte buffer, failing with
+    /// [LimitedResponseBodyError::LimitExceeded] as soon as the body turns out to be larger than the given maximum
+    /// amount of bytes. This should be used for responses from untrusted servers, such as applications inside the
+    /// guest, which could otherwise exhaust the host's memory.
+    fn read_body_to_buffer_limited(
+        &mut self,
+        max_bytes: usize,
+    ) -> impl Future<Output = Result<BytesMut, LimitedResponseBodyError>> + Send;
+
+    /// Stream the entire response body into [ResponseBodyChunks], retaining the received chunks as they are.
+    fn read_body_to_chunks(&mut self) -> impl Future<Output = Result<ResponseBodyChunks, hyper::Error>> + Send;
+
     /// Stream the entire response body into an owned [String].
     fn read_body_to_string(&mut self) -> impl Future<Output = Result<String, hyper::Error>> + Send {
         async {
@@ -405,4 +837,39 @@ impl HyperResponseExt for Response<Incoming> {
 
         Ok(buffer)
     }
+
+    async fn read_body_to_buffer_limited(&mut self, max_bytes: usize) -> Result<BytesMut, LimitedResponseBodyError> {
+        let size_hint = self.size_hint().lower();
+        if size_hint > max_bytes as u64 {
+            return Err(LimitedResponseBodyError::LimitExceeded(max_bytes));
+        }
+
+        let mut buffer = BytesMut::with_capacity(size_hint as usize);
+
+        while let Some(frame) = self.frame().await {
+            let frame = frame.map_err(LimitedResponseBodyError::ReceiveError)?;
+
+            if let Ok(bytes) = frame.into_data() {
+                if buffer.len() + bytes.len() > max_bytes {
+                    return Err(LimitedResponseBodyError::LimitExceeded(max_bytes));
+                }
+
+                buffer.extend(bytes);
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    async fn read_body_to_chunks(&mut self) -> Result<ResponseBodyChunks, hyper::Error> {
+        let mut chunks = VecDeque::new();
+
+        while let Some(frame) = self.frame().await {
+            if let Ok(bytes) = frame?.into_data() {
+                chunks.push_back(bytes);
+            }
+        }
+
+        Ok(ResponseBodyChunks(chunks))
+    }
 }
@@ -1,20 +1,22 @@
 use std::{
     future::poll_fn,
-    path::PathBuf,
+    os::{fd::AsRawFd, unix::fs::MetadataExt},
+    path::{Path, PathBuf},
     sync::{
         Arc, OnceLock,
-        atomic::{AtomicBool, Ordering},
+        atomic::{AtomicBool, AtomicU64, Ordering},
     },
     task::Poll,
 };
 
 use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender};
-use futures_util::StreamExt;
+use futures_util::{AsyncReadExt, StreamExt};
 
 use super::{CreatedResourceType, MovedResourceType, ResourceType, system::ResourceSystemError};
 use crate::{
     process_spawner::ProcessSpawner,
     runtime::{Runtime, RuntimeTask},
+    syscall::{DM_DEV_CREATE, DM_DEV_REMOVE, DM_DEV_SUSPEND, DM_TABLE_LOAD, DeviceMapperIoctl, device_mapper_ioctl},
     vmm::ownership::{VmmOwnershipModel, downgrade_owner, upgrade_owner},
 };
 
@@ -23,6 +25,8 @@ pub struct ResourceInfo {
     pub request_tx: UnboundedSender<ResourceRequest>,
     pub initial_path: PathBuf,
     pub r#type: ResourceType,
+    pub follow_symlinks: bool,
+    pub direct_io_copies: bool,
     pub init_info: OnceLock<Arc<ResourceInitInfo>>,
     pub disposed: AtomicBool,
 }
@@ -33,6 +37,12 @@ pub struct ResourceInitInfo {
     pub virtual_path: Option<PathBuf>,
 }
 
+#[derive(Debug, Default)]
+pub struct ResourceSystemCounters {
+    pub bytes_copied: AtomicU64,
+    pub files_created: AtomicU64,
+}
+
 pub struct OwnedResource<R: Runtime> {
     pub init_task: Option<R::Task<Result<ResourceInitInfo, ResourceSystemError>>>,
     pub dispose_task: Option<R::Task<Result<(), ResourceSystemError>>>,
@@ -47,22 +57,39 @@ pub enum ResourceRequest {
 
 pub enum ResourceSystemRequest<R: Runtime> {
     AddResource(OwnedResource<R>),
-    Synchronize,
+    Synchronize(u64, Option<Vec<Arc<ResourceInfo>>>),
     Shutdown,
 }
 
 pub enum ResourceSystemResponse {
-    SynchronizationComplete(Result<(), ResourceSystemError>),
+    SynchronizationComplete(u64, Result<(), ResourceSystemError>),
+}
+
+pub struct ResourceSystemTaskContext<S: ProcessSpawner, R: Runtime> {
+    pub request_rx: UnboundedReceiver<ResourceSystemRequest<R>>,
+    pub response_tx: UnboundedSender<ResourceSystemResponse>,
+    pub exit_tx: futures_channel::oneshot::Sender<()>,
+    pub process_spawner: S,
+    pub runtime: R,
+    pub ownership_model: VmmOwnershipModel,
+    pub counters: Arc<ResourceSystemCounters>,
 }
 
 pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
-    mut request_rx: UnboundedReceiver<ResourceSystemRequest<R>>,
-    response_tx: UnboundedSender<ResourceSystemResponse>,
+    context: ResourceSystemTaskContext<S, R>,
     mut owned_resources: Vec<OwnedResource<R>>,
-    process_spawner: S,
-    runtime: R,
-    ownership_model: VmmOwnershipModel,
 ) {
+    // the exit sender is only held until this task ends, which is what its dropping signals
+    let ResourceSystemTaskContext {
+        mut request_rx,
+        response_tx,
+        exit_tx: _exit_tx,
+        process_spawner,
+        runtime,
+        ownership_model,
+        counters,
+    } = context;
+
     enum Incoming<R: Runtime> {
         SystemRequest(ResourceSystemRequest<R>),
         ResourceRequest(usize, ResourceRequest),
@@ -70,8 +97,13 @@ pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
         DisposeTaskCompletion(usize, Result<(), ResourceSystemError>),
     }
 
-    let mut synchronization_in_progress = false;
+    // only the latest synchronization is tracked, since a newer one can only be requested once the future of the
+    // previous one has been completed or dropped
+    let mut synchronization_id: Option<u64> = None;
+    let mut synchronization_subset: Option<Vec<Arc<ResourceInfo>>> = None;
     let mut synchronization_errors = Vec::new();
+    // errors of resources outside of a synchronized subset, which are reported by the next full synchronization
+    let mut deferred_errors = Vec::new();
 
     loop {
         let incoming = poll_fn(|cx| {
@@ -93,8 +125,11 @@ pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
                 }
             }
 
-            if let Poll::Ready(Some(request)) = request_rx.poll_next_unpin(cx) {
-                return Poll::Ready(Incoming::SystemRequest(request));
+            match request_rx.poll_next_unpin(cx) {
+                Poll::Ready(Some(request)) => return Poll::Ready(Incoming::SystemRequest(request)),
+                // the ResourceSystem is gone, so nothing can ever be received again and the task must not be leaked
+                Poll::Ready(None) => return Poll::Ready(Incoming::SystemRequest(ResourceSystemRequest::Shutdown)),
+                Poll::Pending => {}
             }
 
             Poll::Pending
@@ -107,10 +142,26 @@ pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
                     owned_resources.push(owned_resource);
                 }
                 ResourceSystemRequest::Shutdown => {
+                    // finalize all scheduled actions on a best-effort basis, as no one can receive their results
+                    for resource in owned_resources {
+                        if let Some(init_task) = resource.init_task {
+                            init_task.join().await;
+                        }
+
+                        if let Some(dispose_task) = resource.dispose_task {
+                            dispose_task.join().await;
+                        }
+                    }
+
                     return;
                 }
-                ResourceSystemRequest::Synchronize => {
-                    synchronization_in_progress = true;
+                ResourceSystemRequest::Synchronize(id, subset) => {
+                    if subset.is_none() {
+                        synchronization_errors.append(&mut deferred_errors);
+                    }
+
+                    synchronization_id = Some(id);
+                    synchronization_subset = subset;
                 }
             },
             Incoming::ResourceRequest(resource_index, request) => {
@@ -126,6 +177,7 @@ pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
                             runtime.clone(),
                             process_spawner.clone(),
                             ownership_model,
+                            counters.clone(),
                         ));
 
                         resource.init_task = Some(init_task);
@@ -133,6 +185,7 @@ pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
                     ResourceRequest::Dispose => {
                         let dispose_task = runtime.spawn_task(resource_system_dispose_task(
                             resource.info.init_info.get().unwrap().clone(),
+                            resource.info.r#type,
                             runtime.clone(),
                             process_spawner.clone(),
                             ownership_model,
@@ -152,8 +205,11 @@ pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
                         let _ = resource.info.init_info.set(Arc::new(init_info));
                     }
                     Err(err) => {
-                        if synchronization_in_progress {
-                            synchronization_errors.push(err);
+                        if synchronization_id.is_some() {
+                            match is_synchronized(&synchronization_subset, &resource.info) {
+                                true => synchronization_errors.push(err),
+                                false => deferred_errors.push(err),
+                            }
                         }
                     }
                 }
@@ -168,23 +224,28 @@ pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
                         resource.info.disposed.store(true, Ordering::Release);
                     }
                     Err(err) => {
-                        if synchronization_in_progress {
-                            synchronization_errors.push(err);
+                        if synchronization_id.is_some() {
+                            match is_synchronized(&synchronization_subset, &resource.info) {
+                                true => synchronization_errors.push(err),
+                                false => deferred_errors.push(err),
+                            }
                         }
                     }
                 }
             }
         };
 
-        if synchronization_in_progress {
+        if let Some(id) = synchronization_id {
             let no_pending_tasks = owned_resources
                 .iter()
                 .filter(|resource| resource.init_task.is_some() || resource.dispose_task.is_some())
+                .filter(|resource| is_synchronized(&synchronization_subset, &resource.info))
                 .next()
                 .is_none();
 
             if no_pending_tasks {
-                synchronization_in_progress = false;
+                synchronization_id = None;
+                synchronization_subset = None;
 
                 let result = match synchronization_errors.len() {
                     0 => Ok(()),
@@ -196,18 +257,69 @@ pub async fn resource_system_main_task<S: ProcessSpawner, R: Runtime>(
                     )),
                 };
 
-                let _ = response_tx.unbounded_send(ResourceSystemResponse::SynchronizationComplete(result));
+                let _ = response_tx.unbounded_send(ResourceSystemResponse::SynchronizationComplete(id, result));
             }
         }
     }
 }
 
+fn is_synchronized(synchronization_subset: &Option<Vec<Arc<ResourceInfo>>>, info: &Arc<ResourceInfo>) -> bool {
+    synchronization_subset
+        .as_ref()
+        .is_none_or(|subset| subset.iter().any(|subset_info| Arc::ptr_eq(subset_info, info)))
+}
+
+async fn copy_moved_resource<R: Runtime>(
+    runtime: &R,
+    source_path: &Path,
+    destination_path: &Path,
+    copy_symlink: bool,
+    direct_io: bool,
+    counters: &ResourceSystemCounters,
+) -> Result<(), ResourceSystemError> {
+    if copy_symlink {
+        // reading and creating a symlink are single syscalls that don't block, so no runtime-specific I/O is needed
+        return std::fs::read_link(source_path)
+            .and_then(|symlink_target| std::os::unix::fs::symlink(symlink_target, destination_path))
+            .map_err(ResourceSystemError::FilesystemError);
+    }
+
+    let copy_result = match direct_io {
+        true => runtime.fs_copy_direct(source_path, destination_path).await,
+        false => runtime.fs_copy(source_path, destination_path).await,
+    };
+
+    if let Err(err) = copy_result {
+        if err.kind() != std::io::ErrorKind::StorageFull {
+            return Err(ResourceSystemError::FilesystemError(err));
+        }
+
+        // A partially copied file would otherwise remain, both wasting the space and failing any fallback operation
+        let _ = runtime.fs_remove_file(destination_path).await;
+        let needed = runtime.fs_file_size(source_path).await;
+        let available = crate::syscall::available_space(destination_path.parent().unwrap_or(destination_path));
+
+        return Err(match (needed, available) {
+            (Ok(needed), Ok(available)) => ResourceSystemError::InsufficientSpace { needed, available },
+            _ => ResourceSystemError::FilesystemError(err),
+        });
+    }
+
+    let bytes_copied = runtime
+        .fs_file_size(destination_path)
+        .await
+        .map_err(ResourceSystemError::FilesystemError)?;
+    counters.bytes_copied.fetch_add(bytes_copied, Ordering::Relaxed);
+    Ok(())
+}
+
 async fn resource_system_init_task<S: ProcessSpawner, R: Runtime>(
     info: Arc<ResourceInfo>,
     init_info: ResourceInitInfo,
     runtime: R,
     process_spawner: S,
     ownership_model: VmmOwnershipModel,
+    counters: Arc<ResourceSystemCounters>,
 ) -> Result<ResourceInitInfo, ResourceSystemError> {
     match info.r#type {
         ResourceType::Moved(moved_resource_type) => {
@@ -219,10 +331,21 @@ async fn resource_system_init_task<S: ProcessSpawner, R: Runtime>(
                 .await
                 .map_err(ResourceSystemError::ChangeOwnerError)?;
 
-            if !runtime
-                .fs_exists(&info.initial_path)
-                .await
-                .map_err(ResourceSystemError::FilesystemError)?
+            // Symlinks are resolved upfront so that all move methods consistently operate either on the symlink's
+            // target or on the symlink itself, instead of that depending on the underlying filesystem operation
+            let is_symlink =
+                crate::syscall::is_symlink(&info.initial_path).map_err(ResourceSystemError::FilesystemError)?;
+            let copy_symlink = is_symlink && !info.follow_symlinks;
+            let source_path = match is_symlink && info.follow_symlinks {
+                true => std::fs::canonicalize(&info.initial_path).map_err(ResourceSystemError::FilesystemError)?,
+                false => info.initial_path.clone(),
+            };
+
+            if !copy_symlink
+                && !runtime
+                    .fs_exists(&source_path)
+                    .await
+                    .map_err(ResourceSystemError::FilesystemError)?
             {
                 return Err(ResourceSystemError::InitialPathMissing);
             }
@@ -236,47 +359,74 @@ async fn resource_system_init_task<S: ProcessSpawner, R: Runtime>(
 
             match moved_resource_type {
                 MovedResourceType::Copied => {
-                    runtime
-                        .fs_copy(&info.initial_path, &init_info.effective_path)
-                        .await
-                        .map_err(ResourceSystemError::FilesystemError)?;
+                    copy_moved_resource(
+                        &runtime,
+                        &source_path,
+                        &init_info.effective_path,
+                        copy_symlink,
+                        info.direct_io_copies,
+                        &counters,
+                    )
+                    .await?;
                 }
                 MovedResourceType::HardLinked => {
                     runtime
-                        .fs_hard_link(&info.initial_path, &init_info.effective_path)
+                        .fs_hard_link(&source_path, &init_info.effective_path)
                         .await
                         .map_err(ResourceSystemError::FilesystemError)?;
                 }
                 MovedResourceType::CopiedOrHardLinked => {
-                    if runtime
-                        .fs_copy(&info.initial_path, &init_info.effective_path)
-                        .await
-                        .is_err()
+                    if copy_moved_resource(
+                        &runtime,
+                        &source_path,
+                        &init_info.effective_path,
+                        copy_symlink,
+                        info.direct_io_copies,
+                        &counters,
+                    )
+                    .await
+                    .is_err()
                     {
                         runtime
-                            .fs_hard_link(&info.initial_path, &init_info.effective_path)
+                            .fs_hard_link(&source_path, &init_info.effective_path)
                             .await
                             .map_err(ResourceSystemError::FilesystemError)?;
                     }
                 }
                 MovedResourceType::HardLinkedOrCopied => {
                     if runtime
-                        .fs_hard_link(&info.initial_path, &init_info.effective_path)
+                        .fs_hard_link(&source_path, &init_info.effective_path)
                         .await
                         .is_err()
                     {
-                        runtime
-                            .fs_copy(&info.initial_path, &init_info.effective_path)
-                            .await
-                            .map_err(ResourceSystemError::FilesystemError)?;
+                        copy_moved_resource(
+                            &runtime,
+                            &source_path,
+                            &init_info.effective_path,
+                            copy_symlink,
+                            info.direct_io_copies,
+                            &counters,
+                        )
+                        .await?;
                     }
                 }
                 MovedResourceType::Renamed => {
                     runtime
-                        .fs_rename(&info.initial_path, &init_info.effective_path)
+                        .fs_rename(&source_path, &init_info.effective_path)
                         .await
                         .map_err(ResourceSystemError::FilesystemError)?;
                 }
+                MovedResourceType::Snapshotted => {
+                    if copy_symlink {
+                        return Err(ResourceSystemError::FilesystemError(std::io::Error::new(
+                            std::io::ErrorKind::InvalidInput,
+                            "A symlink that isn't followed can't be the origin of a snapshotted resource",
+                        )));
+                    }
+
+                    create_snapshot_device(&source_path, &init_info.effective_path)
+                        .map_err(ResourceSystemError::FilesystemError)?;
+                }
             }
         }
         ResourceType::Created(created_resource_type) => {
@@ -294,13 +444,25 @@ async fn resource_system_init_task<S: ProcessSpawner, R: Runtime>(
                         .await
                         .map_err(ResourceSystemError::FilesystemError)?;
                 }
-                CreatedResourceType::Fifo => {
+                CreatedResourceType::FileWithMode(mode) => {
+                    runtime
+                        .fs_create_file_with_mode(&init_info.effective_path, mode)
+                        .await
+                        .map_err(ResourceSystemError::FilesystemError)?;
+                }
+                CreatedResourceType::Fifo | CreatedResourceType::DrainedFifo => {
                     crate::syscall::mkfifo(&init_info.effective_path).map_err(ResourceSystemError::FilesystemError)?;
                 }
             }
 
+            counters.files_created.fetch_add(1, Ordering::Relaxed);
+
             downgrade_owner(&init_info.effective_path, ownership_model)
                 .map_err(ResourceSystemError::ChangeOwnerError)?;
+
+            if created_resource_type == CreatedResourceType::DrainedFifo {
+                spawn_fifo_drain_task(&runtime, init_info.effective_path.clone());
+            }
         }
         ResourceType::Produced => {
             if let Some(parent_path) = init_info.effective_path.parent() {
@@ -319,6 +481,7 @@ async fn resource_system_init_task<S: ProcessSpawner, R: Runtime>(
 
 async fn resource_system_dispose_task<R: Runtime, S: ProcessSpawner>(
     init_info: Arc<ResourceInitInfo>,
+    r#type: ResourceType,
     runtime: R,
     process_spawner: S,
     ownership_model: VmmOwnershipModel,
@@ -327,8 +490,160 @@ async fn resource_system_dispose_task<R: Runtime, S: ProcessSpawner>(
         .await
         .map_err(ResourceSystemError::ChangeOwnerError)?;
 
+    if r#type == ResourceType::Created(CreatedResourceType::DrainedFifo) {
+        // Briefly opening the FIFO for writing unblocks a drain task that is still waiting for a writer, while no
+        // file being opened means that there are no readers left and thus no drain task to unblock
+        crate::syscall::open_fifo_writer_nonblocking(&init_info.effective_path)
+            .map_err(ResourceSystemError::FilesystemError)?;
+    }
+
+    if r#type == ResourceType::Moved(MovedResourceType::Snapshotted) {
+        remove_snapshot_device(&init_info.effective_path).map_err(ResourceSystemError::FilesystemError)?;
+    }
+
     runtime
         .fs_remove_file(&init_info.effective_path)
         .await
         .map_err(ResourceSystemError::FilesystemError)
 }
+
+const FIFO_DRAIN_BUFFER_SIZE: usize = 4096;
+
+// Opening a FIFO for reading blocks until a writer opens it, which is either the VMM or, if the VMM never opened the
+// FIFO, the dispose task. The drain then ends once all writers have closed the FIFO.
+fn spawn_fifo_drain_task<R: Runtime>(runtime: &R, path: PathBuf) {
+    let runtime_clone = runtime.clone();
+    runtime.spawn_task(async move {
+        let Ok(mut file) = runtime_clone.fs_open_file_for_read(&path).await else {
+            return;
+        };
+
+        let mut buf = [0; FIFO_DRAIN_BUFFER_SIZE];
+        while let Ok(1..) = file.read(&mut buf).await {}
+    });
+}
+
+const SECTOR_SIZE: u64 = 512;
+// The chunk size of a snapshot matches the granularity of guest pages
+const SNAPSHOT_CHUNK_SIZE: u64 = 4096;
+
+const LOOP_CONTROL_PATH: &str = "/dev/loop-control";
+const DEVICE_MAPPER_CONTROL_PATH: &str = "/dev/mapper/control";
+
+// A loop device that is detached once dropped, or, if it is still open elsewhere (such as by a device-mapper device),
+// once it is last closed
+struct LoopDevice {
+    file: std::fs::File,
+    path: PathBuf,
+}
+
+impl LoopDevice {
+    fn attach(backing_file: &std::fs::File) -> Result<Self, std::io::Error> {
+        let control = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(LOOP_CONTROL_PATH)?;
+
+        loop {
+            let number = crate::syscall::loop_get_free(control.as_raw_fd())?;
+            let path = PathBuf::from(format!("/dev/loop{number}"));
+            let file = std::fs::OpenOptions::new().read(true).write(true).open(&path)?;
+
+            match crate::syscall::loop_set_fd(file.as_raw_fd(), backing_file.as_raw_fd()) {
+                // Another process has attached the free loop device in the meantime, so the next one is tried
+                Err(err) if err.kind() == std::io::ErrorKind::ResourceBusy => continue,
+                Err(err) => return Err(err),
+                Ok(()) => return Ok(Self { file, path }),
+            }
+        }
+    }
+}
+
+impl Drop for LoopDevice {
+    fn drop(&mut self) {
+        let _ = crate::syscall::loop_clear_fd(self.file.as_raw_fd());
+    }
+}
+
+// The copy-on-write store of a snapshotted resource is placed next to its effective path, so that it is removed
+// alongside the Firecracker environment
+fn get_cow_path(effective_path: &Path) -> PathBuf {
+    let mut cow_file_name = effective_path.file_name().unwrap_or_default().to_owned();
+    cow_file_name.push(".cow");
+    effective_path.with_file_name(cow_file_name)
+}
+
+// Device-mapper device names are global, so the name is derived from the identity of the copy-on-write store
+fn get_snapshot_device_name(cow_path: &Path) -> Result<String, std::io::Error> {
+    let metadata = std::fs::metadata(cow_path)?;
+    Ok(format!("fctools-{}-{}", metadata.dev(), metadata.ino()))
+}
+
+fn create_snapshot_device(source_path: &Path, effective_path: &Path) -> Result<(), std::io::Error> {
+    let origin = std::fs::File::open(source_path)?;
+    let origin_size = origin.metadata()?.len();
+    let cow_path = get_cow_path(effective_path);
+    let cow = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&cow_path)?;
+    // The store is sparse, so sizing it to hold every chunk of the origin only reserves the space
+    cow.set_len(origin_size.next_multiple_of(SNAPSHOT_CHUNK_SIZE))?;
+
+    // The loop devices are only detached once the device-mapper device that holds them open is removed
+    let origin_loop_device = LoopDevice::attach(&origin)?;
+    let cow_loop_device = LoopDevice::attach(&cow)?;
+    let name = get_snapshot_device_name(&cow_path)?;
+    let control = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(DEVICE_MAPPER_CONTROL_PATH)?;
+
+    // A non-persistent snapshot keeps its metadata in memory, since the store is never reused after the device is gone
+    let parameters = format!(
+        "{} {} N {}",
+        origin_loop_device.path.display(),
+        cow_loop_device.path.display(),
+        SNAPSHOT_CHUNK_SIZE / SECTOR_SIZE
+    );
+    let mut table = DeviceMapperIoctl::new(&name)?.with_target(origin_size / SECTOR_SIZE, "snapshot", &parameters)?;
+    let mut status = DeviceMapperIoctl::new(&name)?;
+    device_mapper_ioctl(control.as_raw_fd(), DM_DEV_CREATE, &mut DeviceMapperIoctl::new(&name)?)?;
+
+    // Resuming the device activates the loaded table and returns its status, which includes the device number
+    let result = device_mapper_ioctl(control.as_raw_fd(), DM_TABLE_LOAD, &mut table)
+        .and_then(|_| device_mapper_ioctl(control.as_raw_fd(), DM_DEV_SUSPEND, &mut status))
+        .and_then(|_| crate::syscall::mknod_block_device(effective_path, status.dev));
+
+    if result.is_err() {
+        let _ = device_mapper_ioctl(
+            control.as_raw_fd(),
+            DM_DEV_REMOVE,
+            &mut DeviceMapperIoctl::new(&name)?.with_deferred_remove(),
+        );
+    }
+
+    result
+}
+
+pub fn remove_snapshot_device(effective_path: &Path) -> Result<(), std::io::Error> {
+    let cow_path = get_cow_path(effective_path);
+    // A missing store means that the snapshot has already been removed
+    let name = match get_snapshot_device_name(&cow_path) {
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        result => result?,
+    };
+
+    let control = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(DEVICE_MAPPER_CONTROL_PATH)?;
+    // The removal is deferred in case the device is still briefly held open, such as by udev probing it
+    device_mapper_ioctl(
+        control.as_raw_fd(),
+        DM_DEV_REMOVE,
+        &mut DeviceMapperIoctl::new(&name)?.with_deferred_remove(),
+    )?;
+    std::fs::remove_file(cow_path)
+}
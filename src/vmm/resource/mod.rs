@@ -32,8 +32,16 @@ pub enum ResourceType {
 pub enum CreatedResourceType {
     /// A plain-text file.
     File,
+    /// A plain-text file that is atomically created with the given permission mode (subject to the umask).
+    FileWithMode(u32),
     /// A FIFO named pipe.
     Fifo,
+    /// A FIFO named pipe whose read end is kept open by the resource system, which drains and discards everything
+    /// written to it until all writers close it. Firecracker blocks on writes to a FIFO that nothing reads from, so
+    /// this should be used instead of [CreatedResourceType::Fifo] for logs or metrics that the fctools-utilizing
+    /// application won't consume, for example when no metrics task is spawned. Consequently, the contents of such a
+    /// FIFO can't be reliably read by anything else, such as a metrics task.
+    DrainedFifo,
 }
 
 /// A [MovedResourceType] determines what filesystem operation should be used in order to move the pre-existing
@@ -51,6 +59,17 @@ pub enum MovedResourceType {
     /// Move/rename the source to the destination. This doesn't preserve the source at all, meaning it will be removed
     /// alongside the Firecracker environment after usage.
     Renamed,
+    /// Create the destination as the block device node of a device-mapper snapshot, whose read-only origin is the
+    /// source and whose copy-on-write store is a sparse file private to the Firecracker environment and removed
+    /// alongside it. This avoids copying a shared base image, such as a rootfs, for every VM, since the source is
+    /// never modified and the store only takes up disk space for the chunks written to by the VM.
+    ///
+    /// The loop and device-mapper devices are set up via the syscall backend and thus require the current process to
+    /// have the CAP_SYS_ADMIN and CAP_MKNOD capabilities, the kernel to support the "snapshot" device-mapper target and
+    /// the destination to not reside on a filesystem mounted with "nodev". The destination being a block device, this
+    /// only works for resources that Firecracker can use as such, like drives, and the source can't be a symlink that
+    /// isn't followed.
+    Snapshotted,
 }
 
 /// The underlying state of a [Resource].
@@ -144,6 +163,25 @@ impl Resource {
             .map(|data| data.virtual_path.as_deref().unwrap_or_else(|| self.get_initial_path()))
     }
 
+    /// Get the path that Firecracker itself will use in order to access this [Resource] as a borrowed [Path], which
+    /// is the path that is put into its configuration, or [None] if the [Resource] has not yet been initialized. This
+    /// is the virtual path for moved resources and the initial path for all other resources, since the latter are
+    /// always initialized with their initial path being resolved relative to Firecracker's environment.
+    ///
+    /// For example, a moved rootfs at "/opt/rootfs.ext4" is configured with:
+    /// - An unrestricted executor: the effective and virtual paths, and this path, are all "/opt/rootfs.ext4".
+    /// - A jailed executor with a flat virtual path resolver: the effective path is
+    ///   "/srv/jailer/firecracker/1/root/rootfs.ext4", while the virtual path, and this path, are "/rootfs.ext4".
+    ///
+    /// And, a created log file at "/log.txt" has an effective path of "/srv/jailer/firecracker/1/root/log.txt"
+    /// with a jailed executor, while this path is "/log.txt".
+    pub fn get_jail_relative_path(&self) -> Option<&Path> {
+        match self.0.r#type {
+            ResourceType::Moved(_) => self.get_virtual_path(),
+            _ => self.0.init_info.get().map(|_| self.get_initial_path()),
+        }
+    }
+
     /// Schedule this [Resource] to be initialized by its system to the given effective and virtual paths.
     /// If the virtual path is [None], it is assumed to be the same as the effective path. This operation
     /// doesn't actually wait for the initialization to occur.
@@ -177,6 +215,17 @@ impl Resource {
         Ok(())
     }
 
+    /// Synchronously remove the device-mapper snapshot of this [Resource] if it is an initialized moved resource with
+    /// the [MovedResourceType::Snapshotted] type, which must be done before removing the directory containing it.
+    pub(crate) fn remove_snapshot_device(&self) -> Result<(), std::io::Error> {
+        match (self.0.r#type, self.get_state(), self.get_effective_path()) {
+            (ResourceType::Moved(MovedResourceType::Snapshotted), ResourceState::Initialized, Some(effective_path)) => {
+                internal::remove_snapshot_device(effective_path)
+            }
+            _ => Ok(()),
+        }
+    }
+
     #[inline(always)]
     fn assert_state(&self, expected: ResourceState) -> Result<(), ResourceSystemError> {
         let actual = self.get_state();
@@ -198,7 +247,7 @@ impl serde::Serialize for Resource {
     {
         match self.0.r#type {
             ResourceType::Moved(_) => self
-                .get_virtual_path()
+                .get_jail_relative_path()
                 .expect("called serialize on uninitialized resource")
                 .serialize(serializer),
             _ => self.get_initial_path().serialize(serializer),
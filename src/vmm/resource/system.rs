@@ -2,19 +2,25 @@
 use std::marker::PhantomData;
 use std::{
     path::PathBuf,
-    sync::{Arc, OnceLock, atomic::AtomicBool},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 
 use futures_channel::mpsc;
 use futures_util::StreamExt;
 
 use super::{
-    Resource, ResourceState, ResourceType,
-    internal::{OwnedResource, ResourceInfo, ResourceSystemRequest, ResourceSystemResponse, resource_system_main_task},
+    MovedResourceType, Resource, ResourceState, ResourceType,
+    internal::{
+        OwnedResource, ResourceInfo, ResourceSystemCounters, ResourceSystemRequest, ResourceSystemResponse,
+        ResourceSystemTaskContext, resource_system_main_task,
+    },
 };
 use crate::{
     process_spawner::ProcessSpawner,
-    runtime::Runtime,
+    runtime::{Runtime, cancellation::CancellationToken},
     vmm::ownership::{ChangeOwnerError, VmmOwnershipModel},
 };
 
@@ -23,9 +29,13 @@ use crate::{
 /// auxiliary tasks onto the same [Runtime] that perform asynchronous resource actions such as initialization and disposal.
 ///
 /// The [ResourceSystem] allows the creation of new [Resource]s and global synchronization with the task. After being dropped,
-/// the [ResourceSystem] will transmit a shutdown message that will end the task. The [ResourceSystem] requires not only a
-/// [Runtime], but also a [ProcessSpawner] and a [VmmOwnershipModel] in order to perform its functionality, and these
-/// objects will be used by VMs and VMM processes that internally embed a [ResourceSystem].
+/// the [ResourceSystem] will transmit a shutdown message that will end the task once all scheduled actions have finished
+/// on a best-effort basis, though their errors can't be observed anymore. Explicitly shutting the [ResourceSystem] down via
+/// [ResourceSystem::shutdown] is thus recommended, since it reports these errors and waits until the task has ended.
+///
+/// The [ResourceSystem] requires not only a [Runtime], but also a [ProcessSpawner] and a [VmmOwnershipModel] in order to
+/// perform its functionality, and these objects will be used by VMs and VMM processes that internally embed a
+/// [ResourceSystem].
 #[derive(Debug)]
 pub struct ResourceSystem<S: ProcessSpawner, R: Runtime> {
     request_tx: mpsc::UnboundedSender<ResourceSystemRequest<R>>,
@@ -33,6 +43,12 @@ pub struct ResourceSystem<S: ProcessSpawner, R: Runtime> {
     #[cfg(not(feature = "vmm-process"))]
     marker: PhantomData<S>,
     resources: Vec<Resource>,
+    default_moved_resource_type: MovedResourceType,
+    follow_symlinks: bool,
+    direct_io_copies: bool,
+    synchronization_id: u64,
+    counters: Arc<ResourceSystemCounters>,
+    exit_rx: futures_channel::oneshot::Receiver<()>,
     #[cfg(feature = "vmm-process")]
     pub(crate) process_spawner: S,
     #[cfg(feature = "vmm-process")]
@@ -69,14 +85,20 @@ impl<S: ProcessSpawner, R: Runtime> ResourceSystem<S, R> {
     ) -> Self {
         let (request_tx, request_rx) = mpsc::unbounded();
         let (response_tx, response_rx) = mpsc::unbounded();
+        let counters = Arc::new(ResourceSystemCounters::default());
+        let (exit_tx, exit_rx) = futures_channel::oneshot::channel();
 
         runtime.clone().spawn_task(resource_system_main_task(
-            request_rx,
-            response_tx,
+            ResourceSystemTaskContext {
+                request_rx,
+                response_tx,
+                exit_tx,
+                process_spawner: process_spawner.clone(),
+                runtime: runtime.clone(),
+                ownership_model,
+                counters: counters.clone(),
+            },
             owned_resources,
-            process_spawner.clone(),
-            runtime.clone(),
-            ownership_model,
         ));
 
         Self {
@@ -85,6 +107,12 @@ impl<S: ProcessSpawner, R: Runtime> ResourceSystem<S, R> {
             #[cfg(not(feature = "vmm-process"))]
             marker: PhantomData,
             resources,
+            default_moved_resource_type: MovedResourceType::Copied,
+            follow_symlinks: true,
+            direct_io_copies: false,
+            synchronization_id: 0,
+            counters,
+            exit_rx,
             #[cfg(feature = "vmm-process")]
             process_spawner,
             #[cfg(feature = "vmm-process")]
@@ -101,6 +129,16 @@ impl<S: ProcessSpawner, R: Runtime> ResourceSystem<S, R> {
         &self.resources
     }
 
+    /// Get a snapshot of the [ResourceSystemStatistics] accumulated by this [ResourceSystem] so far. The statistics
+    /// are only updated by initializations that have completed, so a [ResourceSystem::synchronize] call beforehand
+    /// ensures that all scheduled initializations are accounted for.
+    pub fn get_statistics(&self) -> ResourceSystemStatistics {
+        ResourceSystemStatistics {
+            bytes_copied: self.counters.bytes_copied.load(Ordering::Relaxed),
+            files_created: self.counters.files_created.load(Ordering::Relaxed),
+        }
+    }
+
     /// Create a [Resource] in this [ResourceSystem] from a given initial path and a [ResourceType]. The data will
     /// immediately be transmitted to the [ResourceSystem]'s central task, and an extra [Resource] clone will be
     /// stored inside the buffer accessible via [get_resources](ResourceSystem::get_resources).
@@ -119,6 +157,8 @@ impl<S: ProcessSpawner, R: Runtime> ResourceSystem<S, R> {
                 request_tx,
                 initial_path: initial_path.into(),
                 r#type,
+                follow_symlinks: self.follow_symlinks,
+                direct_io_copies: self.direct_io_copies,
                 init_info: OnceLock::new(),
                 disposed: AtomicBool::new(false),
             }),
@@ -134,21 +174,137 @@ impl<S: ProcessSpawner, R: Runtime> ResourceSystem<S, R> {
         Ok(resource)
     }
 
+    /// Set the default [MovedResourceType] used by [create_moved_resource](ResourceSystem::create_moved_resource).
+    /// Unless overridden via this function, [MovedResourceType::Copied] is used.
+    pub fn set_default_move_method(&mut self, moved_resource_type: MovedResourceType) {
+        self.default_moved_resource_type = moved_resource_type;
+    }
+
+    /// Get the default [MovedResourceType] of this [ResourceSystem].
+    pub fn get_default_move_method(&self) -> MovedResourceType {
+        self.default_moved_resource_type
+    }
+
+    /// Set whether moved [Resource]s created from now on in this [ResourceSystem] follow symlinks at their initial
+    /// path. When following, which is the default, the symlink's target is copied, hard linked or renamed. Otherwise,
+    /// the symlink itself is hard linked or renamed, and copying it creates a new symlink with the same target, which
+    /// may be inaccessible to a jailed Firecracker process if it points outside of the jail.
+    pub fn set_follow_symlinks(&mut self, follow_symlinks: bool) {
+        self.follow_symlinks = follow_symlinks;
+    }
+
+    /// Get whether moved [Resource]s created from now on in this [ResourceSystem] follow symlinks.
+    pub fn get_follow_symlinks(&self) -> bool {
+        self.follow_symlinks
+    }
+
+    /// Set whether moved [Resource]s created from now on in this [ResourceSystem] are copied with O_DIRECT, bypassing
+    /// the page cache. This is disabled by default, and enabling it prevents copying large files, such as rootfs
+    /// images, from evicting the cached data of already running VMs on dense hosts at the cost of a slower copy.
+    /// Copies onto or from filesystems that don't support O_DIRECT transparently fall back to regular copies.
+    pub fn set_direct_io_copies(&mut self, direct_io_copies: bool) {
+        self.direct_io_copies = direct_io_copies;
+    }
+
+    /// Get whether moved [Resource]s created from now on in this [ResourceSystem] are copied with O_DIRECT.
+    pub fn get_direct_io_copies(&self) -> bool {
+        self.direct_io_copies
+    }
+
+    /// Create a moved [Resource] in this [ResourceSystem] from a given initial path, using the default
+    /// [MovedResourceType] configured via [set_default_move_method](ResourceSystem::set_default_move_method).
+    pub fn create_moved_resource<P: Into<PathBuf>>(
+        &mut self,
+        initial_path: P,
+    ) -> Result<Resource, ResourceSystemError> {
+        self.create_resource(initial_path, ResourceType::Moved(self.default_moved_resource_type))
+    }
+
     /// Performs manual synchronization with the underlying central task. This operation waits until all initialization,
     /// disposal or other scheduled tasks complete. If all such tasks complete successfully, [Ok] is returned. If only one
     /// such task fails and all others succeed, a standard [ResourceSystemError] is returned. If multiple such tasks fail,
     /// a [ResourceSystemError::ErrorChain] variant is returned, encompassing multiple inner [ResourceSystemError]s for each
     /// failed task.
+    ///
+    /// This operation is cancellation-safe: the scheduled tasks run independently of the returned future, so dropping
+    /// it doesn't interrupt them, and a subsequent call will only wait for the tasks that haven't yet completed. Errors
+    /// of tasks that failed while a dropped synchronization was in progress are reported by the subsequent call.
     pub async fn synchronize(&mut self) -> Result<(), ResourceSystemError> {
+        self.synchronize_inner(None).await
+    }
+
+    /// Performs manual synchronization with the underlying central task like [ResourceSystem::synchronize], but only
+    /// waits until the scheduled tasks of the given [Resource]s complete, while those of all other [Resource]s keep
+    /// running in the background. This allows, for example, boot-critical resources such as the kernel and rootfs to
+    /// be ready before the VMM starts while secondary drives are still being copied.
+    ///
+    /// Only errors of the given [Resource]s' tasks are returned, while errors of other tasks that fail during this
+    /// synchronization are deferred to the next call of [ResourceSystem::synchronize].
+    pub async fn synchronize_subset(&mut self, resources: &[Resource]) -> Result<(), ResourceSystemError> {
+        self.synchronize_inner(Some(resources.iter().map(|resource| resource.0.clone()).collect()))
+            .await
+    }
+
+    async fn synchronize_inner(&mut self, subset: Option<Vec<Arc<ResourceInfo>>>) -> Result<(), ResourceSystemError> {
+        self.synchronization_id = self.synchronization_id.wrapping_add(1);
         self.request_tx
-            .unbounded_send(ResourceSystemRequest::Synchronize)
+            .unbounded_send(ResourceSystemRequest::Synchronize(self.synchronization_id, subset))
             .map_err(|_| ResourceSystemError::ChannelDisconnected)?;
 
-        match self.response_rx.next().await {
-            Some(ResourceSystemResponse::SynchronizationComplete(result)) => result,
-            None => Err(ResourceSystemError::ChannelDisconnected),
+        let mut stale_errors = Vec::new();
+
+        loop {
+            match self.response_rx.next().await {
+                Some(ResourceSystemResponse::SynchronizationComplete(id, result)) if id == self.synchronization_id => {
+                    if stale_errors.is_empty() {
+                        return result;
+                    }
+
+                    if let Err(err) = result {
+                        stale_errors.push(err);
+                    }
+
+                    return match stale_errors.len() {
+                        1 => Err(stale_errors
+                            .pop()
+                            .expect("stale_errors had length 1, but could not pop")),
+                        _ => Err(ResourceSystemError::ErrorChain(stale_errors)),
+                    };
+                }
+                // a response to a previously dropped synchronization, whose errors still need to be reported
+                Some(ResourceSystemResponse::SynchronizationComplete(_, result)) => {
+                    if let Err(err) = result {
+                        stale_errors.push(err);
+                    }
+                }
+                None => return Err(ResourceSystemError::ChannelDisconnected),
+            }
         }
     }
+
+    /// Perform [ResourceSystem::synchronize] until the given [CancellationToken] is cancelled, in which case
+    /// [ResourceSystemError::Cancelled] is returned instead. Since synchronization is cancellation-safe, the scheduled
+    /// tasks keep running in the background and can be waited for via a subsequent synchronization.
+    pub async fn synchronize_until_cancelled(
+        &mut self,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(), ResourceSystemError> {
+        cancellation_token
+            .run_until_cancelled(self.synchronize())
+            .await
+            .unwrap_or(Err(ResourceSystemError::Cancelled))
+    }
+
+    /// Explicitly shut down this [ResourceSystem], which is recommended over dropping it. All scheduled actions are
+    /// awaited via [ResourceSystem::synchronize], whose result is returned, after which the background task is ended
+    /// and awaited as well, guaranteeing that no task of this [ResourceSystem] remains on the [Runtime] afterwards.
+    pub async fn shutdown(mut self) -> Result<(), ResourceSystemError> {
+        let result = self.synchronize().await;
+        let _ = self.request_tx.unbounded_send(ResourceSystemRequest::Shutdown);
+        // the sender is dropped without sending once the task ends, so the cancellation signals the task's exit
+        let _ = (&mut self.exit_rx).await;
+        result
+    }
 }
 
 impl<S: ProcessSpawner, R: Runtime> Drop for ResourceSystem<S, R> {
@@ -157,6 +313,16 @@ impl<S: ProcessSpawner, R: Runtime> Drop for ResourceSystem<S, R> {
     }
 }
 
+/// Statistics about the filesystem work performed by a [ResourceSystem] while initializing its [Resource]s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResourceSystemStatistics {
+    /// The total amount of bytes copied when moving [Resource]s into the VMM environment. Resources that were hard
+    /// linked or renamed don't contribute to this amount.
+    pub bytes_copied: u64,
+    /// The amount of files and named pipes created for [ResourceType::Created] [Resource]s.
+    pub files_created: u64,
+}
+
 /// An error that can be emitted by a [ResourceSystem] or a standalone [Resource].
 #[derive(Debug)]
 pub enum ResourceSystemError {
@@ -172,9 +338,20 @@ pub enum ResourceSystemError {
     FilesystemError(std::io::Error),
     /// A [Resource]'s initial path was missing at the time of the execution of a scheduled action.
     InitialPathMissing,
+    /// Copying a moved [Resource] failed since the filesystem of its effective path ran out of space, after which the
+    /// partially copied file was removed.
+    InsufficientSpace {
+        /// The amount of bytes needed for the copy, which is the size of the [Resource]'s source file.
+        needed: u64,
+        /// The amount of bytes available to unprivileged users on the filesystem after the partially copied file
+        /// was removed.
+        available: u64,
+    },
     /// A chain of multiple [ResourceSystemError]s occurred, represented in the inner [Vec] according to
     /// their chronological order.
     ErrorChain(Vec<ResourceSystemError>),
+    /// The operation was aborted since the [CancellationToken] passed to it was cancelled.
+    Cancelled,
 }
 
 impl std::fmt::Display for ResourceSystemError {
@@ -193,11 +370,16 @@ impl std::fmt::Display for ResourceSystemError {
             ResourceSystemError::ChangeOwnerError(err) => write!(f, "An error occurred when changing ownership: {err}"),
             ResourceSystemError::FilesystemError(err) => write!(f, "A filesystem error occurred: {err}"),
             ResourceSystemError::InitialPathMissing => write!(f, "A resource's initial path is missing"),
+            ResourceSystemError::InsufficientSpace { needed, available } => write!(
+                f,
+                "Copying a resource requires {needed} bytes, but only {available} bytes of space are available"
+            ),
             ResourceSystemError::ErrorChain(errors) => write!(
                 f,
                 "A chain of {} errors occurred, meaning that number of operations failed",
                 errors.len()
             ),
+            ResourceSystemError::Cancelled => write!(f, "The operation was cancelled via a cancellation token"),
         }
     }
 }
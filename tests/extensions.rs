@@ -4,12 +4,18 @@ use bytes::Bytes;
 use codegen::{GuestAgentServiceClient, Ping, Pong};
 use fctools::{
     extension::{
-        grpc_vsock::VmVsockGrpc, http_vsock::VmVsockHttp, metrics::spawn_metrics_task,
+        grpc_vsock::VmVsockGrpc,
+        http_vsock::{VmVsockHttp, VmVsockHttpClientError},
+        metrics::{MetricsReadMode, spawn_metrics_task, spawn_metrics_task_with_read_mode},
         snapshot_editor::SnapshotEditorExt,
     },
+    process_spawner::DirectProcessSpawner,
     runtime::{RuntimeTask, tokio::TokioRuntime},
     vm::{api::VmApi, models::SnapshotType},
-    vmm::{process::HyperResponseExt, resource::CreatedResourceType},
+    vmm::{
+        process::{HyperResponseExt, LimitedResponseBodyError},
+        resource::CreatedResourceType,
+    },
 };
 use futures_util::StreamExt;
 use http_body_util::Full;
@@ -135,6 +141,12 @@ fn snapshot_editor_can_rebase_memory() {
 
         get_real_firecracker_installation()
             .snapshot_editor(TokioRuntime)
+            .rebase_memory(base_snapshot.mem_file_path.clone(), diff_snapshot.mem_file_path.clone())
+            .await
+            .unwrap();
+
+        get_real_firecracker_installation()
+            .snapshot_editor_with_spawner(DirectProcessSpawner, TokioRuntime)
             .rebase_memory(base_snapshot.mem_file_path, diff_snapshot.mem_file_path)
             .await
             .unwrap();
@@ -239,9 +251,44 @@ async fn test_metrics_recv(is_fifo: bool, mut vm: TestVm) {
     let mut metrics_task = spawn_metrics_task(metrics_path, 100, TokioRuntime);
     let metrics = metrics_task.receiver.next().await.unwrap();
     assert!(metrics.put_api_requests.actions_count > 0);
+
+    let prometheus_metrics = metrics.to_prometheus("firecracker");
+    assert!(prometheus_metrics.contains("# TYPE firecracker_put_api_requests_actions_count gauge\n"));
+    assert!(!prometheus_metrics.contains("_total"));
+    assert!(prometheus_metrics.contains("# TYPE firecracker_latencies_us_load_snapshot gauge\n"));
+    assert!(!prometheus_metrics.contains("utc_timestamp_ms"));
     shutdown_test_vm(&mut vm).await;
 }
 
+#[test]
+fn metrics_task_can_receive_data_from_plaintext_in_overwrite_mode() {
+    VmBuilder::new()
+        .metrics_system(CreatedResourceType::File)
+        .run(|mut vm| async move {
+            vm.flush_metrics().await.unwrap();
+            let mut metrics_task = spawn_metrics_task_with_read_mode(
+                vm.get_configuration()
+                    .get_data()
+                    .metrics_system
+                    .as_ref()
+                    .unwrap()
+                    .metrics
+                    .get_effective_path()
+                    .unwrap()
+                    .to_owned(),
+                100,
+                MetricsReadMode::Overwrite {
+                    poll_interval: Duration::from_millis(50),
+                },
+                TokioRuntime,
+            );
+            let metrics = metrics_task.receiver.next().await.unwrap();
+            assert!(metrics.put_api_requests.actions_count > 0);
+            metrics_task.task.cancel().await;
+            shutdown_test_vm(&mut vm).await;
+        });
+}
+
 #[test]
 fn metrics_task_can_be_cancelled_via_join_handle() {
     VmBuilder::new()
@@ -304,6 +351,43 @@ fn vsock_can_use_http_client_backed_by_connection_pool() {
     });
 }
 
+#[test]
+fn vsock_http_client_can_limit_response_body_size() {
+    VmBuilder::new().vsock_device().run(|mut vm| async move {
+        let client = vm.connect_to_http_over_vsock_via_pool(VSOCK_HTTP_GUEST_PORT).unwrap();
+        let response = client
+            .send_request_with_limited_response(make_vsock_req(), 1024)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<PingResponse>(response.body()).unwrap(),
+            PingResponse { c: 20 }
+        );
+
+        let error = client
+            .send_request_with_limited_response(make_vsock_req(), 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            VmVsockHttpClientError::ResponseBodyError(LimitedResponseBodyError::LimitExceeded(1))
+        ));
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
+#[test]
+fn vsock_can_use_http_client_backed_by_lazy_connection() {
+    VmBuilder::new().vsock_device().run(|mut vm| async move {
+        let client = vm.connect_lazily_to_http_over_vsock(VSOCK_HTTP_GUEST_PORT).unwrap();
+        for _ in 0..2 {
+            let response = client.send_request(make_vsock_req()).await.unwrap();
+            assert_vsock_resp(response).await;
+        }
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
 #[test]
 fn vsock_can_perform_unary_grpc_request() {
     VmBuilder::new().vsock_device().run(|mut vm| async move {
@@ -1,12 +1,39 @@
-use std::path::PathBuf;
+use std::{
+    ffi::OsString,
+    io::Write,
+    os::unix::fs::FileTypeExt,
+    path::{Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
 
 use fctools::{
-    process_spawner::{DirectProcessSpawner, ProcessSpawner, SuProcessSpawner, SudoProcessSpawner},
-    runtime::{RuntimeChild, tokio::TokioRuntime},
-    vmm::installation::{VmmInstallation, VmmInstallationVerificationError},
+    process_spawner::{
+        DirectProcessSpawner, ProcessSpawner, SuProcessSpawner, SudoProcessSpawner, ThrottlingProcessSpawner,
+    },
+    runtime::{Runtime, RuntimeChild, cancellation::CancellationToken, tokio::TokioRuntime},
+    vm::{
+        configuration::ConfigValidationError,
+        models::{LoadSnapshotBuilder, MemoryBackendType},
+    },
+    vmm::{
+        arguments::{VmmApiSocket, VmmArguments, jailer::JailerArguments},
+        executor::{
+            VmmExecutor, VmmExecutorContext, VmmExecutorError,
+            jailed::{FlatVirtualPathResolver, JailedVmmExecutor},
+            managed::ManagedVmmExecutor,
+        },
+        id::VmmId,
+        installation::{VmmInstallation, VmmInstallationVerificationError},
+        ownership::VmmOwnershipModel,
+        resource::{CreatedResourceType, MovedResourceType, ResourceState, ResourceType, system::ResourceSystemError},
+    },
 };
 use futures_util::AsyncReadExt;
-use test_framework::{TestOptions, get_test_path};
+use test_framework::{TestMovedResource, TestOptions, get_resource_system, get_test_path, get_tmp_path};
 use uuid::Uuid;
 
 mod test_framework;
@@ -81,6 +108,30 @@ async fn installation_does_not_verify_for_incorrect_binary_version() {
     );
 }
 
+#[tokio::test]
+async fn installation_does_not_verify_for_foreign_architecture() {
+    // a minimal little-endian ELF header for the architecture that the host isn't running
+    let foreign_machine: u16 = if cfg!(target_arch = "x86_64") { 183 } else { 62 };
+    let mut header = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2, 0];
+    header.extend_from_slice(&foreign_machine.to_le_bytes());
+    let foreign_path = get_tmp_path();
+    std::fs::write(&foreign_path, header).unwrap();
+
+    let installation = VmmInstallation::new(
+        foreign_path.clone(),
+        get_test_path("toolchain/jailer"),
+        get_test_path("toolchain/snapshot-editor"),
+    );
+
+    assert_matches::assert_matches!(
+        installation
+            .verify(&TestOptions::get().await.toolchain.version, &TokioRuntime)
+            .await,
+        Err(VmmInstallationVerificationError::BinaryHasIncorrectArchitecture(machine)) if machine == foreign_machine
+    );
+    std::fs::remove_file(foreign_path).unwrap();
+}
+
 #[tokio::test]
 async fn installation_verifies_for_correct_parameters() {
     let installation = VmmInstallation::new(
@@ -95,6 +146,468 @@ async fn installation_verifies_for_correct_parameters() {
         .unwrap();
 }
 
+#[tokio::test]
+async fn installation_can_be_preloaded() {
+    let installation = VmmInstallation::new(
+        get_test_path("toolchain/firecracker"),
+        get_test_path("toolchain/jailer"),
+        get_test_path("toolchain/snapshot-editor"),
+    );
+    let expected_size = std::fs::metadata(installation.get_firecracker_path()).unwrap().len()
+        + std::fs::metadata(installation.get_jailer_path()).unwrap().len();
+    assert_eq!(installation.preload(&TokioRuntime).await.unwrap(), expected_size);
+}
+
+#[tokio::test]
+async fn resource_system_can_resume_synchronization_after_cancellation() {
+    let mut resource_system = get_resource_system();
+    let moved = TestMovedResource::new(
+        &mut resource_system,
+        vec![0u8; 64 * 1024 * 1024],
+        MovedResourceType::Copied,
+    );
+
+    {
+        let mut synchronize_future = std::pin::pin!(resource_system.synchronize());
+        assert!(futures_util::poll!(synchronize_future.as_mut()).is_pending());
+    }
+
+    resource_system.synchronize().await.unwrap();
+    assert_eq!(moved.resource.get_state(), ResourceState::Initialized);
+    assert_eq!(
+        std::fs::metadata(&moved.destination_path).unwrap().len(),
+        std::fs::metadata(&moved.source_path).unwrap().len()
+    );
+
+    let produced_resource = resource_system
+        .create_resource(get_tmp_path(), ResourceType::Produced)
+        .unwrap();
+    produced_resource.start_initialization_with_same_path().unwrap();
+    resource_system.synchronize().await.unwrap();
+    assert_eq!(produced_resource.get_state(), ResourceState::Initialized);
+}
+
+#[tokio::test]
+async fn resource_system_can_snapshot_moved_resource() {
+    let mut resource_system = get_resource_system();
+    let moved = TestMovedResource::new(
+        &mut resource_system,
+        vec![1u8; 1024 * 1024],
+        MovedResourceType::Snapshotted,
+    );
+    let cow_path = PathBuf::from(format!("{}.cow", moved.destination_path.display()));
+
+    resource_system.synchronize().await.unwrap();
+    assert_eq!(moved.resource.get_state(), ResourceState::Initialized);
+    assert!(
+        std::fs::metadata(&moved.destination_path)
+            .unwrap()
+            .file_type()
+            .is_block_device()
+    );
+    assert_eq!(std::fs::read(&moved.destination_path).unwrap(), vec![1u8; 1024 * 1024]);
+
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(&moved.destination_path)
+        .unwrap()
+        .write_all(&[2u8; 4096])
+        .unwrap();
+    assert_eq!(std::fs::read(&moved.destination_path).unwrap()[..4096], [2u8; 4096]);
+    assert_eq!(std::fs::read(&moved.source_path).unwrap(), vec![1u8; 1024 * 1024]);
+
+    moved.resource.start_disposal().unwrap();
+    resource_system.synchronize().await.unwrap();
+    assert_eq!(moved.resource.get_state(), ResourceState::Disposed);
+    assert!(!std::fs::exists(&moved.destination_path).unwrap());
+    assert!(!std::fs::exists(&cow_path).unwrap());
+    assert_eq!(std::fs::read(&moved.source_path).unwrap(), vec![1u8; 1024 * 1024]);
+}
+
+#[tokio::test]
+async fn resource_system_drains_created_fifo() {
+    let path = get_tmp_path();
+
+    let mut resource_system = get_resource_system();
+    let resource = resource_system
+        .create_resource(&path, ResourceType::Created(CreatedResourceType::DrainedFifo))
+        .unwrap();
+    resource.start_initialization(path.clone(), None).unwrap();
+    resource_system.synchronize().await.unwrap();
+    assert_eq!(resource.get_state(), ResourceState::Initialized);
+
+    // much more than the default pipe capacity, which would block forever without a reader
+    let writer_path = path.clone();
+    tokio::time::timeout(
+        Duration::from_secs(10),
+        tokio::task::spawn_blocking(move || std::fs::write(writer_path, vec![0u8; 4 * 1024 * 1024])),
+    )
+    .await
+    .unwrap()
+    .unwrap()
+    .unwrap();
+
+    resource.start_disposal().unwrap();
+    resource_system.synchronize().await.unwrap();
+    assert_eq!(resource.get_state(), ResourceState::Disposed);
+    assert!(!std::fs::exists(&path).unwrap());
+}
+
+#[tokio::test]
+async fn resource_system_unblocks_drain_of_unopened_fifo() {
+    let path = get_tmp_path();
+
+    let mut resource_system = get_resource_system();
+    let resource = resource_system
+        .create_resource(&path, ResourceType::Created(CreatedResourceType::DrainedFifo))
+        .unwrap();
+    resource.start_initialization(path.clone(), None).unwrap();
+    resource_system.synchronize().await.unwrap();
+
+    resource.start_disposal().unwrap();
+    tokio::time::timeout(Duration::from_secs(10), resource_system.synchronize())
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(!std::fs::exists(&path).unwrap());
+}
+
+#[tokio::test]
+async fn jailed_executor_reuses_pre_created_jail_once() {
+    let chroot_base_dir = get_tmp_path();
+    let installation = VmmInstallation::new("/opt/firecracker", "/opt/jailer", "/opt/snapshot-editor");
+    let executor = JailedVmmExecutor::new(
+        VmmArguments::new(VmmApiSocket::Enabled("/sockets/fc.sock".into())),
+        JailerArguments::new(VmmId::new("1").unwrap()).chroot_base_dir(&chroot_base_dir),
+        FlatVirtualPathResolver,
+    );
+    let jail_path = chroot_base_dir.join("firecracker/1/root");
+    let kernel_path = get_tmp_path();
+    std::fs::write(&kernel_path, "kernel").unwrap();
+
+    let mut resource_system = get_resource_system();
+    let kernel = resource_system
+        .create_resource(&kernel_path, ResourceType::Moved(MovedResourceType::Copied))
+        .unwrap();
+    let context = || VmmExecutorContext {
+        installation: installation.clone(),
+        process_spawner: DirectProcessSpawner,
+        runtime: TokioRuntime,
+        ownership_model: VmmOwnershipModel::Shared,
+        resources: &[],
+    };
+
+    executor
+        .pre_create_jail(&installation, &TokioRuntime, &mut resource_system)
+        .await
+        .unwrap();
+    assert!(std::fs::exists(jail_path.join("sockets")).unwrap());
+    assert_eq!(kernel.get_state(), ResourceState::Initialized);
+    let kernel_effective_path = kernel.get_effective_path().unwrap().to_owned();
+    assert_eq!(std::fs::read_to_string(&kernel_effective_path).unwrap(), "kernel");
+    std::fs::write(jail_path.join("warm"), "").unwrap();
+
+    // the pre-moved kernel isn't initialized again
+    executor
+        .prepare(VmmExecutorContext {
+            resources: resource_system.get_resources(),
+            ..context()
+        })
+        .await
+        .unwrap();
+    resource_system.synchronize().await.unwrap();
+    assert!(std::fs::exists(jail_path.join("warm")).unwrap());
+    assert!(std::fs::exists(&kernel_effective_path).unwrap());
+
+    executor.prepare(context()).await.unwrap();
+    assert!(!std::fs::exists(jail_path.join("warm")).unwrap());
+    assert!(std::fs::exists(jail_path.join("sockets")).unwrap());
+
+    // a marker whose jail was removed in the meantime isn't trusted
+    executor
+        .pre_create_jail(&installation, &TokioRuntime, &mut resource_system)
+        .await
+        .unwrap();
+    std::fs::remove_dir_all(&jail_path).unwrap();
+    executor.prepare(context()).await.unwrap();
+    assert!(std::fs::exists(jail_path.join("sockets")).unwrap());
+
+    std::fs::remove_dir_all(chroot_base_dir).unwrap();
+    std::fs::remove_file(kernel_path).unwrap();
+}
+
+#[tokio::test]
+async fn managed_executor_moves_resources_into_working_dir() {
+    let working_dir = get_tmp_path();
+    let executor = ManagedVmmExecutor::new(VmmArguments::new(VmmApiSocket::Disabled), &working_dir);
+    let kernel_path = get_tmp_path();
+    std::fs::write(&kernel_path, "kernel").unwrap();
+
+    let mut resource_system = get_resource_system();
+    let kernel = resource_system
+        .create_resource(&kernel_path, ResourceType::Moved(MovedResourceType::Copied))
+        .unwrap();
+    executor
+        .prepare(VmmExecutorContext {
+            installation: VmmInstallation::new("/opt/firecracker", "/opt/jailer", "/opt/snapshot-editor"),
+            process_spawner: DirectProcessSpawner,
+            runtime: TokioRuntime,
+            ownership_model: VmmOwnershipModel::Shared,
+            resources: resource_system.get_resources(),
+        })
+        .await
+        .unwrap();
+    resource_system.synchronize().await.unwrap();
+
+    let effective_path = working_dir.join(kernel_path.file_name().unwrap());
+    assert_eq!(kernel.get_effective_path(), Some(effective_path.as_path()));
+    assert_eq!(std::fs::read_to_string(effective_path).unwrap(), "kernel");
+
+    std::fs::remove_dir_all(working_dir).unwrap();
+    std::fs::remove_file(kernel_path).unwrap();
+}
+
+#[tokio::test]
+async fn managed_executor_rejects_moved_resources_with_same_filename() {
+    let working_dir = get_tmp_path();
+    let executor = ManagedVmmExecutor::new(VmmArguments::new(VmmApiSocket::Disabled), &working_dir);
+    let (first_dir, second_dir) = (get_tmp_path(), get_tmp_path());
+
+    let mut resource_system = get_resource_system();
+    for dir in [&first_dir, &second_dir] {
+        std::fs::create_dir(dir).unwrap();
+        std::fs::write(dir.join("rootfs.ext4"), "rootfs").unwrap();
+        resource_system
+            .create_resource(dir.join("rootfs.ext4"), ResourceType::Moved(MovedResourceType::Copied))
+            .unwrap();
+    }
+
+    let result = executor
+        .prepare(VmmExecutorContext {
+            installation: VmmInstallation::new("/opt/firecracker", "/opt/jailer", "/opt/snapshot-editor"),
+            process_spawner: DirectProcessSpawner,
+            runtime: TokioRuntime,
+            ownership_model: VmmOwnershipModel::Shared,
+            resources: resource_system.get_resources(),
+        })
+        .await;
+    assert!(matches!(
+        result,
+        Err(VmmExecutorError::ResourcePathCollision(path)) if path == working_dir.join("rootfs.ext4")
+    ));
+    assert!(
+        resource_system
+            .get_resources()
+            .iter()
+            .all(|resource| resource.get_state() == ResourceState::Uninitialized)
+    );
+    assert!(!std::fs::exists(&working_dir).unwrap());
+
+    std::fs::remove_dir_all(first_dir).unwrap();
+    std::fs::remove_dir_all(second_dir).unwrap();
+}
+
+#[tokio::test]
+async fn cancellation_token_aborts_futures_of_all_clones() {
+    let cancellation_token = CancellationToken::new();
+    let cancelling_token = cancellation_token.clone();
+    assert_eq!(cancellation_token.run_until_cancelled(async { 1 }).await, Some(1));
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancelling_token.cancel();
+    });
+
+    assert_eq!(
+        cancellation_token
+            .run_until_cancelled(std::future::pending::<()>())
+            .await,
+        None
+    );
+    assert!(cancellation_token.is_cancelled());
+    cancellation_token.cancelled().await;
+}
+
+#[tokio::test]
+async fn resource_system_synchronization_can_be_cancelled() {
+    let mut resource_system = get_resource_system();
+    let moved = TestMovedResource::new(
+        &mut resource_system,
+        vec![0u8; 16 * 1024 * 1024],
+        MovedResourceType::Copied,
+    );
+
+    let cancellation_token = CancellationToken::new();
+    cancellation_token.cancel();
+    assert_matches::assert_matches!(
+        resource_system.synchronize_until_cancelled(&cancellation_token).await,
+        Err(ResourceSystemError::Cancelled)
+    );
+
+    resource_system.synchronize().await.unwrap();
+    assert_eq!(moved.resource.get_state(), ResourceState::Initialized);
+}
+
+#[tokio::test]
+async fn resource_system_can_synchronize_subset() {
+    let created_path = get_tmp_path();
+    let mut resource_system = get_resource_system();
+    let moved = TestMovedResource::new(
+        &mut resource_system,
+        vec![0u8; 16 * 1024 * 1024],
+        MovedResourceType::Copied,
+    );
+    let created_resource = resource_system
+        .create_resource(&created_path, ResourceType::Created(CreatedResourceType::File))
+        .unwrap();
+    created_resource
+        .start_initialization(created_path.clone(), None)
+        .unwrap();
+
+    resource_system
+        .synchronize_subset(std::slice::from_ref(&created_resource))
+        .await
+        .unwrap();
+    assert_eq!(created_resource.get_state(), ResourceState::Initialized);
+
+    resource_system.synchronize().await.unwrap();
+    assert_eq!(moved.resource.get_state(), ResourceState::Initialized);
+
+    std::fs::remove_file(created_path).unwrap();
+}
+
+#[tokio::test]
+async fn resource_system_finalizes_scheduled_actions_on_shutdown() {
+    let mut resource_system = get_resource_system();
+    let moved = TestMovedResource::new(
+        &mut resource_system,
+        vec![0u8; 16 * 1024 * 1024],
+        MovedResourceType::Copied,
+    );
+    resource_system.shutdown().await.unwrap();
+
+    assert_eq!(moved.resource.get_state(), ResourceState::Initialized);
+    assert_eq!(
+        std::fs::metadata(&moved.destination_path).unwrap().len(),
+        std::fs::metadata(&moved.source_path).unwrap().len()
+    );
+}
+
+#[tokio::test]
+async fn resource_system_moves_symlinks_according_to_follow_setting() {
+    let target_path = get_tmp_path();
+    let symlink_path = get_tmp_path();
+    std::fs::write(&target_path, "content").unwrap();
+    std::os::unix::fs::symlink(&target_path, &symlink_path).unwrap();
+
+    let mut resource_system = get_resource_system();
+    assert!(resource_system.get_follow_symlinks());
+    let followed_destination_path = get_tmp_path();
+    let followed_resource = resource_system
+        .create_resource(&symlink_path, ResourceType::Moved(MovedResourceType::Copied))
+        .unwrap();
+    followed_resource
+        .start_initialization(followed_destination_path.clone(), None)
+        .unwrap();
+
+    resource_system.set_follow_symlinks(false);
+    let unfollowed_destination_path = get_tmp_path();
+    let unfollowed_resource = resource_system
+        .create_resource(&symlink_path, ResourceType::Moved(MovedResourceType::Copied))
+        .unwrap();
+    unfollowed_resource
+        .start_initialization(unfollowed_destination_path.clone(), None)
+        .unwrap();
+
+    resource_system.synchronize().await.unwrap();
+    assert!(std::fs::symlink_metadata(&followed_destination_path).unwrap().is_file());
+    assert_eq!(std::fs::read_to_string(&followed_destination_path).unwrap(), "content");
+    assert_eq!(std::fs::read_link(&unfollowed_destination_path).unwrap(), target_path);
+
+    for path in [
+        target_path,
+        symlink_path,
+        followed_destination_path,
+        unfollowed_destination_path,
+    ] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn resource_system_copies_unaligned_files_with_direct_io() {
+    let content = (0..5000).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let mut resource_system = get_resource_system();
+    resource_system.set_direct_io_copies(true);
+    let moved = TestMovedResource::new(&mut resource_system, &content, MovedResourceType::Copied);
+    resource_system.synchronize().await.unwrap();
+
+    assert_eq!(std::fs::read(&moved.destination_path).unwrap(), content);
+    assert_eq!(resource_system.get_statistics().bytes_copied, content.len() as u64);
+}
+
+#[tokio::test]
+async fn runtime_deduplicates_files_without_changing_contents() {
+    let path = get_tmp_path();
+    let reference_path = get_tmp_path();
+    let pattern = (0..4096).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let content = [vec![0; 8192], pattern.clone(), pattern.clone()].concat();
+    let reference_content = [vec![1; 8192], pattern, vec![1; 4096]].concat();
+    std::fs::write(&path, &content).unwrap();
+    std::fs::write(&reference_path, &reference_content).unwrap();
+
+    // the zeroed blocks are always turned into holes, while sharing the identical block depends on the filesystem
+    let deduplicated_length = TokioRuntime.fs_deduplicate(&path, &reference_path).await.unwrap();
+    assert!(deduplicated_length == 8192 || deduplicated_length == 12288);
+    assert_eq!(std::fs::read(&path).unwrap(), content);
+    assert_eq!(std::fs::read(&reference_path).unwrap(), reference_content);
+
+    for path in [path, reference_path] {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[tokio::test]
+async fn load_snapshot_builder_validates_load_snapshot() {
+    let mut resource_system = get_resource_system();
+    let snapshot = resource_system
+        .create_resource(get_tmp_path(), ResourceType::Moved(MovedResourceType::Copied))
+        .unwrap();
+    let mem_file = resource_system
+        .create_resource(get_tmp_path(), ResourceType::Moved(MovedResourceType::Copied))
+        .unwrap();
+    let produced_mem_file = resource_system
+        .create_resource(get_tmp_path(), ResourceType::Produced)
+        .unwrap();
+
+    let load_snapshot = LoadSnapshotBuilder::new(snapshot.clone(), MemoryBackendType::File, mem_file.clone())
+        .network_override("eth0", "tap1")
+        .build()
+        .unwrap();
+    assert_eq!(load_snapshot.resume_vm, Some(true));
+    assert_eq!(load_snapshot.network_overrides.len(), 1);
+
+    assert_eq!(
+        LoadSnapshotBuilder::new(snapshot.clone(), MemoryBackendType::File, produced_mem_file.clone()).build(),
+        Err(ConfigValidationError::SnapshotResourceNotMoved(
+            produced_mem_file.get_initial_path().to_owned()
+        ))
+    );
+    LoadSnapshotBuilder::new(snapshot.clone(), MemoryBackendType::Uffd, produced_mem_file)
+        .build()
+        .unwrap();
+
+    assert_eq!(
+        LoadSnapshotBuilder::new(snapshot, MemoryBackendType::File, mem_file)
+            .network_override("eth0", "tap1")
+            .network_override("eth0", "tap2")
+            .build(),
+        Err(ConfigValidationError::DuplicateNetworkOverrideIds(vec![
+            "eth0".to_owned()
+        ]))
+    );
+}
+
 #[tokio::test]
 async fn direct_process_spawner_can_null_pipes() {
     let mut process = DirectProcessSpawner
@@ -138,6 +651,51 @@ async fn sudo_process_spawner_can_null_pipes() {
     test_elevation(|password| SudoProcessSpawner::new(Some(password), None), true).await;
 }
 
+#[tokio::test]
+async fn throttling_process_spawner_limits_alive_processes() {
+    #[derive(Clone, Default)]
+    struct MockProcessSpawner(Arc<AtomicUsize>);
+
+    impl ProcessSpawner for MockProcessSpawner {
+        async fn spawn<R: Runtime>(
+            &self,
+            binary_path: &Path,
+            arguments: &[OsString],
+            disable_pipes: bool,
+            runtime: &R,
+        ) -> Result<R::Child, std::io::Error> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            DirectProcessSpawner
+                .spawn(binary_path, arguments, disable_pipes, runtime)
+                .await
+        }
+    }
+
+    let mock_process_spawner = MockProcessSpawner::default();
+    let process_spawner = ThrottlingProcessSpawner::new(mock_process_spawner.clone(), 1);
+    let mut process = process_spawner
+        .spawn(&PathBuf::from("sleep"), &["1".into()], true, &TokioRuntime)
+        .await
+        .unwrap();
+    assert_eq!(process_spawner.get_available_permits(), 0);
+
+    tokio::time::timeout(
+        Duration::from_millis(100),
+        process_spawner.spawn(&PathBuf::from("true"), &[], true, &TokioRuntime),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(mock_process_spawner.0.load(Ordering::SeqCst), 1);
+
+    process.wait().await.unwrap();
+    let mut process = process_spawner
+        .spawn(&PathBuf::from("true"), &[], true, &TokioRuntime)
+        .await
+        .unwrap();
+    assert_eq!(mock_process_spawner.0.load(Ordering::SeqCst), 2);
+    process.wait().await.unwrap();
+}
+
 async fn test_elevation<F: FnOnce(String) -> S, S: ProcessSpawner>(process_spawner_function: F, pipes_nulled: bool) {
     let Ok(password) = std::env::var("ROOT_PWD") else {
         println!("ROOT_PWD env var wasn't set for the elevation test, skipping it");
@@ -1,7 +1,13 @@
 use std::time::Duration;
 
 use bytes::Bytes;
-use fctools::vmm::process::{HyperResponseExt, VmmProcessState};
+use fctools::{
+    runtime::tokio::TokioRuntime,
+    vmm::{
+        executor::process_handle::ProcessHandle,
+        process::{HyperResponseExt, SeccompMode, VmmProcessSigkillTarget, VmmProcessState},
+    },
+};
 use futures_util::{AsyncBufReadExt, StreamExt, io::BufReader};
 use http_body_util::Full;
 use hyper::Request;
@@ -12,6 +18,15 @@ use crate::test_framework::assert_stdout_normality;
 
 mod test_framework;
 
+#[tokio::test]
+async fn pidfd_process_handle_reports_unknown_exit_status_of_reaped_process() {
+    let mut child = std::process::Command::new("sh").args(["-c", "exit 1"]).spawn().unwrap();
+    let mut process_handle = ProcessHandle::from_pidfd(child.id() as i32, TokioRuntime).unwrap();
+    assert!(!child.wait().unwrap().success());
+
+    assert_eq!(process_handle.wait_for_exit_status().await.unwrap(), None);
+}
+
 #[tokio::test]
 async fn vmm_can_recv_ctrl_alt_del() {
     run_vmm_process_test(false, |mut process| async move {
@@ -36,6 +51,40 @@ async fn vmm_can_recv_sigkill() {
     .await;
 }
 
+#[tokio::test]
+async fn vmm_can_recv_sigkill_to_process_group() {
+    run_vmm_process_test(false, |process| async move {
+        let mut process = process.sigkill_target(VmmProcessSigkillTarget::ProcessGroup);
+        process.send_sigkill().unwrap();
+        process.wait_for_exit().await.unwrap();
+        assert!(matches!(process.get_state(), VmmProcessState::Crashed(_)));
+        process.cleanup().await.unwrap();
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn vmm_exposes_process_handle() {
+    run_vmm_process_test(true, |mut process| async move {
+        let pid = process.get_pid();
+        assert!(pid.is_some());
+        assert_eq!(process.get_process_handle().unwrap().get_pid(), pid);
+        assert!(process.get_process_handle_mut().unwrap().try_wait().unwrap().is_none());
+        shutdown(&mut process).await;
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn vmm_reports_seccomp_mode() {
+    run_vmm_process_test(true, |mut process| async move {
+        assert_eq!(process.seccomp_mode().await.unwrap(), SeccompMode::Filter);
+        shutdown(&mut process).await;
+        process.seccomp_mode().await.unwrap_err();
+    })
+    .await;
+}
+
 #[tokio::test]
 async fn vmm_can_take_out_pipes() {
     run_vmm_process_test(true, |mut process| async move {
@@ -17,8 +17,8 @@ use fctools::{
         Vm,
         configuration::{InitMethod, VmConfiguration, VmConfigurationData},
         models::{
-            BalloonDevice, BootSource, CreateSnapshot, Drive, LoggerSystem, MachineConfiguration, MetricsSystem,
-            MmdsConfiguration, MmdsVersion, NetworkInterface, SnapshotType, VsockDevice,
+            BalloonDevice, BalloonSizeMib, BootSource, CreateSnapshot, Drive, LoggerSystem, MachineConfiguration,
+            MetricsSystem, MmdsConfiguration, MmdsVersion, NetworkInterface, SnapshotType, VsockDevice,
         },
         shutdown::{VmShutdownAction, VmShutdownMethod},
     },
@@ -36,7 +36,7 @@ use fctools::{
         installation::VmmInstallation,
         ownership::VmmOwnershipModel,
         process::{VmmProcess, VmmProcessState},
-        resource::{CreatedResourceType, MovedResourceType, ResourceType, system::ResourceSystem},
+        resource::{CreatedResourceType, MovedResourceType, Resource, ResourceType, system::ResourceSystem},
     },
 };
 use serde::Deserialize;
@@ -134,6 +134,7 @@ pub fn get_create_snapshot(resource_system: &mut TestResourceSystem) -> CreateSn
         mem_file: resource_system
             .create_resource(get_tmp_path(), ResourceType::Produced)
             .unwrap(),
+        overwrite: false,
     }
 }
 
@@ -152,6 +153,54 @@ pub fn assert_stdout_normality(buf: String) {
     );
 }
 
+// RESOURCE SYSTEM TEST FRAMEWORK
+
+#[allow(unused)]
+pub fn get_resource_system() -> TestResourceSystem {
+    ResourceSystem::new(DirectProcessSpawner, TokioRuntime, VmmOwnershipModel::Shared)
+}
+
+/// A moved resource scheduled for initialization from a temporary source file into a temporary destination, both of
+/// which are removed once this is dropped.
+#[allow(unused)]
+pub struct TestMovedResource {
+    pub resource: Resource,
+    pub source_path: PathBuf,
+    pub destination_path: PathBuf,
+}
+
+#[allow(unused)]
+impl TestMovedResource {
+    pub fn new(
+        resource_system: &mut TestResourceSystem,
+        content: impl AsRef<[u8]>,
+        moved_resource_type: MovedResourceType,
+    ) -> Self {
+        let source_path = get_tmp_path();
+        let destination_path = get_tmp_path();
+        std::fs::write(&source_path, content).unwrap();
+
+        let resource = resource_system
+            .create_resource(&source_path, ResourceType::Moved(moved_resource_type))
+            .unwrap();
+        resource.start_initialization(destination_path.clone(), None).unwrap();
+
+        Self {
+            resource,
+            source_path,
+            destination_path,
+        }
+    }
+}
+
+impl Drop for TestMovedResource {
+    fn drop(&mut self) {
+        for path in [&self.source_path, &self.destination_path] {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
 // VMM TEST FRAMEWORK
 
 #[allow(unused)]
@@ -272,6 +321,7 @@ pub struct VmBuilder {
     boot_arg_append: String,
     mmds: bool,
     new_pid_ns: bool,
+    boot_args_from_resource: bool,
 }
 
 #[allow(unused)]
@@ -289,6 +339,7 @@ impl VmBuilder {
             boot_arg_append: String::new(),
             mmds: false,
             new_pid_ns: true,
+            boot_args_from_resource: false,
         }
     }
 
@@ -327,7 +378,7 @@ impl VmBuilder {
         free_page_hinting: bool,
     ) -> Self {
         self.balloon_device = Some(BalloonDevice {
-            amount_mib: 64,
+            amount_mib: BalloonSizeMib::new(64),
             deflate_on_oom: false,
             stats_polling_interval_s: polling_interval_s,
             free_page_reporting: Some(free_page_reporting),
@@ -358,6 +409,11 @@ impl VmBuilder {
         self
     }
 
+    pub fn boot_args_from_resource(mut self) -> Self {
+        self.boot_args_from_resource = true;
+        self
+    }
+
     fn setup_simple_network(&self) -> NetworkData {
         let subnet_index = fastrand::u16(1..1000);
         let subnet = LinkLocalSubnet::new(subnet_index, 30).unwrap();
@@ -483,6 +539,7 @@ impl VmBuilder {
                         .unwrap(),
                     boot_args: Some(boot_args),
                     initrd: None,
+                    boot_args_resource: None,
                 },
                 drives: vec![Drive {
                     drive_id: "rootfs".to_string(),
@@ -544,6 +601,16 @@ impl VmBuilder {
             }
         }
 
+        fn move_boot_args_to_resource(resource_system: &mut TestResourceSystem, data: &mut VmConfigurationData) {
+            let boot_args_path = get_tmp_path();
+            let boot_args = data.boot_source.boot_args.take().unwrap();
+            std::fs::write(&boot_args_path, format!("{boot_args}\n")).unwrap();
+            let resource = resource_system
+                .create_resource(boot_args_path, ResourceType::Moved(MovedResourceType::Copied))
+                .unwrap();
+            data.boot_source = data.boot_source.clone().boot_args_from_resource(resource);
+        }
+
         fn new_vsock_device(resource_system: &mut TestResourceSystem) -> VsockDevice {
             VsockDevice {
                 guest_cid: fastrand::u32(2..u32::MAX),
@@ -613,6 +680,11 @@ impl VmBuilder {
             jailed_data.metrics_system = Some(new_metrics_system(&mut jailed_resource_system, r#type));
         }
 
+        if self.boot_args_from_resource {
+            move_boot_args_to_resource(&mut unrestricted_resource_system, &mut unrestricted_data);
+            move_boot_args_to_resource(&mut jailed_resource_system, &mut jailed_data);
+        }
+
         if self.vsock_device {
             unrestricted_data.vsock_device = Some(new_vsock_device(&mut unrestricted_resource_system));
             jailed_data.vsock_device = Some(new_vsock_device(&mut jailed_resource_system));
@@ -5,10 +5,11 @@ use fctools::{
     runtime::tokio::TokioRuntime,
     vm::{
         VmState,
-        api::VmApi,
-        configuration::InitMethod,
+        api::{VmApi, VmApiError},
+        configuration::{ConfigValidationError, InitMethod},
+        models::{CreateSnapshot, SnapshotType},
         shutdown::{VmShutdownAction, VmShutdownMethod},
-        snapshot::{PrepareVmFromSnapshotOptions, VmSnapshot},
+        snapshot::{PrepareVmFromSnapshotOptions, VmQuiescer, VmSnapshot},
     },
     vmm::{
         arguments::{VmmApiSocket, VmmArguments, jailer::JailerArguments},
@@ -18,7 +19,8 @@ use fctools::{
             unrestricted::UnrestrictedVmmExecutor,
         },
         ownership::VmmOwnershipModel,
-        resource::{CreatedResourceType, MovedResourceType},
+        process::VmmProcessState,
+        resource::{CreatedResourceType, MovedResourceType, ResourceType},
     },
 };
 use futures_util::{AsyncBufReadExt, StreamExt, io::BufReader};
@@ -47,6 +49,15 @@ fn vm_can_boot_via_json() {
         });
 }
 
+#[test]
+fn vm_can_boot_via_transient_json() {
+    VmBuilder::new()
+        .init_method(InitMethod::ViaTransientJsonConfiguration(get_tmp_path()))
+        .run(|mut vm| async move {
+            shutdown_test_vm(&mut vm).await;
+        });
+}
+
 #[test]
 fn vm_can_shut_down_via_ctrl_alt_del() {
     vm_shutdown_test(VmShutdownMethod::CtrlAltDel);
@@ -77,11 +88,69 @@ fn vm_shutdown_test(method: VmShutdownMethod) {
             assert!(method != VmShutdownMethod::CtrlAltDel || outcome.graceful);
             assert!(outcome.errors.is_empty());
             assert_eq!(outcome.index, 0);
+            assert!(!vm.is_cleaned_up());
             vm.cleanup().await.unwrap();
+            assert!(vm.is_cleaned_up());
+            vm.cleanup().await.unwrap_err(); // cannot clean up twice
+
+            let resource_report = vm.resource_report();
+            assert!(resource_report.run_duration.is_some());
+            assert!(resource_report.peak_disk_usage > 0);
         }
     });
 }
 
+#[test]
+fn vm_snapshot_exposes_vsock_cid() {
+    VmBuilder::new().vsock_device().run(|mut vm| async move {
+        vm.pause().await.unwrap();
+        let create_snapshot = get_create_snapshot(vm.get_resource_system_mut());
+        let snapshot = vm.create_snapshot(create_snapshot).await.unwrap();
+        vm.resume().await.unwrap();
+
+        assert_eq!(
+            snapshot.get_vsock_cid(),
+            Some(
+                vm.get_configuration()
+                    .get_data()
+                    .vsock_device
+                    .as_ref()
+                    .unwrap()
+                    .guest_cid
+            )
+        );
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
+#[test]
+fn vm_records_timing_breakdown() {
+    VmBuilder::new().run(|mut vm| async move {
+        let timing = vm.timing_breakdown();
+        assert!(timing.prepare.is_some());
+        assert!(timing.invoke.is_some());
+        assert!(timing.socket_wait.is_some());
+        assert!(timing.api_init.is_some());
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
+#[test]
+fn vm_can_be_cleaned_up_in_best_effort_manner() {
+    VmBuilder::new().run(|mut vm| async move {
+        vm.shutdown([VmShutdownAction {
+            method: VmShutdownMethod::Kill,
+            timeout: None,
+            graceful: true,
+        }])
+        .await
+        .unwrap();
+        assert!(vm.cleanup_best_effort().await.is_empty());
+        assert!(vm.is_cleaned_up());
+        assert_eq!(vm.cleanup_best_effort().await.len(), 1); // cannot clean up twice
+    });
+}
+
 #[test]
 fn vm_processes_logger_path_as_fifo() {
     vm_logger_test(CreatedResourceType::Fifo);
@@ -211,6 +280,23 @@ fn vm_can_take_pipes() {
         });
 }
 
+#[test]
+fn vm_can_stream_console() {
+    VmBuilder::new().no_new_pid_ns().run(|mut vm| async move {
+        let mut console_stream = Box::pin(vm.console_stream().unwrap());
+        vm.take_pipes().unwrap_err(); // cannot take out pipes after the console stream
+        vm.console_stream().unwrap_err(); // cannot stream the console twice
+        shutdown_test_vm(&mut vm).await;
+
+        let mut buf = Vec::new();
+        while let Some(Ok(chunk)) = console_stream.next().await {
+            buf.extend_from_slice(&chunk);
+        }
+
+        assert_stdout_normality(String::from_utf8_lossy(&buf).into_owned());
+    });
+}
+
 #[test]
 fn vm_tracks_state_with_graceful_exit() {
     VmBuilder::new()
@@ -226,6 +312,20 @@ fn vm_tracks_state_with_graceful_exit() {
         });
 }
 
+#[test]
+fn vm_does_not_watch_host_initiated_shutdown_as_guest_initiated() {
+    VmBuilder::new().run(|mut vm| async move {
+        let (guest_shutdown_tx, guest_shutdown_rx) = futures_channel::oneshot::channel();
+        vm.watch_guest_shutdown(move || {
+            let _ = guest_shutdown_tx.send(());
+        })
+        .unwrap();
+
+        shutdown_test_vm(&mut vm).await;
+        assert!(guest_shutdown_rx.await.is_err());
+    });
+}
+
 #[test]
 fn vm_tracks_state_with_crash() {
     VmBuilder::new().run(|mut vm| async move {
@@ -247,6 +347,90 @@ fn vm_can_snapshot_while_original_is_running() {
     });
 }
 
+#[test]
+fn vm_can_snapshot_quiesced() {
+    #[derive(Default)]
+    struct CountingQuiescer {
+        quiesced: bool,
+        unquiesce_count: u32,
+    }
+
+    impl VmQuiescer for CountingQuiescer {
+        async fn quiesce(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.quiesced = true;
+            Ok(())
+        }
+
+        async fn unquiesce(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.unquiesce_count += 1;
+            Ok(())
+        }
+    }
+
+    VmBuilder::new().run(|mut vm| async move {
+        let mut quiescer = CountingQuiescer::default();
+        let create_snapshot = get_create_snapshot(vm.get_resource_system_mut());
+        vm.snapshot_quiesced(create_snapshot, &mut quiescer).await.unwrap();
+        assert!(quiescer.quiesced);
+        assert_eq!(quiescer.unquiesce_count, 1);
+        assert_eq!(vm.get_state(), VmState::Running);
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
+#[test]
+fn vm_rejects_overwriting_snapshot_unless_allowed() {
+    VmBuilder::new().run(|mut vm| async move {
+        vm.pause().await.unwrap();
+        let create_snapshot = get_create_snapshot(vm.get_resource_system_mut());
+        let snapshot_path = create_snapshot.snapshot.get_initial_path().to_owned();
+        let mem_file_path = create_snapshot.mem_file.get_initial_path().to_owned();
+        vm.create_snapshot(create_snapshot).await.unwrap();
+
+        let mut create_snapshot = CreateSnapshot {
+            snapshot_type: Some(SnapshotType::Full),
+            snapshot: vm
+                .get_resource_system_mut()
+                .create_resource(snapshot_path, ResourceType::Produced)
+                .unwrap(),
+            mem_file: vm
+                .get_resource_system_mut()
+                .create_resource(mem_file_path, ResourceType::Produced)
+                .unwrap(),
+            overwrite: false,
+        };
+        assert_matches::assert_matches!(
+            vm.create_snapshot(create_snapshot.clone()).await,
+            Err(VmApiError::InvalidConfiguration(
+                ConfigValidationError::SnapshotFileAlreadyExists(_)
+            ))
+        );
+
+        create_snapshot.overwrite = true;
+        vm.create_snapshot(create_snapshot).await.unwrap();
+        vm.resume().await.unwrap();
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
+#[test]
+fn vm_can_dump_guest_memory() {
+    VmBuilder::new().run(|mut vm| async move {
+        let snapshot = vm.dump_guest_memory(get_tmp_path(), get_tmp_path()).await.unwrap();
+        assert_eq!(vm.get_state(), VmState::Running);
+        assert_eq!(
+            metadata(&snapshot.mem_file_path).await.unwrap().len(),
+            snapshot.configuration_data.machine_configuration.mem_size_mib as u64 * 1024 * 1024
+        );
+
+        vm.pause().await.unwrap();
+        vm.dump_guest_memory(get_tmp_path(), get_tmp_path()).await.unwrap();
+        assert_eq!(vm.get_state(), VmState::Paused);
+        vm.resume().await.unwrap();
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
 #[test]
 fn vm_can_snapshot_after_original_has_exited() {
     VmBuilder::new().run_with_is_jailed(|mut old_vm, is_jailed| async move {
@@ -264,6 +448,31 @@ fn vm_can_snapshot_after_original_has_exited() {
     });
 }
 
+#[test]
+fn vm_can_revert_to_snapshot() {
+    VmBuilder::new().run_with_is_jailed(|mut vm, is_jailed| async move {
+        vm.pause().await.unwrap();
+        let create_snapshot = get_create_snapshot(vm.get_resource_system_mut());
+        let mut snapshot = vm.create_snapshot(create_snapshot).await.unwrap();
+        snapshot
+            .copy(&TokioRuntime, get_tmp_path(), get_tmp_path())
+            .await
+            .unwrap();
+        vm.resume().await.unwrap();
+
+        vm.revert_to_snapshot(
+            &snapshot,
+            get_prepare_snapshot_options(is_jailed).await,
+            Duration::from_millis(TestOptions::get().await.waits.boot_socket_timeout_ms),
+        )
+        .await
+        .unwrap();
+        assert_eq!(vm.get_state(), VmState::Running);
+        vm.get_info().await.unwrap();
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
 #[test]
 fn vm_can_boot_with_simple_networking() {
     VmBuilder::new().simple_networking().run(|mut vm| async move {
@@ -271,6 +480,41 @@ fn vm_can_boot_with_simple_networking() {
     });
 }
 
+#[test]
+fn vm_reports_network_topology() {
+    VmBuilder::new().simple_networking().run(|mut vm| async move {
+        let network_topology = vm.network_topology();
+        assert_eq!(network_topology.len(), 1);
+        assert_eq!(network_topology[0].iface_id, "eth0");
+        assert_eq!(network_topology[0].guest_dev_name, "eth0");
+        assert!(network_topology[0].host_dev_name.starts_with("vtap"));
+        assert!(network_topology[0].guest_ip.unwrap().is_link_local());
+        assert_eq!(network_topology[0].mmds_ipv4_address, None);
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
+#[test]
+fn vm_can_be_decomposed_into_vmm_process() {
+    VmBuilder::new().run(|vm| async move {
+        let mut vmm_process = vm.into_vmm_process();
+        assert_eq!(vmm_process.get_state(), VmmProcessState::Started);
+        vmm_process.send_ctrl_alt_del().await.unwrap();
+        assert!(vmm_process.wait_for_exit().await.unwrap().success());
+        vmm_process.cleanup().await.unwrap();
+    });
+}
+
+#[test]
+fn vm_can_boot_with_boot_args_from_resource() {
+    VmBuilder::new().boot_args_from_resource().run(|mut vm| async move {
+        let boot_args = vm.get_configuration().get_data().boot_source.boot_args.clone().unwrap();
+        assert!(boot_args.starts_with("console=ttyS0"));
+        assert!(!boot_args.ends_with('\n'));
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
 #[test]
 fn vm_can_boot_with_namespaced_networking() {
     VmBuilder::new().namespaced_networking().run(|mut vm| async move {
@@ -286,6 +530,15 @@ fn vm_can_boot_with_vsock_device() {
 }
 
 async fn prepare_snapshot_vm(old_vm: &mut TestVm, snapshot: VmSnapshot, is_jailed: bool) -> TestVm {
+    snapshot
+        .prepare_vm(old_vm, get_prepare_snapshot_options(is_jailed).await)
+        .await
+        .unwrap()
+}
+
+async fn get_prepare_snapshot_options(
+    is_jailed: bool,
+) -> PrepareVmFromSnapshotOptions<EitherVmmExecutor<FlatVirtualPathResolver>, DirectProcessSpawner, TokioRuntime> {
     let executor = match is_jailed {
         true => EitherVmmExecutor::Jailed(JailedVmmExecutor::new(
             VmmArguments::new(VmmApiSocket::Enabled(get_tmp_path())),
@@ -297,25 +550,20 @@ async fn prepare_snapshot_vm(old_vm: &mut TestVm, snapshot: VmSnapshot, is_jaile
         ))),
     };
 
-    snapshot
-        .prepare_vm(
-            old_vm,
-            PrepareVmFromSnapshotOptions {
-                executor,
-                process_spawner: DirectProcessSpawner,
-                runtime: TokioRuntime,
-                moved_resource_type: MovedResourceType::Copied,
-                ownership_model: VmmOwnershipModel::Downgraded {
-                    uid: TestOptions::get().await.jailer_uid,
-                    gid: TestOptions::get().await.jailer_gid,
-                },
-                track_dirty_pages: Some(false),
-                resume_vm: Some(true),
-                network_overrides: Vec::new(),
-            },
-        )
-        .await
-        .unwrap()
+    PrepareVmFromSnapshotOptions {
+        executor,
+        process_spawner: DirectProcessSpawner,
+        runtime: TokioRuntime,
+        moved_resource_type: MovedResourceType::Copied,
+        ownership_model: VmmOwnershipModel::Downgraded {
+            uid: TestOptions::get().await.jailer_uid,
+            gid: TestOptions::get().await.jailer_gid,
+        },
+        track_dirty_pages: Some(false),
+        resume_vm: Some(true),
+        network_overrides: Vec::new(),
+        preallocate_mem_file: is_jailed,
+    }
 }
 
 async fn restore_snapshot_vm(mut new_vm: TestVm) {
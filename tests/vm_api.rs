@@ -1,12 +1,15 @@
-use std::time::Duration;
+use std::{num::NonZeroU16, time::Duration};
 
 use assert_matches::assert_matches;
 use bytes::Bytes;
 use fctools::{
     vm::{
         VmState,
-        api::{VmApi, VmApiError},
-        models::{StartBalloonFreePageHintingRun, UpdateBalloonDevice, UpdateBalloonStatistics},
+        api::{VmApi, VmApiError, parse_api_error},
+        models::{
+            BalloonSizeMib, PatchMachineConfiguration, StartBalloonFreePageHintingRun, UpdateBalloonDevice,
+            UpdateBalloonStatistics,
+        },
     },
     vmm::{process::HyperResponseExt, resource::CreatedResourceType},
 };
@@ -58,6 +61,24 @@ fn vm_api_can_send_custom_request() {
     });
 }
 
+#[test]
+fn vm_api_can_parse_custom_request_errors() {
+    VmBuilder::new().run(|mut vm| async move {
+        let mut response = vm
+            .send_custom_api_request(
+                "/snapshot/load",
+                Request::builder().method("PUT").body(Full::new(Bytes::new())).unwrap(),
+                None,
+            )
+            .await
+            .unwrap();
+        assert!(response.status().is_client_error());
+        let api_error = parse_api_error(&response.read_body_to_string().await.unwrap()).unwrap();
+        assert!(!api_error.fault_message.is_empty());
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
 #[test]
 fn vm_api_custom_requests_perform_pause_changes() {
     VmBuilder::new().run(|mut vm| async move {
@@ -101,7 +122,7 @@ fn vm_api_can_get_balloon() {
         .run(|mut vm| async move {
             let balloon = vm.get_balloon_device().await.unwrap();
             assert_eq!(balloon.stats_polling_interval_s, Some(0));
-            assert_eq!(balloon.amount_mib, 64);
+            assert_eq!(balloon.amount_mib.get(), 64);
             assert!(!balloon.deflate_on_oom);
             shutdown_test_vm(&mut vm).await;
         });
@@ -132,11 +153,13 @@ fn vm_api_can_update_balloon() {
     VmBuilder::new()
         .balloon_device(None, false, false)
         .run(|mut vm| async move {
-            vm.update_balloon_device(UpdateBalloonDevice { amount_mib: 50 })
-                .await
-                .unwrap();
+            vm.update_balloon_device(UpdateBalloonDevice {
+                amount_mib: BalloonSizeMib::new(50),
+            })
+            .await
+            .unwrap();
             let balloon = vm.get_balloon_device().await.unwrap();
-            assert_eq!(balloon.amount_mib, 50);
+            assert_eq!(balloon.amount_mib.get(), 50);
             shutdown_test_vm(&mut vm).await;
         });
 }
@@ -153,6 +176,23 @@ fn vm_api_can_get_balloon_statistics() {
         });
 }
 
+#[test]
+fn vm_api_rejects_balloon_statistics_when_disabled() {
+    VmBuilder::new()
+        .balloon_device(None, false, false)
+        .run(|mut vm| async move {
+            assert_matches!(
+                vm.get_balloon_statistics().await,
+                Err(VmApiError::BalloonStatisticsDisabled)
+            );
+            assert_matches!(
+                vm.enable_balloon_statistics(NonZeroU16::new(1).unwrap()).await,
+                Err(VmApiError::BalloonStatisticsDisabled)
+            );
+            shutdown_test_vm(&mut vm).await;
+        });
+}
+
 #[test]
 fn vm_api_can_update_balloon_statistics() {
     VmBuilder::new()
@@ -178,6 +218,71 @@ fn vm_api_can_get_machine_configuration() {
     });
 }
 
+#[test]
+fn vm_api_can_patch_machine_configuration_before_start() {
+    VmBuilder::new()
+        .pre_start_hook(|vm| {
+            Box::pin(async {
+                assert_matches!(
+                    vm.patch_machine_configuration(PatchMachineConfiguration {
+                        mem_size_mib: Some(0),
+                        ..Default::default()
+                    })
+                    .await,
+                    Err(VmApiError::InvalidConfiguration(_))
+                );
+                vm.patch_machine_configuration(PatchMachineConfiguration {
+                    mem_size_mib: Some(256),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            })
+        })
+        .run(|mut vm| async move {
+            let machine_configuration = vm.get_machine_configuration().await.unwrap();
+            assert_eq!(machine_configuration.vcpu_count, 1);
+            assert_eq!(machine_configuration.mem_size_mib, 256);
+            assert_matches!(
+                vm.patch_machine_configuration(PatchMachineConfiguration::default())
+                    .await,
+                Err(VmApiError::StateCheckError(_))
+            );
+            shutdown_test_vm(&mut vm).await;
+        });
+}
+
+#[test]
+fn vm_api_handle_can_be_used_concurrently() {
+    VmBuilder::new().run(|mut vm| async move {
+        let api_handle = vm.get_api_handle().await.unwrap();
+        let cloned_api_handle = api_handle.clone();
+
+        let (machine_configuration, firecracker_version, pause_result) = futures_util::join!(
+            api_handle.get_machine_configuration(),
+            cloned_api_handle.get_firecracker_version(),
+            vm.pause()
+        );
+        assert_eq!(machine_configuration.unwrap().mem_size_mib, 128);
+        assert!(firecracker_version.unwrap().contains("1"));
+        pause_result.unwrap();
+
+        assert!(api_handle.get_info().await.unwrap().is_paused);
+        vm.resume().await.unwrap();
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
+#[test]
+fn vm_api_can_get_drives() {
+    VmBuilder::new().run(|mut vm| async move {
+        let drives = vm.get_drives().await.unwrap();
+        assert_eq!(drives.len(), vm.get_configured_drives().len());
+        assert!(drives.iter().any(|drive| drive.is_root_device));
+        shutdown_test_vm(&mut vm).await;
+    });
+}
+
 #[test]
 fn vm_api_can_get_firecracker_version() {
     VmBuilder::new().run(|mut vm| async move {